@@ -0,0 +1,246 @@
+//! Derive macros for [eyros](https://docs.rs/eyros). Enable the `derive` feature of eyros and
+//! use `#[derive(eyros::Point)]` and `#[derive(eyros::Value)]` instead of depending on this
+//! crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2,Span};
+use quote::{quote,format_ident};
+use syn::{parse_macro_input,DeriveInput,Data,Fields,Type,PathArguments,GenericArgument,
+  Member,Index,Error};
+
+/// Implement `eyros::Point` and `eyros::TuplePoint` for a struct of 2 to 8 `Coord<X>` fields.
+/// Points are stored as the tuple of their fields, in declaration order, in a `Tree{N}`,
+/// so open the database with `eyros::open_from_path_of` or use `eyros::TreeOf<P,V>`
+/// as the tree type. The codec (`ToBytes`, `FromBytes` and `CountBytes`) is also derived.
+#[proc_macro_derive(Point)]
+pub fn derive_point(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  point(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Implement `eyros::Value` for a struct, along with its codec.
+/// Mark the field that uniquely identifies a value with `#[id]`, which is used to delete rows.
+/// Without an `#[id]` field, the whole value is its own id.
+#[proc_macro_derive(Value, attributes(id))]
+pub fn derive_value(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  value(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+struct Field<'a> {
+  member: Member,
+  ty: &'a Type,
+  is_id: bool,
+}
+
+fn fields(input: &DeriveInput) -> Result<(Vec<Field<'_>>,bool),Error> {
+  let data = match &input.data {
+    Data::Struct(data) => data,
+    _ => return Err(Error::new_spanned(&input.ident, "eyros derives only support structs")),
+  };
+  let named = matches![data.fields, Fields::Named(_)];
+  let fields = data.fields.iter().enumerate().map(|(i,f)| Field {
+    member: match &f.ident {
+      Some(ident) => Member::Named(ident.clone()),
+      None => Member::Unnamed(Index::from(i)),
+    },
+    ty: &f.ty,
+    is_id: f.attrs.iter().any(|a| a.path().is_ident("id")),
+  }).collect();
+  Ok((fields,named))
+}
+
+// return the X of a field typed as Coord<X>
+fn coord_scalar(ty: &Type) -> Option<&Type> {
+  let segment = match ty {
+    Type::Path(p) if p.qself.is_none() => p.path.segments.last()?,
+    _ => return None,
+  };
+  if segment.ident != "Coord" { return None }
+  match &segment.arguments {
+    PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+      GenericArgument::Type(x) => Some(x),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn construct(fields: &[Field], named: bool, values: &[TokenStream2]) -> TokenStream2 {
+  let members = fields.iter().map(|f| &f.member);
+  if named {
+    quote! { Self { #(#members: #values),* } }
+  } else {
+    quote! { Self(#(#values),*) }
+  }
+}
+
+fn codec(input: &DeriveInput, fields: &[Field], named: bool) -> TokenStream2 {
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let members = fields.iter().map(|f| &f.member).collect::<Vec<_>>();
+  let tys = fields.iter().map(|f| f.ty).collect::<Vec<_>>();
+  let vars = (0..fields.len()).map(|i| format_ident!("x{}", i)).collect::<Vec<_>>();
+  let values = vars.iter().map(|v| quote! { #v }).collect::<Vec<_>>();
+  let ctor = construct(fields, named, &values);
+  quote! {
+    impl #impl_generics ::eyros::desert::ToBytes for #name #ty_generics #where_clause {
+      fn to_bytes(&self) -> Result<Vec<u8>,::eyros::Error> {
+        let mut buf = vec![0u8;::eyros::desert::CountBytes::count_bytes(self)];
+        ::eyros::desert::ToBytes::write_bytes(self, &mut buf)?;
+        Ok(buf)
+      }
+      fn write_bytes(&self, buf: &mut [u8]) -> Result<usize,::eyros::Error> {
+        let mut offset = 0;
+        #(offset += ::eyros::desert::ToBytes::write_bytes(&self.#members, &mut buf[offset..])?;)*
+        Ok(offset)
+      }
+    }
+    impl #impl_generics ::eyros::desert::FromBytes for #name #ty_generics #where_clause {
+      fn from_bytes(src: &[u8]) -> Result<(usize,Self),::eyros::Error> {
+        let mut offset = 0;
+        #(
+          let (size,#vars) = <#tys as ::eyros::desert::FromBytes>::from_bytes(&src[offset..])?;
+          offset += size;
+        )*
+        Ok((offset,#ctor))
+      }
+    }
+    impl #impl_generics ::eyros::desert::CountBytes for #name #ty_generics #where_clause {
+      fn count_bytes(&self) -> usize {
+        0 #(+ ::eyros::desert::CountBytes::count_bytes(&self.#members))*
+      }
+      fn count_from_bytes(src: &[u8]) -> Result<usize,::eyros::Error> {
+        let mut offset = 0;
+        #(offset += <#tys as ::eyros::desert::CountBytes>::count_from_bytes(&src[offset..])?;)*
+        Ok(offset)
+      }
+    }
+  }
+}
+
+fn point(input: &DeriveInput) -> Result<TokenStream2,Error> {
+  let (fields,named) = fields(input)?;
+  if fields.len() < 2 || fields.len() > 8 {
+    return Err(Error::new_spanned(&input.ident,
+      "eyros::Point can only be derived for structs with 2 to 8 fields"));
+  }
+  let mut scalars = vec![];
+  for f in fields.iter() {
+    match coord_scalar(f.ty) {
+      Some(x) => scalars.push(x),
+      None => return Err(Error::new_spanned(f.ty, "eyros::Point fields must be Coord<X>")),
+    }
+  }
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let n = fields.len();
+  let tree = format_ident!("Tree{}", n);
+  let members = fields.iter().map(|f| &f.member).collect::<Vec<_>>();
+  let tys = fields.iter().map(|f| f.ty).collect::<Vec<_>>();
+  let values = (0..n).map(|i| {
+    let i = Index::from(i);
+    quote! { tuple.#i }
+  }).collect::<Vec<_>>();
+  let ctor = construct(&fields, named, &values);
+  let codec = codec(input, &fields, named);
+  Ok(quote! {
+    impl #impl_generics ::eyros::TuplePoint for #name #ty_generics #where_clause {
+      type Tuple = (#(#tys),*);
+      type Tree<V: ::eyros::Value> = ::eyros::#tree<#(#scalars),*,V>;
+      fn to_tuple(&self) -> Self::Tuple {
+        (#(self.#members.clone()),*)
+      }
+      fn from_tuple(tuple: Self::Tuple) -> Self {
+        #ctor
+      }
+    }
+    impl #impl_generics ::eyros::Point for #name #ty_generics #where_clause {
+      type Bounds = <(#(#tys),*) as ::eyros::Point>::Bounds;
+      const DIMENSIONS: Option<usize> = Some(#n);
+      fn to_bounds(&self) -> Result<Self::Bounds,::eyros::Error> {
+        ::eyros::Point::to_bounds(&::eyros::TuplePoint::to_tuple(self))
+      }
+      fn from_bounds(bounds: &Self::Bounds) -> Self {
+        ::eyros::TuplePoint::from_tuple(<(#(#tys),*) as ::eyros::Point>::from_bounds(bounds))
+      }
+      fn check(&self) -> Result<(),::eyros::Error> {
+        ::eyros::Point::check(&::eyros::TuplePoint::to_tuple(self))
+      }
+      fn check_total_order(&self) -> Result<(),::eyros::Error> {
+        ::eyros::Point::check_total_order(&::eyros::TuplePoint::to_tuple(self))
+      }
+      fn cmp_dim(&self, other: &Self, dim: usize) -> Option<std::cmp::Ordering> {
+        ::eyros::Point::cmp_dim(
+          &::eyros::TuplePoint::to_tuple(self),
+          &::eyros::TuplePoint::to_tuple(other),
+          dim
+        )
+      }
+      fn dimensions(&self) -> usize {
+        #n
+      }
+      fn union(&self, other: &Self) -> Option<Self> {
+        ::eyros::Point::union(
+          &::eyros::TuplePoint::to_tuple(self),
+          &::eyros::TuplePoint::to_tuple(other)
+        ).map(::eyros::TuplePoint::from_tuple)
+      }
+      fn count_point_bytes(&self) -> usize {
+        ::eyros::desert::CountBytes::count_bytes(self)
+      }
+      fn write_point_bytes(&self, buf: &mut [u8]) -> Result<usize,::eyros::Error> {
+        ::eyros::desert::ToBytes::write_bytes(self, buf)
+      }
+      fn point_from_bytes(src: &[u8]) -> Result<(usize,Self),::eyros::Error> {
+        <Self as ::eyros::desert::FromBytes>::from_bytes(src)
+      }
+      fn scalar_kinds(dimensions: usize) -> Vec<::eyros::ScalarKind> {
+        <(#(#tys),*) as ::eyros::Point>::scalar_kinds(dimensions)
+      }
+      fn write_export(&self, buf: &mut Vec<u8>) -> Result<(),::eyros::Error> {
+        ::eyros::Point::write_export(&::eyros::TuplePoint::to_tuple(self), buf)
+      }
+      fn read_export(src: &[u8], dimensions: usize) -> Result<(usize,Self),::eyros::Error> {
+        let (size,tuple) = <(#(#tys),*) as ::eyros::Point>::read_export(src, dimensions)?;
+        Ok((size,::eyros::TuplePoint::from_tuple(tuple)))
+      }
+    }
+    impl #impl_generics ::eyros::Overlap for #name #ty_generics #where_clause {
+      fn overlap(&self, other: &Self) -> bool {
+        ::eyros::Overlap::overlap(
+          &::eyros::TuplePoint::to_tuple(self),
+          &::eyros::TuplePoint::to_tuple(other)
+        )
+      }
+    }
+    #codec
+  })
+}
+
+fn value(input: &DeriveInput) -> Result<TokenStream2,Error> {
+  let (fields,named) = fields(input)?;
+  let ids = fields.iter().filter(|f| f.is_id).collect::<Vec<_>>();
+  if ids.len() > 1 {
+    return Err(Error::new(Span::call_site(), "only one field can be marked with #[id]"));
+  }
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let (id_ty,get_id) = match ids.first() {
+    Some(f) => {
+      let (ty,member) = (f.ty,&f.member);
+      (quote! { #ty }, quote! { self.#member.clone() })
+    },
+    None => (quote! { Self }, quote! { self.clone() }),
+  };
+  let codec = codec(input, &fields, named);
+  Ok(quote! {
+    impl #impl_generics ::eyros::Value for #name #ty_generics #where_clause {
+      type Id = #id_ty;
+      fn get_id(&self) -> Self::Id {
+        #get_id
+      }
+    }
+    #codec
+  })
+}
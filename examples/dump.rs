@@ -0,0 +1,23 @@
+use eyros::{DB,Coord};
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u64;
+type E = Box<dyn std::error::Error+Sync+Send>;
+
+// usage: cargo run --example dump -- DBDIR [json|dot] [TREE_ID]
+#[async_std::main]
+async fn main() -> Result<(),E> {
+  let args: Vec<String> = std::env::args().collect();
+  let db: DB<_,_,P,V> = eyros::open_from_path2(
+    &std::path::PathBuf::from(args[1].clone())
+  ).await?;
+  let dump = match args.get(3) {
+    Some(id) => db.dump_tree(id.parse()?).await?,
+    None => db.dump().await?,
+  };
+  match args.get(2).map(|s| s.as_str()) {
+    Some("dot") => print!["{}", dump.to_dot()],
+    _ => println!["{}", dump.to_json()],
+  }
+  Ok(())
+}
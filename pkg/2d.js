@@ -1,6 +1,7 @@
 var api = require('./lib/2d-api.js')
 var wrapStorage = require('./lib/wrap-storage.js')
 var setWasm = require('./lib/set-wasm.js')
+var getOpen = require('./lib/get-open.js')
 
 module.exports = function (opts) {
   if (!opts.storage) throw new Error('opts.storage not provided')
@@ -8,7 +9,9 @@ module.exports = function (opts) {
     if (typeof opts.getId === 'function') {
       api.set_getid(opts.getId)
     }
-    return api.open_f32_f32(Object.assign({}, opts, {
+    var types = opts.types || ['f32','f32']
+    var open = getOpen(api, 2, types)
+    return open(Object.assign({}, opts, {
       storage: wrapStorage(opts.storage),
       remove: opts.remove || function () {}
     }))
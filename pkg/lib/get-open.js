@@ -0,0 +1,12 @@
+// find the open function for opts.types or fail with the type combinations that are supported
+module.exports = function (api, n, types) {
+  var open = api['open_' + types.join('_')]
+  if (typeof open === 'function') return open
+  var supported = Object.keys(api).filter(function (key) {
+    return /^open_/.test(key) && key.split('_').length === n + 1
+  }).map(function (key) {
+    return '[' + key.split('_').slice(1).join(',') + ']'
+  })
+  throw new Error('unsupported types for ' + n + 'd: [' + types.join(',') + ']. '
+    + 'supported types: ' + supported.join(' '))
+}
@@ -0,0 +1,71 @@
+const RAM = require('random-access-memory')
+const eyros = require('../2d')
+const fs = require('fs')
+const test = require('tape')
+
+test('optimize, fields, and batch reports', async function (t) {
+  t.plan(13)
+  var db = await eyros({
+    storage: RAM,
+    wasmSource: fs.readFileSync(require.resolve('../2d.wasm')),
+    extRecords: 200,
+    inlineMaxBytes: 5000,
+    rebuildDepth: 3,
+  })
+  t.deepEqual(await db.fields(), {
+    branchFactor: 6,
+    maxDepth: 8,
+    maxRecords: 20000,
+    extRecords: 200,
+    inline: 50,
+    inlineMaxBytes: 5000,
+    treeCacheSize: 1000,
+    treeCacheBytes: 0,
+    maxPendingBytes: 0,
+    rebuildDepth: 3,
+    totalOrder: false,
+  }, 'fields include open options')
+
+  var batch = []
+  for (var i = 0; i < 2000; i++) {
+    batch.push({
+      type: 'insert',
+      point: [Math.random()*2-1,Math.random()*2-1],
+      value: Uint8Array.from([i%256,Math.floor(i/256)])
+    })
+  }
+  var report = await db.batch(batch.slice(0,1000))
+  t.equal(report.inserted, 1000, 'first report inserted')
+  t.equal(report.deleted, 0, 'first report deleted')
+  t.deepEqual(report.missing, [], 'first report missing')
+  t.ok(report.treesCreated.length > 0, 'first batch creates trees')
+  t.deepEqual(report.treesRemoved, [], 'first batch removes no trees')
+
+  var deletes = batch.slice(0,10).map(row => ({ type: 'delete', point: row.point, id: row.value }))
+  deletes.push({ type: 'delete', point: [0.5,0.5], id: Uint8Array.from([9,9,9]) })
+  report = await db.batch(deletes.concat(batch.slice(1000)), { errorIfMissing: false })
+  t.equal(report.inserted, 1000, 'second report inserted')
+  t.equal(report.deleted, 10, 'second report deleted')
+  t.deepEqual(report.missing, [Uint8Array.from([9,9,9])], 'second report missing')
+  t.ok(report.treesRemoved.length > 0, 'merged trees are removed')
+  await db.sync()
+
+  await db.optimize()
+  await db.optimize(2)
+  var rows = await collect(await db.query([-1,-1,+1,+1]))
+  t.equal(rows.length, 1990, 'all rows after optimize')
+  var expected = batch.slice(10).map(row => row.value).sort(Buffer.compare)
+  t.deepEqual(rows.map(row => row[1]).sort(Buffer.compare), expected, 'same rows after optimize')
+  rows = await collect(await db.query([-0.5,-0.5,+0.5,+0.5]))
+  t.equal(rows.length, batch.slice(10).filter(row => {
+    return Math.abs(row.point[0]) <= 0.5 && Math.abs(row.point[1]) <= 0.5
+  }).length, 'bbox query after optimize')
+})
+
+async function collect (iter) {
+  var row, rows = []
+  while (row = await iter.next()) {
+    rows.push(row)
+  }
+  return rows
+}
@@ -0,0 +1,47 @@
+const RAM = require('random-access-memory')
+const eyros = require('../2d')
+const fs = require('fs')
+const test = require('tape')
+
+test('export and import', async function (t) {
+  t.plan(7)
+  var db = await eyros({
+    storage: RAM,
+    wasmSource: fs.readFileSync(require.resolve('../2d.wasm')),
+  })
+  var batch = []
+  for (var i = 0; i < 500; i++) {
+    batch.push({
+      type: 'insert',
+      point: i % 2 === 0 ? [Math.random()*2-1,Math.random()*2-1] : [[-0.5,0.5],Math.random()],
+      value: Uint8Array.from([i%256,Math.floor(i/256)])
+    })
+  }
+  await db.batch(batch)
+  await db.sync()
+  var rows = await collect(db)
+  var data = await db.export()
+  t.ok(data instanceof Uint8Array, 'export returns bytes')
+
+  // importing into the same database inserts every row a second time
+  var report = await db.import(data)
+  t.equal(report.rows, 500, 'imported rows')
+  t.equal(report.dimensions, 2, 'exported dimensions')
+  t.deepEqual(report.scalars, ['f32','f32'], 'exported scalars')
+  t.deepEqual(await collect(db), rows.concat(rows).sort(), 'rows inserted twice')
+
+  try {
+    await db.import(data.subarray(0, data.length - 20))
+    t.fail('truncated import should fail')
+  } catch (err) {
+    t.ok(err, 'truncated import fails')
+  }
+  t.equal((await collect(db)).length, 1000, 'no rows from a failed import')
+})
+
+async function collect (db) {
+  var q = await db.query([-1,-1,+1,+1])
+  var rows = [], row
+  while (row = await q.next()) rows.push(JSON.stringify([row[0],Array.from(row[1])]))
+  return rows.sort()
+}
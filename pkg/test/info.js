@@ -0,0 +1,47 @@
+const RAM = require('random-access-memory')
+const eyros = require('../2d')
+const fs = require('fs')
+const test = require('tape')
+
+test('info, verify, and stats', async function (t) {
+  t.plan(14)
+  var db = await eyros({
+    storage: RAM,
+    wasmSource: fs.readFileSync(require.resolve('../2d.wasm')),
+    extRecords: 100,
+  })
+  var info = await db.info()
+  t.deepEqual(info.roots, [], 'empty database has no roots')
+  t.equal(info.bbox, null, 'empty database has no bbox')
+
+  var batch = []
+  for (var i = 0; i < 500; i++) {
+    batch.push({
+      type: 'insert',
+      point: [Math.random()*2-1,Math.random()*2-1],
+      value: Uint8Array.from([i%256,Math.floor(i/256)])
+    })
+  }
+  await db.batch(batch)
+  await db.sync()
+
+  info = await db.info()
+  t.equal(info.records, 500, 'info records')
+  t.equal(info.roots.length, 1, 'one root')
+  t.equal(info.roots[0].records, 500, 'root records')
+  t.ok(info.trees > 1, 'external trees are counted')
+  t.equal(info.depths[0], 1, 'one tree at depth 0')
+  t.equal(info.inlineRecords + info.externalRecords, 500, 'inline and external records')
+  t.ok(info.bytes > 0, 'info bytes')
+  t.ok(info.bbox[0] >= -1 && info.bbox[2] <= 1 && info.bbox[0] <= info.bbox[2], 'info bbox')
+
+  var v = await db.verify()
+  t.ok(v.ok, 'verify ok')
+  t.deepEqual([v.trees,v.records,v.problems], [info.trees,500,[]], 'verify counts')
+
+  var q = await db.query([-1,-1,+1,+1])
+  while (await q.next()) {}
+  var stats = await db.stats()
+  t.equal(stats.queries, 1, 'stats queries')
+  t.equal(stats.rowsReturned, 500, 'stats rows returned')
+})
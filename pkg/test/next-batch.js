@@ -0,0 +1,59 @@
+const RAM = require('random-access-memory')
+const eyros = require('../2d')
+const fs = require('fs')
+const test = require('tape')
+
+test('next batch', async function (t) {
+  t.plan(7)
+  var db = await eyros({
+    storage: RAM,
+    wasmSource: fs.readFileSync(require.resolve('../2d.wasm'))
+  })
+  var batch = []
+  for (var i = 0; i < 250; i++) {
+    var x = Math.round(Math.random()*200-100)/4
+    var y = Math.round(Math.random()*200-100)/4
+    batch.push({
+      type: 'insert',
+      point: [i%3 === 0 ? [x,x+0.5] : x, y],
+      value: Uint8Array.from(i%2 === 0 ? [i%256] : [i%256,Math.floor(i/256),7])
+    })
+  }
+  await db.batch(batch)
+  var expected = batch.map(row => [row.point,row.value]).sort(cmp)
+
+  var q = await db.query([-100,-100,+100,+100])
+  try {
+    await q.nextBatch(0)
+    t.fail('nextBatch(0) should reject')
+  } catch (err) {
+    t.ok(/n > 0/.test(err.message), 'nextBatch(0) rejects')
+  }
+  var first = await q.next()
+  var rows = [first.slice(0,2)], sizes = []
+  var b
+  while (b = await q.nextBatch(100)) {
+    sizes.push(b.length)
+    for (var j = 0; j < b.length; j++) {
+      var point = []
+      for (var d = 0; d < 2; d++) {
+        var k = (j*2+d)*2
+        point.push(b.mask[j*2+d] ? [b.coords[k],b.coords[k+1]] : b.coords[k])
+        if (!b.mask[j*2+d] && b.coords[k] !== b.coords[k+1]) t.fail('scalar min and max differ')
+      }
+      rows.push([point,b.values.subarray(b.offsets[j],b.offsets[j+1])])
+    }
+  }
+  t.deepEqual(sizes, [100,100,49], 'batch sizes')
+  t.ok(b === null, 'finished iterator yields null')
+  t.ok(await q.nextBatch(100) === null, 'null again after finishing')
+  t.notOk(await q.next(), 'next is finished too')
+  t.equal(rows.length, 250, 'all rows returned')
+  t.deepEqual(rows.map(r => [r[0],Uint8Array.from(r[1])]).sort(cmp), expected,
+    'batches match inserted rows')
+})
+
+function cmp (a, b) {
+  if (a[1].length !== b[1].length) return a[1].length - b[1].length
+  return Buffer.compare(a[1], b[1])
+}
@@ -0,0 +1,39 @@
+const RAM = require('random-access-memory')
+const eyros = require('../2d')
+const fs = require('fs')
+const test = require('tape')
+
+test('open intervals', async function (t) {
+  t.plan(4)
+  var db = await eyros({
+    storage: RAM,
+    wasmSource: fs.readFileSync(require.resolve('../2d.wasm'))
+  })
+  await db.batch([
+    { type: 'insert', point: [[5,null],+2], value: Uint8Array.from([1]) },
+    { type: 'insert', point: [[-Infinity,-5],+4], value: Uint8Array.from([2]) },
+    { type: 'insert', point: [[null,null],[-3,Infinity]], value: Uint8Array.from([3]) },
+    { type: 'insert', point: [+1,+2], value: Uint8Array.from([4]) },
+  ])
+  t.deepEqual(await collect(await db.query([100,0,200,10])), [
+    [[[5,Infinity],2],[1]],
+    [[[-Infinity,Infinity],[-3,Infinity]],[3]],
+  ], 'from and all')
+  t.deepEqual(await collect(await db.query([-200,3,-100,10])), [
+    [[[-Infinity,-5],4],[2]],
+    [[[-Infinity,Infinity],[-3,Infinity]],[3]],
+  ], 'to and all')
+  t.deepEqual(await collect(await db.query([0,-10,2,-5])), [], 'no results')
+  var q = await db.query([4,1,6,3])
+  var b = await q.nextBatch(10)
+  t.deepEqual(Array.from(b.coords).sort(), [-3,2,2,5,Infinity,Infinity,-Infinity,Infinity].sort(),
+    'batch coords use infinity for open ends')
+})
+
+async function collect (q) {
+  var rows = [], row
+  while (row = await q.next()) {
+    rows.push([row[0],Array.from(row[1])])
+  }
+  return rows.sort((a,b) => a[1][0] - b[1][0])
+}
@@ -0,0 +1,85 @@
+const RAM = require('random-access-memory')
+const eyros = require('../2d')
+const fs = require('fs')
+const test = require('tape')
+
+var wasmSource = fs.readFileSync(require.resolve('../2d.wasm'))
+
+test('write and read errors', async function (t) {
+  t.plan(4)
+  var files = {}
+  var fail = { write: false, read: false }
+  function storage (name) {
+    if (!files[name]) files[name] = new RAM
+    var r = files[name]
+    return {
+      get length () { return r.length },
+      write: function (offset, buf, cb) {
+        if (fail.write) cb(new Error('write failed'))
+        else r.write(offset, buf, cb)
+      },
+      read: function (offset, length, cb) {
+        if (fail.read) throw new Error('read failed')
+        r.read(offset, length, cb)
+      },
+      del: function (cb) { r.del(cb) },
+    }
+  }
+  var batch = []
+  for (var i = 0; i < 500; i++) {
+    batch.push({
+      type: 'insert',
+      point: [Math.random()*2-1,Math.random()*2-1],
+      value: Uint8Array.from([i%256,Math.floor(i/256)])
+    })
+  }
+  // a small tree cache makes the query below read trees from storage
+  var db = await eyros({ storage, wasmSource, treeCacheSize: 1 })
+  await db.batch(batch.slice(0,400))
+  await db.sync()
+  t.pass('sync succeeds without errors')
+
+  fail.read = true
+  try {
+    var q = await db.query([-1,-1,+1,+1])
+    while (await q.next()) {}
+    t.fail('query should reject')
+  } catch (err) {
+    t.ok(/read failed/.test(String(err)), 'query rejects with the read error')
+  }
+  fail.read = false
+
+  fail.write = true
+  await db.batch(batch.slice(400))
+  try {
+    await db.sync()
+    t.fail('sync should reject')
+  } catch (err) {
+    t.ok(/write failed/.test(String(err)), 'sync rejects with the write error')
+  }
+  fail.write = false
+  await db.sync()
+  var rows = await collect(await db.query([-1,-1,+1,+1]))
+  t.equal(rows.length, 500, 'sync and query work again once storage recovers')
+})
+
+test('storage function throws', async function (t) {
+  t.plan(1)
+  try {
+    await eyros({
+      storage: function (name) { throw new Error('cannot open ' + name) },
+      wasmSource
+    })
+    t.fail('open should reject')
+  } catch (err) {
+    t.ok(/cannot open meta/.test(String(err)), 'open rejects with the storage error')
+  }
+})
+
+async function collect (iter) {
+  var row, rows = []
+  while (row = await iter.next()) {
+    rows.push(row)
+  }
+  return rows
+}
@@ -0,0 +1,82 @@
+const RAM = require('random-access-memory')
+const eyros = {
+  2: require('../2d'),
+  3: require('../3d'),
+}
+const fs = require('fs')
+const test = require('tape')
+
+test('f64 types', async function (t) {
+  t.plan(3)
+  var db = await eyros[2]({
+    storage: RAM,
+    types: ['f64','f64'],
+    wasmSource: fs.readFileSync(require.resolve('../2d.wasm'))
+  })
+  await db.batch([
+    { type:'insert', point:[-122.41940001,37.77490001], value: Uint8Array.from([97]) },
+    { type:'insert', point:[-122.41940002,37.77490002], value: Uint8Array.from([98]) },
+    { type:'insert', point:[[-122.5,-122.4],37.7749], value: Uint8Array.from([99]) },
+  ])
+  var rows = await collect(await db.query([-122.419400015,37.7749,-122.4194,37.7750]))
+  t.deepEqual(rows.map(row => row.slice(0,2)).sort(cmp), [
+    [ [-122.41940001,37.77490001], Uint8Array.from([97]) ],
+    [ [[-122.5,-122.4],37.7749], Uint8Array.from([99]) ],
+  ], 'coordinates keep f64 precision')
+  try {
+    await eyros[2]({
+      storage: RAM,
+      types: ['u8','u8'],
+      wasmSource: fs.readFileSync(require.resolve('../2d.wasm'))
+    })
+    t.fail('unsupported types should fail')
+  } catch (err) {
+    t.ok(/unsupported types/.test(err.message), 'unsupported types rejected')
+    t.ok(/\[f32,f32\]/.test(err.message) && /\[f64,f64\]/.test(err.message),
+      'error lists supported types')
+  }
+})
+
+test('f64,f64,u32 types', async function (t) {
+  t.plan(3)
+  var db = await eyros[3]({
+    storage: RAM,
+    types: ['f64','f64','u32'],
+    wasmSource: fs.readFileSync(require.resolve('../3d.wasm'))
+  })
+  await db.batch([
+    { type:'insert', point:[+1.5,+2.5,1600000000], value: Uint8Array.from([97]) },
+    { type:'insert', point:[+1.5,+2.5,1600000001], value: Uint8Array.from([98]) },
+    { type:'insert', point:[+1.5,+2.5,[1599999000,1600000000]], value: Uint8Array.from([99]) },
+  ])
+  var rows = await collect(await db.query([0,0,1600000001,5,5,1700000000]))
+  t.deepEqual(rows.map(row => row.slice(0,2)).sort(cmp), [
+    [ [+1.5,+2.5,1600000001], Uint8Array.from([98]) ],
+  ], 'u32 timestamps are exact')
+  try {
+    await db.batch([
+      { type:'insert', point:[+1.5,+2.5,1600000000.5], value: Uint8Array.from([100]) },
+    ])
+    t.fail('fractional u32 coordinate should fail')
+  } catch (err) {
+    t.ok(/u32/.test(err.message), 'fractional u32 coordinate rejected')
+  }
+  try {
+    await db.batch([
+      { type:'insert', point:[+1.5,+2.5,-1], value: Uint8Array.from([100]) },
+    ])
+    t.fail('negative u32 coordinate should fail')
+  } catch (err) {
+    t.ok(/u32/.test(err.message), 'negative u32 coordinate rejected')
+  }
+})
+
+function cmp (a, b) { return Buffer.compare(a[1], b[1]) }
+
+async function collect (iter) {
+  var row, rows = []
+  while (row = await iter.next()) {
+    rows.push(row)
+  }
+  return rows
+}
@@ -1,3 +1,5 @@
+use crate::{Value,TreeId};
+
 pub struct BatchFields {
   pub rebuild_depth: usize,
   pub error_if_missing: bool,
@@ -23,6 +25,9 @@ impl BatchOptions {
     self.fields.rebuild_depth = depth;
     self
   }
+  /// Fail the batch with an error that lists the ids of `Row::Delete` records that were not
+  /// found, which is the default. Set to `false` to apply the batch and receive the missing
+  /// ids as `Value::Id`s in `BatchReport::missing` instead.
   pub fn error_if_missing(mut self, x: bool) -> Self {
     self.fields.error_if_missing = x;
     self
@@ -32,3 +37,49 @@ impl BatchOptions {
 impl Default for BatchOptions {
   fn default() -> Self { Self::new() }
 }
+
+/// Summary of the changes made by a batch, returned from `DB::batch()` and related methods.
+#[derive(Debug,Clone)]
+pub struct BatchReport<V> where V: Value {
+  /// Number of `Row::Insert` records written.
+  pub inserted: usize,
+  /// Number of records removed by `Row::Delete`.
+  pub deleted: usize,
+  /// Ids from `Row::Delete` that were not found in the database.
+  /// This is only populated when `error_if_missing` is `false`,
+  /// otherwise missing ids produce an error.
+  pub missing: Vec<V::Id>,
+  /// Trees written for the first time by this batch.
+  pub trees_created: Vec<TreeId>,
+  /// Existing trees rewritten to remove records, by the id they were written under. Trees that
+  /// were already synced move to a new id, and their old id is listed in `trees_removed`.
+  pub trees_updated: Vec<TreeId>,
+  /// Trees merged into new trees or moved to a new id, scheduled for removal.
+  pub trees_removed: Vec<TreeId>,
+}
+
+impl<V> BatchReport<V> where V: Value {
+  pub fn new() -> Self {
+    Self {
+      inserted: 0,
+      deleted: 0,
+      missing: vec![],
+      trees_created: vec![],
+      trees_updated: vec![],
+      trees_removed: vec![],
+    }
+  }
+  /// Add the counts and tree ids from `other` to this report.
+  pub fn extend(&mut self, other: BatchReport<V>) {
+    self.inserted += other.inserted;
+    self.deleted += other.deleted;
+    self.missing.extend(other.missing);
+    self.trees_created.extend(other.trees_created);
+    self.trees_updated.extend(other.trees_updated);
+    self.trees_removed.extend(other.trees_removed);
+  }
+}
+
+impl<V> Default for BatchReport<V> where V: Value {
+  fn default() -> Self { Self::new() }
+}
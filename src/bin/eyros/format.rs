@@ -0,0 +1,179 @@
+use eyros::{Coord,Point,Scalar,Error};
+use crate::json::Json;
+use std::fmt::Display;
+use std::str::FromStr;
+
+pub trait CliScalar: Scalar+Copy+Display+FromStr {
+  const LOWEST: Self;
+  const HIGHEST: Self;
+}
+
+macro_rules! impl_cli_scalar {
+  ($($T:ty),+) => {
+    $(impl CliScalar for $T {
+      const LOWEST: Self = <$T>::MIN;
+      const HIGHEST: Self = <$T>::MAX;
+    })+
+  }
+}
+impl_cli_scalar![f32,f64,i32,i64,u32,u64];
+
+/// Points that can be built from a list of coordinates, one per dimension.
+pub trait CliPoint<X>: Point where X: CliScalar {
+  fn from_coords(coords: Vec<Coord<X>>) -> Self;
+  fn to_coords(&self) -> Vec<Coord<X>>;
+}
+
+macro_rules! rep { ($_i:tt,$t:ty) => { $t } }
+
+macro_rules! impl_cli_point {
+  ($($i:tt),+) => {
+    impl<X> CliPoint<X> for ($(rep![$i,Coord<X>]),+) where X: CliScalar {
+      fn from_coords(coords: Vec<Coord<X>>) -> Self {
+        ($(coords[$i].clone()),+)
+      }
+      fn to_coords(&self) -> Vec<Coord<X>> {
+        vec![$(self.$i.clone()),+]
+      }
+    }
+  }
+}
+#[cfg(feature="2d")] impl_cli_point![0,1];
+#[cfg(feature="3d")] impl_cli_point![0,1,2];
+#[cfg(feature="4d")] impl_cli_point![0,1,2,3];
+#[cfg(feature="5d")] impl_cli_point![0,1,2,3,4];
+#[cfg(feature="6d")] impl_cli_point![0,1,2,3,4,5];
+#[cfg(feature="7d")] impl_cli_point![0,1,2,3,4,5,6];
+#[cfg(feature="8d")] impl_cli_point![0,1,2,3,4,5,6,7];
+
+#[cfg(feature="nd")]
+impl<X> CliPoint<X> for Vec<Coord<X>> where X: CliScalar {
+  fn from_coords(coords: Vec<Coord<X>>) -> Self {
+    coords
+  }
+  fn to_coords(&self) -> Vec<Coord<X>> {
+    self.clone()
+  }
+}
+
+/// Bounding box from a list of minimums followed by a list of maximums.
+pub fn bbox<P,X>(min: Vec<X>, max: Vec<X>) -> Result<P::Bounds,Error>
+where P: CliPoint<X>, X: CliScalar {
+  P::from_coords(min.into_iter().zip(max).map(|(a,b)| Coord::Interval(a,b)).collect()).to_bounds()
+}
+
+/// Bounding box that covers every possible point.
+pub fn bbox_all<P,X>(dims: usize) -> Result<P::Bounds,Error>
+where P: CliPoint<X>, X: CliScalar {
+  bbox::<P,X>(vec![X::LOWEST;dims], vec![X::HIGHEST;dims])
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Format {
+  Csv,
+  Ndjson,
+}
+
+impl FromStr for Format {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self,Error> {
+    match s {
+      "csv" => Ok(Format::Csv),
+      "ndjson" | "jsonl" => Ok(Format::Ndjson),
+      _ => Err(format!["unknown format {}, expected csv or ndjson", s].into()),
+    }
+  }
+}
+
+fn parse<T>(s: &str) -> Result<T,Error> where T: FromStr {
+  s.trim().parse().map_err(|_| format!["could not parse {:?}", s].into())
+}
+
+fn parse_end<X>(s: &str) -> Result<Option<X>,Error> where X: CliScalar {
+  if s.trim().is_empty() { Ok(None) } else { Ok(Some(parse(s)?)) }
+}
+
+// csv coordinates are written like rust ranges: x, min..max, min.., ..max or ..
+fn csv_coord<X>(s: &str) -> Result<Coord<X>,Error> where X: CliScalar {
+  match s.split_once("..") {
+    Some((min,max)) => Ok(Coord::from_ends(parse_end(min)?, parse_end(max)?)),
+    None => Ok(Coord::Scalar(parse(s)?)),
+  }
+}
+
+fn json_end<X>(j: &Json) -> Result<Option<X>,Error> where X: CliScalar {
+  match j {
+    Json::Null => Ok(None),
+    Json::Number(n) => Ok(Some(parse(n)?)),
+    _ => Err(format!["expected a number or null, found {:?}", j].into()),
+  }
+}
+
+// json coordinates are a number for a scalar or [min,max] with null for an unbounded end
+fn json_coord<X>(j: &Json) -> Result<Coord<X>,Error> where X: CliScalar {
+  match j {
+    Json::Number(n) => Ok(Coord::Scalar(parse(n)?)),
+    Json::Array(ends) if ends.len() == 2 => {
+      Ok(Coord::from_ends(json_end(&ends[0])?, json_end(&ends[1])?))
+    },
+    _ => Err(format!["expected a number or [min,max], found {:?}", j].into()),
+  }
+}
+
+/// Parse one line of input into a point and value, or `None` for blank lines and `#` comments.
+pub fn parse_row<P,X,V>(format: Format, dims: usize, line: &str) -> Result<Option<(P,V)>,Error>
+where P: CliPoint<X>, X: CliScalar, V: FromStr {
+  let line = line.trim();
+  if line.is_empty() || line.starts_with('#') { return Ok(None) }
+  let (coords,value) = match format {
+    Format::Csv => {
+      let mut cols = line.split(',').collect::<Vec<_>>();
+      let value = parse(cols.pop().unwrap())?;
+      (cols.into_iter().map(csv_coord).collect::<Result<Vec<_>,_>>()?,value)
+    },
+    Format::Ndjson => {
+      let row = Json::parse(line)?;
+      let coords = match row.get("point") {
+        Some(Json::Array(xs)) => xs.iter().map(json_coord).collect::<Result<Vec<_>,_>>()?,
+        _ => return Err("expected a \"point\" array".into()),
+      };
+      let value = match row.get("value") {
+        Some(Json::Number(n)) => parse(n)?,
+        Some(Json::String(s)) => parse(s)?,
+        _ => return Err("expected a \"value\"".into()),
+      };
+      (coords,value)
+    },
+  };
+  if coords.len() != dims {
+    return Err(format!["expected {} coordinates, found {}", dims, coords.len()].into());
+  }
+  Ok(Some((P::from_coords(coords),value)))
+}
+
+fn fmt_end<X>(x: Option<&X>, null: &str) -> String where X: CliScalar {
+  x.map(|x| x.to_string()).unwrap_or_else(|| null.to_string())
+}
+
+/// Write a point and value as one line of output, without a trailing newline.
+pub fn fmt_row<P,X,V>(format: Format, point: &P, value: &V) -> String
+where P: CliPoint<X>, X: CliScalar, V: Display {
+  let coords = point.to_coords();
+  match format {
+    Format::Csv => {
+      let mut cols = coords.iter().map(|c| match c {
+        Coord::Scalar(x) => x.to_string(),
+        _ => format!["{}..{}", fmt_end(c.min(), ""), fmt_end(c.max(), "")],
+      }).collect::<Vec<_>>();
+      cols.push(value.to_string());
+      cols.join(",")
+    },
+    Format::Ndjson => {
+      let cols = coords.iter().map(|c| match c {
+        Coord::Scalar(x) => x.to_string(),
+        _ => format!["[{},{}]", fmt_end(c.min(), "null"), fmt_end(c.max(), "null")],
+      }).collect::<Vec<_>>();
+      format!["{{\"point\":[{}],\"value\":{}}}", cols.join(","), value]
+    },
+  }
+}
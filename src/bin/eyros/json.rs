@@ -0,0 +1,153 @@
+// just enough json to read the ndjson rows of `eyros import`.
+// numbers are kept as text so that they parse into any scalar type without a round trip through f64.
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(String),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String,Json)>),
+}
+
+impl Json {
+  pub fn parse(src: &str) -> Result<Json,String> {
+    let mut p = Parser { src: src.as_bytes(), offset: 0 };
+    let value = p.value()?;
+    p.space();
+    if p.offset < p.src.len() {
+      return Err(format!["unexpected trailing input at offset {}", p.offset]);
+    }
+    Ok(value)
+  }
+  pub fn get(&self, key: &str) -> Option<&Json> {
+    match self {
+      Json::Object(fields) => fields.iter().find(|(k,_)| k == key).map(|(_,v)| v),
+      _ => None,
+    }
+  }
+}
+
+struct Parser<'a> {
+  src: &'a [u8],
+  offset: usize,
+}
+
+impl<'a> Parser<'a> {
+  fn space(&mut self) {
+    while self.offset < self.src.len() && self.src[self.offset].is_ascii_whitespace() {
+      self.offset += 1;
+    }
+  }
+  fn peek(&mut self) -> Option<u8> {
+    self.space();
+    self.src.get(self.offset).copied()
+  }
+  fn expect(&mut self, c: u8) -> Result<(),String> {
+    if self.peek() != Some(c) {
+      return Err(format!["expected '{}' at offset {}", c as char, self.offset]);
+    }
+    self.offset += 1;
+    Ok(())
+  }
+  fn literal(&mut self, word: &str, value: Json) -> Result<Json,String> {
+    if self.src[self.offset..].starts_with(word.as_bytes()) {
+      self.offset += word.len();
+      Ok(value)
+    } else {
+      Err(format!["unexpected input at offset {}", self.offset])
+    }
+  }
+  fn value(&mut self) -> Result<Json,String> {
+    match self.peek() {
+      None => Err("unexpected end of input".to_string()),
+      Some(b'n') => self.literal("null", Json::Null),
+      Some(b't') => self.literal("true", Json::Bool(true)),
+      Some(b'f') => self.literal("false", Json::Bool(false)),
+      Some(b'"') => Ok(Json::String(self.string()?)),
+      Some(b'[') => {
+        self.offset += 1;
+        let mut items = vec![];
+        if self.peek() == Some(b']') {
+          self.offset += 1;
+          return Ok(Json::Array(items));
+        }
+        loop {
+          items.push(self.value()?);
+          match self.peek() {
+            Some(b',') => self.offset += 1,
+            Some(b']') => { self.offset += 1; break }
+            _ => return Err(format!["expected ',' or ']' at offset {}", self.offset]),
+          }
+        }
+        Ok(Json::Array(items))
+      },
+      Some(b'{') => {
+        self.offset += 1;
+        let mut fields = vec![];
+        if self.peek() == Some(b'}') {
+          self.offset += 1;
+          return Ok(Json::Object(fields));
+        }
+        loop {
+          if self.peek() != Some(b'"') {
+            return Err(format!["expected a key at offset {}", self.offset]);
+          }
+          let key = self.string()?;
+          self.expect(b':')?;
+          fields.push((key,self.value()?));
+          match self.peek() {
+            Some(b',') => self.offset += 1,
+            Some(b'}') => { self.offset += 1; break }
+            _ => return Err(format!["expected ',' or '}}' at offset {}", self.offset]),
+          }
+        }
+        Ok(Json::Object(fields))
+      },
+      Some(c) if c == b'-' || c.is_ascii_digit() => {
+        let start = self.offset;
+        while self.offset < self.src.len()
+        && matches![self.src[self.offset], b'-'|b'+'|b'.'|b'e'|b'E'|b'0'..=b'9'] {
+          self.offset += 1;
+        }
+        Ok(Json::Number(String::from_utf8_lossy(&self.src[start..self.offset]).to_string()))
+      },
+      Some(_) => Err(format!["unexpected input at offset {}", self.offset]),
+    }
+  }
+  fn string(&mut self) -> Result<String,String> {
+    self.expect(b'"')?;
+    let mut out = vec![];
+    loop {
+      match self.src.get(self.offset) {
+        None => return Err("unterminated string".to_string()),
+        Some(b'"') => { self.offset += 1; break }
+        Some(b'\\') => {
+          let c = self.src.get(self.offset+1).copied();
+          self.offset += 2;
+          match c {
+            Some(b'n') => out.push(b'\n'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b'b') => out.push(8),
+            Some(b'f') => out.push(12),
+            Some(b'u') => {
+              let hex = self.src.get(self.offset..self.offset+4)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!["invalid unicode escape at offset {}", self.offset])?;
+              self.offset += 4;
+              let c = char::from_u32(hex).unwrap_or(char::REPLACEMENT_CHARACTER);
+              out.extend_from_slice(c.to_string().as_bytes());
+            },
+            Some(c) => out.push(c),
+            None => return Err("unterminated string".to_string()),
+          }
+        },
+        Some(c) => { out.push(*c); self.offset += 1 }
+      }
+    }
+    String::from_utf8(out).map_err(|e| e.to_string())
+  }
+}
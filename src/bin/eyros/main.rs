@@ -0,0 +1,277 @@
+use eyros::{Setup,DB,Tree,Row,Coord,Error};
+use async_std::{prelude::*,io::{self,BufReader}};
+use std::path::PathBuf;
+
+mod json;
+mod format;
+use format::{CliPoint,CliScalar,Format,bbox,bbox_all,parse_row,fmt_row};
+
+type V = u64;
+
+const COMMANDS: [&str;7] = ["info","query","import","export","verify","optimize","dump-tree"];
+
+const USAGE: &str = "usage: eyros [OPTIONS] DB COMMAND [ARGS]
+
+Work with the eyros database in the directory DB.
+
+commands:
+  info             summarize the roots, trees, records and bounds
+  query BBOX       print the rows that intersect BBOX, given as min0,min1,...,max0,max1,...
+  import [FILE]    insert rows from FILE, or from stdin when FILE is missing or -
+  export           print every row
+  verify           check every tree, and exit with status 1 when problems are found
+  optimize DEPTH   rebuild the trees down to DEPTH into a single root
+  dump-tree ID     print the nodes of a tree as json, or as graphviz with --format dot
+
+options:
+  -d, --dims N          number of dimensions (default 2)
+  -t, --type TYPE       scalar type of the coordinates: f32, f64, i32, i64, u32 or u64 (default f32)
+  -n, --nd              points are stored in a TreeN instead of a Tree{N}
+  -f, --format FORMAT   csv or ndjson for rows (default csv)
+  -b, --batch-size N    rows per batch for import (default 100000)
+  -h, --help            show this message
+
+Values are u64. In csv, each row is the coordinates followed by the value, where a coordinate
+is a scalar x or a range like min..max, min.., ..max or .. for unbounded ends.
+In ndjson, each row is {\"point\":[x,[min,max],...],\"value\":v} with null for unbounded ends.
+";
+
+struct Opts {
+  path: PathBuf,
+  command: String,
+  args: Vec<String>,
+  dims: usize,
+  scalar: String,
+  nd: bool,
+  format: String,
+  batch_size: usize,
+}
+
+fn parse_args(args: &[String]) -> Result<Opts,Error> {
+  let mut positional = vec![];
+  let mut opts = Opts {
+    path: PathBuf::new(),
+    command: String::new(),
+    args: vec![],
+    dims: 2,
+    scalar: "f32".to_string(),
+    nd: false,
+    format: "csv".to_string(),
+    batch_size: 100_000,
+  };
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let mut value = |name: &str| {
+      iter.next().cloned().ok_or_else(|| Error::from(format!["missing value for {}", name]))
+    };
+    match arg.as_str() {
+      "-h" | "--help" => {
+        print!["{}", USAGE];
+        std::process::exit(0);
+      },
+      "-d" | "--dims" => opts.dims = value(arg)?.parse()?,
+      "-t" | "--type" => opts.scalar = value(arg)?,
+      "-n" | "--nd" => opts.nd = true,
+      "-f" | "--format" => opts.format = value(arg)?,
+      "-b" | "--batch-size" => opts.batch_size = value(arg)?.parse()?,
+      "--" => positional.extend(iter.by_ref().cloned()),
+      // negative numbers, like in a bounding box, and - for stdin are not options
+      a if a.starts_with('-') && a.len() > 1 && !a[1..].starts_with(|c: char| {
+        c.is_ascii_digit() || c == '.'
+      }) => return Err(format!["unknown option {}", a].into()),
+      _ => positional.push(arg.clone()),
+    }
+  }
+  if positional.len() < 2 {
+    return Err("expected a database path and a command".into());
+  }
+  opts.path = PathBuf::from(&positional[0]);
+  opts.command = positional[1].clone();
+  if !COMMANDS.contains(&opts.command.as_str()) {
+    return Err(format!["unknown command {}", opts.command].into());
+  }
+  opts.args = positional[2..].to_vec();
+  Ok(opts)
+}
+
+fn arg<'a>(opts: &'a Opts, i: usize, name: &str) -> Result<&'a str,Error> {
+  opts.args.get(i).map(|s| s.as_str())
+    .ok_or_else(|| format!["{} requires {}", opts.command, name].into())
+}
+
+// returns whether the command succeeded
+async fn run<T,P,X>(opts: &Opts) -> Result<bool,Error>
+where T: Tree<P,V>, P: CliPoint<X>, X: CliScalar {
+  // only import creates a database. the meta is written on the first sync, so a directory
+  // without one holds no database yet.
+  let exists = std::fs::metadata(opts.path.join("meta")).map_or(false, |m| m.len() > 0);
+  if !exists && opts.command != "import" {
+    return Err(format!["no database at {}", opts.path.display()].into());
+  }
+  // the point type is checked on open, except for the dimensions of a TreeN
+  let mut db: DB<_,T,P,V> = Setup::from_path(&opts.path).build().await?;
+  let dimensions = db.meta.read().await.dimensions;
+  if opts.nd && dimensions > 0 && dimensions != opts.dims {
+    return Err(format!["database has {} dimensions but --dims is {}",
+      dimensions, opts.dims].into());
+  }
+  let mut stdout = io::stdout();
+  match opts.command.as_str() {
+    "info" => {
+      let info = db.info().await?;
+      println!["roots: {}", info.roots.len()];
+      println!["trees: {}", info.trees];
+      println!["records: {}", info.records];
+      println!["inline records: {}", info.inline_records];
+      println!["external records: {}", info.external_records];
+      println!["depths: {:?}", info.depths];
+      println!["bytes: {}", info.bytes];
+      match &info.bounds {
+        Some(b) => println!["bounds: {:?}", b],
+        None => println!["bounds: none"],
+      }
+      for r in info.roots.iter() {
+        println!["root {}: id={} records={} trees={} depth={} bounds={:?}",
+          r.index, r.id, r.records, r.trees, r.depth, r.bounds];
+      }
+    },
+    "query" | "export" => {
+      let format: Format = opts.format.parse()?;
+      let bbox = if opts.command == "query" {
+        let xs = arg(opts, 0, "a bounding box")?.split(',')
+          .map(|x| x.trim().parse::<X>().map_err(|_| format!["invalid coordinate {:?}", x]))
+          .collect::<Result<Vec<X>,_>>()?;
+        if xs.len() != opts.dims*2 {
+          return Err(format!["expected {} numbers in the bounding box", opts.dims*2].into());
+        }
+        let (min,max) = xs.split_at(opts.dims);
+        bbox::<P,X>(min.to_vec(), max.to_vec())?
+      } else {
+        bbox_all::<P,X>(opts.dims)?
+      };
+      let mut stream = db.query(&bbox).await?;
+      while let Some(result) = stream.next().await {
+        let (point,value) = result?;
+        let line = fmt_row::<P,X,V>(format, &point, &value) + "\n";
+        stdout.write_all(line.as_bytes()).await?;
+      }
+      stdout.flush().await?;
+    },
+    "import" => {
+      let format: Format = opts.format.parse()?;
+      let input: Box<dyn io::Read+Unpin> = match opts.args.first().map(|s| s.as_str()) {
+        None | Some("-") => Box::new(io::stdin()),
+        Some(file) => Box::new(async_std::fs::File::open(file).await?),
+      };
+      let mut lines = BufReader::new(input).lines();
+      let mut rows = Vec::with_capacity(opts.batch_size);
+      let (mut count, mut line_no) = (0,0);
+      while let Some(line) = lines.next().await {
+        line_no += 1;
+        let row = parse_row::<P,X,V>(format, opts.dims, &line?)
+          .map_err(|e| format!["line {}: {}", line_no, e])?;
+        if let Some((point,value)) = row {
+          rows.push(Row::Insert(point,value));
+        }
+        if rows.len() >= opts.batch_size {
+          db.batch(&rows).await?;
+          count += rows.len();
+          rows.clear();
+        }
+      }
+      if !rows.is_empty() {
+        db.batch(&rows).await?;
+        count += rows.len();
+      }
+      db.sync().await?;
+      eprintln!["imported {} rows", count];
+    },
+    "verify" => {
+      let verify = db.verify().await?;
+      for problem in verify.problems.iter() {
+        println!["{}", problem];
+      }
+      println!["checked {} trees and {} records: {} problems",
+        verify.trees, verify.records, verify.problems.len()];
+      return Ok(verify.is_ok());
+    },
+    "optimize" => {
+      let depth = arg(opts, 0, "a depth")?.parse()?;
+      db.optimize(depth).await?;
+      db.sync().await?;
+    },
+    "dump-tree" => {
+      let id = arg(opts, 0, "a tree id")?.parse()?;
+      let dump = db.dump_tree(id).await?;
+      match opts.format.as_str() {
+        "dot" => print!["{}", dump.to_dot()],
+        _ => println!["{}", dump.to_json()],
+      }
+    },
+    _ => unreachable![],
+  }
+  Ok(true)
+}
+
+macro_rules! dispatch_dims {
+  ($opts:expr, $X:ty) => {
+    match ($opts.nd, $opts.dims) {
+      #[cfg(feature="nd")]
+      (true,_) => run::<eyros::TreeN<$X,V>,Vec<Coord<$X>>,$X>($opts).await,
+      #[cfg(feature="2d")]
+      (false,2) => run::<eyros::Tree2<$X,$X,V>,(Coord<$X>,Coord<$X>),$X>($opts).await,
+      #[cfg(feature="3d")]
+      (false,3) => run::<eyros::Tree3<$X,$X,$X,V>,
+        (Coord<$X>,Coord<$X>,Coord<$X>),$X>($opts).await,
+      #[cfg(feature="4d")]
+      (false,4) => run::<eyros::Tree4<$X,$X,$X,$X,V>,
+        (Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>),$X>($opts).await,
+      #[cfg(feature="5d")]
+      (false,5) => run::<eyros::Tree5<$X,$X,$X,$X,$X,V>,
+        (Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>),$X>($opts).await,
+      #[cfg(feature="6d")]
+      (false,6) => run::<eyros::Tree6<$X,$X,$X,$X,$X,$X,V>,
+        (Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>),$X>($opts).await,
+      #[cfg(feature="7d")]
+      (false,7) => run::<eyros::Tree7<$X,$X,$X,$X,$X,$X,$X,V>,
+        (Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>),$X>($opts).await,
+      #[cfg(feature="8d")]
+      (false,8) => run::<eyros::Tree8<$X,$X,$X,$X,$X,$X,$X,$X,V>,
+        (Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>,Coord<$X>),$X>
+        ($opts).await,
+      (_,d) => Err(format!["{} dimensions are not supported by this build", d].into()),
+    }
+  }
+}
+
+async fn dispatch(opts: &Opts) -> Result<bool,Error> {
+  match opts.scalar.as_str() {
+    "f32" => dispatch_dims![opts, f32],
+    "f64" => dispatch_dims![opts, f64],
+    "i32" => dispatch_dims![opts, i32],
+    "i64" => dispatch_dims![opts, i64],
+    "u32" => dispatch_dims![opts, u32],
+    "u64" => dispatch_dims![opts, u64],
+    t => Err(format!["unsupported scalar type {}", t].into()),
+  }
+}
+
+#[async_std::main]
+async fn main() {
+  let args = std::env::args().skip(1).collect::<Vec<_>>();
+  let result = match parse_args(&args) {
+    Ok(opts) => dispatch(&opts).await,
+    Err(e) => Err(format!["{}\n\n{}", e, USAGE].into()),
+  };
+  match result {
+    Ok(true) => {},
+    Ok(false) => std::process::exit(1),
+    // stop quietly when output is piped into a program that exits early, like head
+    Err(e) if e.downcast_ref::<std::io::Error>()
+      .map(|e| e.kind() == std::io::ErrorKind::BrokenPipe).unwrap_or(false) => {},
+    Err(e) => {
+      eprintln!["{}", e];
+      std::process::exit(1);
+    },
+  }
+}
@@ -0,0 +1,312 @@
+use crate::{DB,Tree,Point,Value,Setup,Error,EyrosErrorKind,RA,Storage,check_dimensions,check_point,
+  DebugEvent,BatchReport,tree::{TreeRef,TreeId,InsertValue,build_tree}};
+use async_std::sync::{Arc,Mutex};
+use futures::stream::{Stream,StreamExt};
+use std::collections::{BinaryHeap,VecDeque};
+use std::cmp::Ordering;
+
+// rows per serialized block in a run file.
+// data nodes store their row count in 16 bits, so this must stay below 65536.
+const RUN_BLOCK_SIZE: usize = 4096;
+
+impl<S,T,P,V> DB<S,T,P,V>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  /// Create a new database from `setup` and fill it with every `(point,value)` record from `rows`
+  /// in a single pass.
+  ///
+  /// Instead of merging batch after batch into a growing forest, rows are written to temporary
+  /// sorted runs in storage, merged back together sorted along the first dimension, and built into
+  /// trees one partition at a time. Runs and partitions hold up to `stream_batch_size` rows and
+  /// `stream_batch_bytes` bytes. A single root tree is then built over the partitions. Memory use
+  /// is bounded by one run or partition plus one block of up to 4096 rows per run.
+  ///
+  /// The storage in `setup` must not already contain a database. Rows are sorted with
+  /// `Point::cmp_dim()`, so point types that don't implement it return an error.
+  pub async fn bulk_load<R>(setup: Setup<S>, mut rows: R) -> Result<Self,Error>
+  where R: Stream<Item=(P,V)>+Unpin+Send {
+    let mut db = Self::open_from_setup(setup).await?;
+    if db.meta.read().await.roots.iter().any(|r| r.is_some()) {
+      return EyrosErrorKind::BulkLoadNotEmpty {}.raise();
+    }
+    let mut builder = BulkBuilder::new(&db).await;
+    while let Some(row) = rows.next().await {
+      builder.push(&db, row).await?;
+    }
+    builder.finish(&db).await?;
+    db.sync().await?;
+    db.fields.log(DebugEvent::BulkComplete).await?;
+    Ok(db)
+  }
+}
+
+// rows written to sorted runs and built into a tree that is added to a free root slot,
+// without merging into the trees already in the forest
+pub(crate) struct BulkBuilder<S,T,P,V> where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  id: TreeId,
+  run_files: Vec<String>,
+  buffer: Vec<(P,V)>,
+  buffer_bytes: usize,
+  rows: usize,
+  dims: usize,
+  first: Option<P>,
+  _marker: std::marker::PhantomData<(S,T)>,
+}
+
+impl<S,T,P,V> BulkBuilder<S,T,P,V> where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  pub(crate) async fn new(db: &DB<S,T,P,V>) -> Self {
+    // reserve a tree id so that run files from concurrent builders never share a name
+    let (id,dims) = {
+      let mut meta = db.meta.write().await;
+      meta.next_tree += 1;
+      (meta.next_tree - 1, meta.dimensions)
+    };
+    Self {
+      id,
+      run_files: vec![],
+      buffer: vec![],
+      buffer_bytes: 0,
+      rows: 0,
+      dims,
+      first: None,
+      _marker: std::marker::PhantomData,
+    }
+  }
+  pub(crate) async fn push(&mut self, db: &DB<S,T,P,V>, row: (P,V)) -> Result<(),Error> {
+    check_point(&db.fields, &row.0)?;
+    self.dims = check_dimensions(self.dims, std::iter::once(&row.0))?;
+    // runs are sorted and merged with cmp_dim, so points it can't compare are refused
+    // instead of being built into trees from unsorted runs
+    match &self.first {
+      Some(first) => {
+        if first.cmp_dim(&row.0, 0).is_none() {
+          return EyrosErrorKind::BulkLoadUnsorted {}.raise();
+        }
+      },
+      None => self.first = Some(row.0.clone()),
+    }
+    self.buffer_bytes += row.0.count_point_bytes() + row.1.count_bytes();
+    self.buffer.push(row);
+    self.rows += 1;
+    if full(db, self.buffer.len(), self.buffer_bytes) {
+      self.write_run(db).await?;
+    }
+    Ok(())
+  }
+  async fn write_run(&mut self, db: &DB<S,T,P,V>) -> Result<(),Error> {
+    let file = format!["bulk/{}-{}", self.id, self.run_files.len()];
+    db.fields.log(DebugEvent::BulkWriteRun { file: file.clone(), rows: self.buffer.len() }).await?;
+    let mut unsorted = false;
+    self.buffer.sort_unstable_by(|a,b| a.0.cmp_dim(&b.0, 0).unwrap_or_else(|| {
+      unsorted = true;
+      Ordering::Equal
+    }));
+    if unsorted {
+      return EyrosErrorKind::BulkLoadUnsorted {}.raise();
+    }
+    write_run::<S,T,P,V>(&db.storage, &file, &mut self.buffer).await?;
+    self.buffer_bytes = 0;
+    self.run_files.push(file);
+    Ok(())
+  }
+  // merge the runs, build each partition and the root over them, and add the root to the
+  // meta. the trees are written but the meta is left for the caller to sync.
+  pub(crate) async fn finish(mut self, db: &DB<S,T,P,V>) -> Result<BatchReport<V>,Error> {
+    let mut report = BatchReport::new();
+    report.inserted = self.rows;
+    if !self.buffer.is_empty() {
+      self.write_run(db).await?;
+    }
+    let mut heap = BinaryHeap::with_capacity(self.run_files.len());
+    let mut runs = Vec::with_capacity(self.run_files.len());
+    for (i,file) in self.run_files.iter().enumerate() {
+      let mut run = Run::<S,T,P,V>::open(&db.storage, file).await?;
+      if let Some(row) = run.next().await? {
+        heap.push(Head { row, run: i });
+      }
+      runs.push(run);
+    }
+    let mut partitions: Vec<TreeRef<P>> = vec![];
+    let mut partition: Vec<(P,V)> = vec![];
+    let mut partition_bytes = 0;
+    loop {
+      let head = heap.pop();
+      let done = head.is_none();
+      if let Some(Head { row, run }) = head {
+        partition_bytes += row.0.count_point_bytes() + row.1.count_bytes();
+        partition.push(row);
+        if let Some(row) = runs[run].next().await? {
+          heap.push(Head { row, run });
+        }
+      }
+      if full(db, partition.len(), partition_bytes) || (done && !partition.is_empty()) {
+        db.fields.log(DebugEvent::BulkBuildPartition {
+          index: partitions.len(),
+          rows: partition.len(),
+        }).await?;
+        let inserts = partition.iter()
+          .map(|(p,v)| (p.clone(),InsertValue::Value(v)))
+          .collect::<Vec<_>>();
+        let mut meta = db.meta.write().await;
+        let (tr, create_trees) = build_tree::<T,P,V>(
+          Arc::clone(&db.fields),
+          &inserts,
+          &mut meta.next_tree,
+          false
+        ).await;
+        for (r,t) in create_trees {
+          report.trees_created.push(r);
+          db.trees.put(&r, t).await?;
+        }
+        // write the partition out without removing files that the synced meta may still need
+        db.trees.write_updated().await?;
+        partitions.extend(tr);
+        partition.clear();
+        partition_bytes = 0;
+      }
+      if done { break }
+    }
+    for file in self.run_files.iter() {
+      db.storage.lock().await.remove(file).await?;
+    }
+    if partitions.is_empty() {
+      return Ok(report);
+    }
+
+    // build the root over every partition:
+    let mut meta = db.meta.write().await;
+    // a batch from a clone may have set the dimensions since the builder started
+    if meta.dimensions == 0 {
+      meta.dimensions = self.dims;
+    } else if meta.dimensions != self.dims {
+      return EyrosErrorKind::DimensionMismatch {
+        expected: meta.dimensions,
+        received: self.dims,
+      }.raise();
+    }
+    let n = partitions.len();
+    let root = if n == 1 {
+      partitions.pop()
+    } else {
+      let refs = partitions.iter()
+        .map(|r| (r.bounds.clone(),InsertValue::Ref(r.clone())))
+        .collect::<Vec<_>>();
+      let (tr, create_trees) = build_tree::<T,P,V>(
+        Arc::clone(&db.fields),
+        &refs,
+        &mut meta.next_tree,
+        false
+      ).await;
+      for (r,t) in create_trees {
+        report.trees_created.push(r);
+        db.trees.put(&r, t).await?;
+      }
+      tr
+    };
+    // use the slot a series of equally-sized batches would have filled, or the next free slot
+    // above it, so that the new tree never merges with the trees already in the forest
+    let slot = (usize::BITS - n.leading_zeros() - 1) as usize;
+    match (slot..meta.roots.len()).find(|i| meta.roots[*i].is_none()) {
+      Some(i) => meta.roots[i] = root,
+      None => {
+        while meta.roots.len() < slot { meta.roots.push(None) }
+        meta.roots.push(root);
+      },
+    }
+    Ok(report)
+  }
+}
+
+fn full<S,T,P,V>(db: &DB<S,T,P,V>, rows: usize, bytes: usize) -> bool
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  let max_bytes = db.fields.stream_batch_bytes;
+  rows >= db.fields.stream_batch_size.max(1) || (max_bytes > 0 && bytes >= max_bytes)
+}
+
+async fn write_run<S,T,P,V>(storage: &Arc<Mutex<Box<dyn Storage<S>>>>, file: &str,
+rows: &mut Vec<(P,V)>) -> Result<(),Error>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  let mut s = storage.lock().await.open(file).await?;
+  let mut offset = 0;
+  let mut rest = rows.drain(..);
+  loop {
+    let block = rest.by_ref().take(RUN_BLOCK_SIZE).collect::<Vec<_>>();
+    if block.is_empty() { break }
+    let bytes = T::from_rows(block).to_bytes()?;
+    s.write(offset, &(bytes.len() as u32).to_be_bytes()).await?;
+    s.write(offset+4, &bytes).await?;
+    offset += 4 + bytes.len() as u64;
+  }
+  s.sync_all().await?;
+  Ok(())
+}
+
+struct Run<S,T,P,V> {
+  store: S,
+  file: String,
+  offset: u64,
+  len: u64,
+  rows: VecDeque<(P,V)>,
+  _marker: std::marker::PhantomData<T>,
+}
+
+impl<S,T,P,V> Run<S,T,P,V> where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  async fn open(storage: &Arc<Mutex<Box<dyn Storage<S>>>>, file: &str) -> Result<Self,Error> {
+    let store = storage.lock().await.open(file).await?;
+    let len = store.len().await?;
+    Ok(Self {
+      store,
+      file: file.to_string(),
+      offset: 0,
+      len,
+      rows: VecDeque::new(),
+      _marker: std::marker::PhantomData,
+    })
+  }
+  async fn next(&mut self) -> Result<Option<(P,V)>,Error> {
+    if self.rows.is_empty() && self.offset < self.len {
+      if self.offset + 4 > self.len {
+        return EyrosErrorKind::BulkLoadRunCorrupt {
+          file: self.file.clone(),
+          offset: self.offset,
+        }.raise();
+      }
+      let mut n = [0u8;4];
+      n.copy_from_slice(&self.store.read(self.offset, 4).await?);
+      let n = u32::from_be_bytes(n) as u64;
+      if self.offset + 4 + n > self.len {
+        return EyrosErrorKind::BulkLoadRunCorrupt {
+          file: self.file.clone(),
+          offset: self.offset,
+        }.raise();
+      }
+      let bytes = self.store.read(self.offset+4, n).await?;
+      self.offset += 4 + n;
+      self.rows.extend(T::from_bytes(&bytes)?.1.list().0);
+    }
+    Ok(self.rows.pop_front())
+  }
+}
+
+// the next row from a run, ordered so that BinaryHeap pops the smallest point first
+struct Head<P,V> {
+  row: (P,V),
+  run: usize,
+}
+
+impl<P,V> Ord for Head<P,V> where P: Point {
+  fn cmp(&self, other: &Self) -> Ordering {
+    other.row.0.cmp_dim(&self.row.0, 0).unwrap_or(Ordering::Equal)
+      .then_with(|| other.run.cmp(&self.run))
+  }
+}
+impl<P,V> PartialOrd for Head<P,V> where P: Point {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+impl<P,V> PartialEq for Head<P,V> where P: Point {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+impl<P,V> Eq for Head<P,V> where P: Point {}
@@ -0,0 +1,105 @@
+use desert::{ToBytes,FromBytes,CountBytes};
+use crate::{Coord,Scalar,Error,EyrosErrorKind};
+
+// points in tree files store a 4-bit kind for each dimension, packed two to a byte,
+// followed by the bounded ends of each coordinate.
+
+pub(crate) fn coord_kind<X>(c: &Coord<X>) -> u8 where X: Scalar {
+  match c {
+    Coord::Scalar(_) => 0,
+    Coord::Interval(_,_) => 1,
+    Coord::From(_) => 2,
+    Coord::To(_) => 3,
+    Coord::All => 4,
+  }
+}
+
+pub(crate) fn kinds_len(dims: usize) -> usize {
+  (dims+1)/2
+}
+
+pub(crate) fn write_kind(kinds: &mut [u8], i: usize, kind: u8) {
+  kinds[i/2] |= kind << (4*(i%2));
+}
+
+pub(crate) fn read_kind(kinds: &[u8], i: usize) -> u8 {
+  (kinds[i/2] >> (4*(i%2))) & 0x0f
+}
+
+pub(crate) fn write_coord_values<X>(c: &Coord<X>, buf: &mut [u8]) -> Result<usize,Error>
+where X: Scalar {
+  let mut offset = 0;
+  match c {
+    Coord::Scalar(x) | Coord::From(x) | Coord::To(x) => {
+      offset += x.write_bytes(&mut buf[offset..])?;
+    },
+    Coord::Interval(x,y) => {
+      offset += x.write_bytes(&mut buf[offset..])?;
+      offset += y.write_bytes(&mut buf[offset..])?;
+    },
+    Coord::All => {},
+  }
+  Ok(offset)
+}
+
+pub(crate) fn read_coord<X>(kind: u8, src: &[u8]) -> Result<(usize,Coord<X>),Error>
+where X: Scalar {
+  let mut offset = 0;
+  let c = match kind {
+    0 | 2 | 3 => {
+      let (s,x) = X::from_bytes(&src[offset..])?;
+      offset += s;
+      match kind {
+        0 => Coord::Scalar(x),
+        2 => Coord::From(x),
+        _ => Coord::To(x),
+      }
+    },
+    1 => {
+      let (s,x) = X::from_bytes(&src[offset..])?;
+      offset += s;
+      let (s,y) = X::from_bytes(&src[offset..])?;
+      offset += s;
+      Coord::Interval(x,y)
+    },
+    4 => Coord::All,
+    _ => return EyrosErrorKind::CoordKindInvalid { kind }.raise(),
+  };
+  Ok((offset,c))
+}
+
+pub(crate) fn count_coord_values<X>(c: &Coord<X>) -> usize where X: Scalar {
+  match c {
+    Coord::Scalar(x) | Coord::From(x) | Coord::To(x) => x.count_bytes(),
+    Coord::Interval(x,y) => x.count_bytes() + y.count_bytes(),
+    Coord::All => 0,
+  }
+}
+
+impl<X> ToBytes for Coord<X> where X: Scalar {
+  fn to_bytes(&self) -> Result<Vec<u8>,Error> {
+    let mut buf = vec![0u8;self.count_bytes()];
+    self.write_bytes(&mut buf)?;
+    Ok(buf)
+  }
+  fn write_bytes(&self, buf: &mut [u8]) -> Result<usize,Error> {
+    buf[0] = coord_kind(self);
+    Ok(1 + write_coord_values(self, &mut buf[1..])?)
+  }
+}
+
+impl<X> FromBytes for Coord<X> where X: Scalar {
+  fn from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+    let (s,c) = read_coord(src[0], &src[1..])?;
+    Ok((1+s,c))
+  }
+}
+
+impl<X> CountBytes for Coord<X> where X: Scalar {
+  fn count_bytes(&self) -> usize {
+    1 + count_coord_values(self)
+  }
+  fn count_from_bytes(src: &[u8]) -> Result<usize,Error> {
+    Ok(Self::from_bytes(src)?.0)
+  }
+}
@@ -1,5 +1,6 @@
 use desert::{CountBytes,varint};
 use crate::{Coord,Scalar,Value,Error};
+use super::{kinds_len,count_coord_values};
 
 macro_rules! impl_count_bytes {
   ($Tree:ident,$Branch:ident,$Node:ident,$count_point_bytes:ident,($($i:tt),+),($($T:tt),+)) => {
@@ -24,9 +25,8 @@ macro_rules! impl_count_bytes {
                 }
               }
             },
-            $Node::Data(_,_) => {
-              bytes += node.count_bytes();
-            },
+            // only the root can be a data node here and it was counted above
+            $Node::Data(_,_) => {},
           }
         }
         bytes
@@ -72,11 +72,7 @@ macro_rules! impl_count_bytes {
               sum + $count_point_bytes(&row.0) + row.1.count_bytes()
             })
             + refs.iter().fold(0usize, |sum,r| {
-              sum + varint::length(r.id as u64)
-                $(+ match &r.bounds.$i {
-                  Coord::Interval(x,y) => x.count_bytes() + y.count_bytes(),
-                  _ => panic!["unexpected scalar in TreeRef bound"],
-                })+
+              sum + varint::length(r.id as u64) + $count_point_bytes(&r.bounds)
             }),
         }
       }
@@ -86,11 +82,8 @@ macro_rules! impl_count_bytes {
     }
 
     fn $count_point_bytes<$($T),+>(pt: &($(Coord<$T>),+)) -> usize where $($T: Scalar),+ {
-      let mut size = 1; // 1-byte arity bitfield
-      $(size += match &pt.$i {
-        Coord::Scalar(x) => x.count_bytes(),
-        Coord::Interval(x,y) => x.count_bytes() + y.count_bytes(),
-      };)+
+      let mut size = kinds_len([$($i),+].len());
+      $(size += count_coord_values(&pt.$i);)+
       size
     }
   }
@@ -1,5 +1,6 @@
 use desert::{FromBytes,varint};
 use crate::{Scalar,Coord,Value,tree::TreeRef,Error};
+use super::{kinds_len,read_kind,read_coord};
 use async_std::sync::Arc;
 
 macro_rules! impl_from_bytes {
@@ -14,11 +15,11 @@ macro_rules! impl_from_bytes {
         offset += s;
         let root = match n%2 {
           0 => {
-            let root = $parse_branch(&src, (n/2) as usize, 0)?;
+            let root = $parse_branch(&src, (n/2) as usize, 0, false)?;
             root
           },
           1 => {
-            let (s,data) = $parse_data(&src[offset..], n as usize)?;
+            let (s,data) = $parse_data(&src[offset..], n as usize, false)?;
             offset += s;
             data
           },
@@ -28,7 +29,19 @@ macro_rules! impl_from_bytes {
       }
     }
 
-    fn $parse_branch<$($T),+,V>(src: &[u8], xoffset: usize, depth: usize)
+    impl<$($T),+,V> $Tree<$($T),+,V> where $($T: Scalar),+, V: Value {
+      // read a tree file from format version 1, which only differs in how points are stored
+      pub(crate) fn from_bytes_v1(src: &[u8]) -> Result<Self,Error> {
+        let (s,n) = u32::from_bytes(src)?;
+        let root = match n%2 {
+          0 => $parse_branch(&src, (n/2) as usize, 0, true)?,
+          _ => $parse_data(&src[s..], n as usize, true)?.1,
+        };
+        Ok($Tree::new(Arc::new(root)))
+      }
+    }
+
+    fn $parse_branch<$($T),+,V>(src: &[u8], xoffset: usize, depth: usize, v1: bool)
     -> Result<$Node<$($T),+,V>,Error> where $($T: Scalar),+, V: Value {
       let mut offset = xoffset;
       let mut pivots = ($($n),+);
@@ -61,11 +74,11 @@ macro_rules! impl_from_bytes {
           0 => {
             intersections.push((
               bitfield,
-              Arc::new($parse_branch(&src, (n/2) as usize, depth+1)?)
+              Arc::new($parse_branch(&src, (n/2) as usize, depth+1, v1)?)
             ));
           },
           1 => {
-            let (s,data) = $parse_data(&src[offset..], n as usize)?;
+            let (s,data) = $parse_data(&src[offset..], n as usize, v1)?;
             offset += s;
             intersections.push((bitfield,Arc::new(data)));
           },
@@ -78,10 +91,10 @@ macro_rules! impl_from_bytes {
         offset += s;
         match n%2 {
           0 => {
-            nodes.push(Arc::new($parse_branch(&src, (n/2) as usize, depth+1)?));
+            nodes.push(Arc::new($parse_branch(&src, (n/2) as usize, depth+1, v1)?));
           },
           1 => {
-            let (s,data) = $parse_data(&src[offset..], n as usize)?;
+            let (s,data) = $parse_data(&src[offset..], n as usize, v1)?;
             offset += s;
             nodes.push(Arc::new(data));
           },
@@ -95,56 +108,65 @@ macro_rules! impl_from_bytes {
       )))
     }
 
-    fn $parse_data<$($T),+,V>(src: &[u8], n: usize) -> Result<(usize,$Node<$($T),+,V>),Error>
+    fn $parse_data<$($T),+,V>(src: &[u8], n: usize, v1: bool)
+    -> Result<(usize,$Node<$($T),+,V>),Error>
     where $($T: Scalar),+, V: Value {
       let mut offset = 0;
       let (data_len,ref_len) = ((n>>1)&0xffff,n>>17);
       let mut data: Vec<(($(Coord<$T>),+),V)> = Vec::with_capacity(data_len);
       let mut refs: Vec<TreeRef<($(Coord<$T>),+)>> = vec![];
-      for _ in 0..data_len {
-        let bitfield = src[offset];
-        offset += 1;
-        let point = {
-          $(let $v = match (bitfield>>$i)&1 {
-            0 => {
-              let (s,x) = $T::from_bytes(&src[offset..])?;
-              offset += s;
-              Coord::Scalar(x)
-            },
+      fn parse_point<$($T),+>(src: &[u8]) -> Result<(usize,($(Coord<$T>),+)),Error>
+      where $($T: Scalar),+ {
+        let kinds = &src[0..kinds_len($dim)];
+        let mut offset = kinds.len();
+        $(let $v = {
+          let (s,c) = read_coord(read_kind(kinds, $i), &src[offset..])?;
+          offset += s;
+          c
+        };)+
+        Ok((offset,($($v),+)))
+      }
+      // version 1 points start with a bitfield of which dimensions are intervals, and the bounds
+      // of refs are an interval in every dimension
+      fn parse_point_v1<$($T),+>(src: &[u8], bitfield: u8)
+      -> Result<(usize,($(Coord<$T>),+)),Error> where $($T: Scalar),+ {
+        let mut offset = 0;
+        $(let $v = {
+          let (s,x) = $T::from_bytes(&src[offset..])?;
+          offset += s;
+          match (bitfield>>$i)&1 {
+            0 => Coord::Scalar(x),
             _ => {
-              let (s,x) = $T::from_bytes(&src[offset..])?;
-              offset += s;
               let (s,y) = $T::from_bytes(&src[offset..])?;
               offset += s;
               Coord::Interval(x,y)
-            }
-          };)+
-          ($($v),+)
+            },
+          }
+        };)+
+        Ok((offset,($($v),+)))
+      }
+      for _ in 0..data_len {
+        let (s,point) = match v1 {
+          true => {
+            let (s,point) = parse_point_v1(&src[offset+1..], src[offset])?;
+            (1+s,point)
+          },
+          false => parse_point(&src[offset..])?,
         };
+        offset += s;
         let (s,value) = V::from_bytes(&src[offset..])?;
         offset += s;
         data.push((point,value));
       }
       for _i in 0..ref_len {
-        let (s,r) = varint::decode(&src[offset..])?;
+        let (s,id) = varint::decode(&src[offset..])?;
         offset += s;
-        let tr = TreeRef {
-          id: r,
-          bounds: {
-            $(let $v = {
-              let (s,xmin) = $T::from_bytes(&src[offset..])?;
-              assert![xmin == xmin, "non-identity deserializing xmin={:?}", xmin];
-              offset += s;
-              let (s,xmax) = $T::from_bytes(&src[offset..])?;
-              assert![xmax == xmax, "non-identity deserializing xmax={:?}", xmax];
-              offset += s;
-              Coord::Interval(xmin,xmax)
-            };)+
-            ($($v),+)
-          },
+        let (s,bounds) = match v1 {
+          true => parse_point_v1(&src[offset..], 0xff)?,
+          false => parse_point(&src[offset..])?,
         };
-        //eprintln!["from:bounds={:?}",&tr.bounds];
-        refs.push(tr);
+        offset += s;
+        refs.push(TreeRef { id, bounds });
       }
       Ok((offset,$Node::Data(data,refs)))
     }
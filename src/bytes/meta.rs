@@ -1,11 +1,135 @@
 use desert::{ToBytes,FromBytes,CountBytes,varint};
-use crate::{Point,Meta,TreeRef,Error,EyrosErrorKind};
+use crate::{Point,Meta,TreeRef,Error,EyrosErrorKind,ScalarKind};
+
+// each meta slot starts with MAGIC and a varint format version. version 1 files, from before
+// points could hold open-ended ranges, have no header and are read by decode_meta_v1() to
+// upgrade the database on open.
+// the header goes on with a generation, the length and a checksum of the body, which is the
+// Meta codec below. see docs/schema.md for the layout.
+pub(crate) const MAGIC: &[u8] = b"eyros";
+pub(crate) const FORMAT_VERSION: u32 = 2;
+
+// wrap an encoded Meta in the header of a meta slot
+pub(crate) fn encode_slot(generation: u64, body: &[u8]) -> Result<Vec<u8>,Error> {
+  let size = MAGIC.len() + varint::length(FORMAT_VERSION as u64) + varint::length(generation)
+    + varint::length(body.len() as u64) + 4 + body.len();
+  let mut buf = vec![0u8;size];
+  let mut offset = 0;
+  buf[offset..offset+MAGIC.len()].copy_from_slice(MAGIC);
+  offset += MAGIC.len();
+  offset += varint::encode(FORMAT_VERSION as u64, &mut buf[offset..])?;
+  offset += varint::encode(generation, &mut buf[offset..])?;
+  offset += varint::encode(body.len() as u64, &mut buf[offset..])?;
+  buf[offset..offset+4].copy_from_slice(&crc32(body).to_le_bytes());
+  offset += 4;
+  buf[offset..].copy_from_slice(body);
+  Ok(buf)
+}
+
+// the generation and body of a meta slot, or None for a slot that is empty or was only partly
+// written. a slot from another format version is an error.
+pub(crate) fn decode_slot(src: &[u8]) -> Result<Option<(u64,&[u8])>,Error> {
+  if !src.starts_with(MAGIC) { return Ok(None) }
+  let mut offset = MAGIC.len();
+  let version = match varint::decode(&src[offset..]) {
+    Ok((n,version)) => { offset += n; version },
+    Err(_) => return Ok(None),
+  };
+  if version != FORMAT_VERSION as u64 {
+    return EyrosErrorKind::FormatVersion { version: version as u32 }.raise();
+  }
+  let generation = match varint::decode(&src[offset..]) {
+    Ok((n,generation)) => { offset += n; generation },
+    Err(_) => return Ok(None),
+  };
+  let len = match varint::decode(&src[offset..]) {
+    Ok((n,len)) => { offset += n; len as usize },
+    Err(_) => return Ok(None),
+  };
+  if src.len() < offset + 4 + len { return Ok(None) }
+  let mut checksum = [0u8;4];
+  checksum.copy_from_slice(&src[offset..offset+4]);
+  offset += 4;
+  let body = &src[offset..offset+len];
+  if u32::from_le_bytes(checksum) != crc32(body) { return Ok(None) }
+  Ok(Some((generation,body)))
+}
+
+// read a version 1 meta, which has no header or point type and stores the bounds of each root
+// as Point::Bounds. databases with runtime dimensions didn't exist in that version.
+pub(crate) fn decode_meta_v1<P>(src: &[u8]) -> Result<Meta<P>,Error> where P: Point {
+  let dimensions = match P::DIMENSIONS {
+    Some(d) => d,
+    None => return EyrosErrorKind::FormatVersion { version: 1 }.raise(),
+  };
+  let mut offset = 0;
+  let (n,next_tree) = varint::decode(&src[offset..])?;
+  offset += n;
+  let (n,len64) = varint::decode(&src[offset..])?;
+  offset += n;
+  let len = len64 as usize;
+  if src.len() < offset + (len+7)/8 {
+    return EyrosErrorKind::MetaBitfieldInsufficientBytes {}.raise();
+  }
+  let bitfield = &src[offset..offset+(len+7)/8];
+  offset += (len+7)/8;
+  let mut roots = Vec::with_capacity(len);
+  for i in 0..len {
+    if (bitfield[i/8]>>(i%8))&1==1 {
+      let (n,id) = varint::decode(&src[offset..])?;
+      offset += n;
+      let (n,bounds) = <P::Bounds>::from_bytes(&src[offset..])?;
+      offset += n;
+      roots.push(Some(TreeRef { id, bounds: P::from_bounds(&bounds) }));
+    } else {
+      roots.push(None);
+    }
+  }
+  Ok(Meta { roots, next_tree, dimensions })
+}
+
+// crc-32 (ieee 802.3), computed bitwise since the meta is small
+fn crc32(bytes: &[u8]) -> u32 {
+  let mut crc = !0u32;
+  for b in bytes.iter() {
+    crc ^= *b as u32;
+    for _ in 0..8 {
+      crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+    }
+  }
+  !crc
+}
+
+// the body starts with the point type: whether the number of dimensions is only known at
+// runtime, then the kind of each scalar. a database opened with another point type is refused.
+fn describe_point<P>(kinds: &[ScalarKind]) -> String where P: Point {
+  describe_layout(P::DIMENSIONS.is_none(), kinds)
+}
+
+fn describe_layout(runtime: bool, kinds: &[ScalarKind]) -> String {
+  if runtime {
+    format!["{} (runtime dimensions)", ScalarKind::describe(kinds)]
+  } else {
+    ScalarKind::describe(kinds)
+  }
+}
 
 impl<P> ToBytes for Meta<P> where P: Point, Self: CountBytes {
   fn to_bytes(&self) -> Result<Vec<u8>,Error> {
     let mut offset = 0;
     let mut buf = vec![0u8;self.count_bytes()];
+    buf[offset] = P::DIMENSIONS.is_none() as u8;
+    offset += 1;
+    let kinds = P::scalar_kinds(self.dimensions);
+    offset += varint::encode(kinds.len() as u64, &mut buf[offset..])?;
+    for kind in kinds.iter() {
+      buf[offset] = kind.code();
+      offset += 1;
+    }
     offset += varint::encode(self.next_tree as u64, &mut buf[offset..])?;
+    if P::DIMENSIONS.is_none() {
+      offset += varint::encode(self.dimensions as u64, &mut buf[offset..])?;
+    }
     offset += varint::encode(self.roots.len() as u64, &mut buf[offset..])?;
     for (i,r) in self.roots.iter().enumerate() {
       if r.is_some() {
@@ -17,8 +141,7 @@ impl<P> ToBytes for Meta<P> where P: Point, Self: CountBytes {
       match root {
         Some(r) => {
           offset += varint::encode(r.id as u64, &mut buf[offset..])?;
-          //eprintln!["meta:to bounds={:?}", r.bounds.to_bounds().unwrap()];
-          offset += r.bounds.to_bounds().unwrap().write_bytes(&mut buf[offset..])?;
+          offset += r.bounds.write_point_bytes(&mut buf[offset..])?;
         },
         None => {},
       }
@@ -30,8 +153,44 @@ impl<P> ToBytes for Meta<P> where P: Point, Self: CountBytes {
 impl<P> FromBytes for Meta<P> where P: Point {
   fn from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
     let mut offset = 0;
+    let runtime = match src.first() {
+      Some(b) => *b == 1,
+      None => return EyrosErrorKind::MetaBitfieldInsufficientBytes {}.raise(),
+    };
+    offset += 1;
+    let (n,kinds_len) = varint::decode(&src[offset..])?;
+    offset += n;
+    let mut kinds = Vec::with_capacity(kinds_len as usize);
+    for _ in 0..kinds_len {
+      match src.get(offset).and_then(|code| ScalarKind::from_code(*code)) {
+        Some(kind) => kinds.push(kind),
+        None => return EyrosErrorKind::MetaBitfieldInsufficientBytes {}.raise(),
+      }
+      offset += 1;
+    }
+    if runtime != P::DIMENSIONS.is_none() {
+      return EyrosErrorKind::PointType {
+        expected: describe_point::<P>(&P::scalar_kinds(P::DIMENSIONS.unwrap_or(kinds.len()))),
+        stored: describe_layout(runtime, &kinds),
+      }.raise();
+    }
     let (n,next_tree) = varint::decode(&src[offset..])?;
     offset += n;
+    let dimensions = match P::DIMENSIONS {
+      Some(d) => d,
+      None => {
+        let (n,d) = varint::decode(&src[offset..])?;
+        offset += n;
+        d as usize
+      },
+    };
+    let expected = P::scalar_kinds(dimensions);
+    if expected != kinds {
+      return EyrosErrorKind::PointType {
+        expected: describe_point::<P>(&expected),
+        stored: describe_point::<P>(&kinds),
+      }.raise();
+    }
     let (n,len64) = varint::decode(&src[offset..])?;
     offset += n;
     let len = len64 as usize;
@@ -45,28 +204,31 @@ impl<P> FromBytes for Meta<P> where P: Point {
       if (bitfield[i/8]>>(i%8))&1==1 {
         let (n,id) = varint::decode(&src[offset..])?;
         offset += n;
-        let (n,bounds) = <P::Bounds>::from_bytes(&src[offset..])?;
-        //eprintln!["meta:from bounds={:?}", &bounds];
+        let (n,bounds) = P::point_from_bytes(&src[offset..])?;
         offset += n;
-        roots.push(Some(TreeRef { id, bounds: P::from_bounds(&bounds) }));
+        roots.push(Some(TreeRef { id, bounds }));
       } else {
         roots.push(None);
       }
     }
-    Ok((offset,Self { roots, next_tree }))
+    Ok((offset,Self { roots, next_tree, dimensions }))
   }
 }
 
 impl<P> CountBytes for Meta<P> where P: Point {
   fn count_bytes(&self) -> usize {
-    let mut size = 0;
+    let kinds = P::scalar_kinds(self.dimensions).len();
+    let mut size = 1 + varint::length(kinds as u64) + kinds;
     size += varint::length(self.next_tree as u64);
+    if P::DIMENSIONS.is_none() {
+      size += varint::length(self.dimensions as u64);
+    }
     size += varint::length(self.roots.len() as u64);
     size += (self.roots.len()+7)/8;
     for root in self.roots.iter() {
       size += match root {
         Some(r) => varint::length(r.id as u64)
-          + r.bounds.to_bounds().unwrap().count_bytes(),
+          + r.bounds.count_point_bytes(),
         None => 0,
       }
     }
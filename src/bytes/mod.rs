@@ -2,4 +2,9 @@ mod to;
 mod from;
 mod count;
 mod tree_ref;
+mod coord;
+pub(crate) use coord::*;
 mod meta;
+pub(crate) use meta::{FORMAT_VERSION,MAGIC,encode_slot,decode_slot,decode_meta_v1};
+#[cfg(feature="nd")] mod tree_n;
+mod tree_of;
@@ -1,5 +1,6 @@
 use desert::{ToBytes,CountBytes,varint};
 use crate::{Coord,Scalar,Value,tree::TreeRef,Error};
+use super::{coord_kind,kinds_len,write_kind,write_coord_values};
 use std::collections::HashMap;
 
 macro_rules! impl_to_bytes {
@@ -123,19 +124,15 @@ macro_rules! impl_to_bytes {
 
     fn $write_point_bytes<$($T),+>(pt: &($(Coord<$T>),+), buf: &mut [u8]) -> Result<usize,Error>
     where $($T: Scalar),+ {
-      let mut offset = 1;
-      buf[0] = 0;
-      $(match &pt.$i {
-        Coord::Scalar(x) => {
-          offset += x.write_bytes(&mut buf[offset..])?;
-        },
-        Coord::Interval(x,y) => {
-          buf[0] |= 1 << $i;
-          offset += x.write_bytes(&mut buf[offset..])?;
-          offset += y.write_bytes(&mut buf[offset..])?;
-        },
-      })+
-      Ok(offset)
+      let klen = kinds_len([$($i),+].len());
+      let (kinds,rest) = buf.split_at_mut(klen);
+      kinds.fill(0);
+      let mut offset = 0;
+      $(
+        write_kind(kinds, $i, coord_kind(&pt.$i));
+        offset += write_coord_values(&pt.$i, &mut rest[offset..])?;
+      )+
+      Ok(klen + offset)
     }
 
     fn $write_data_bytes<$($T),+,V>(rows: &[(($(Coord<$T>),+),V)],
@@ -150,15 +147,7 @@ macro_rules! impl_to_bytes {
       }
       for r in refs.iter() {
         offset += varint::encode(r.id, &mut buf[offset..])?;
-        $(match &r.bounds.$i {
-          Coord::Interval(xmin,xmax) => {
-            assert![xmin == xmin, "non-idenity serializing xmin={:?}", xmin];
-            assert![xmax == xmax, "non-idenity serializing xmax={:?}", xmax];
-            offset += xmin.write_bytes(&mut buf[offset..])?;
-            offset += xmax.write_bytes(&mut buf[offset..])?;
-          },
-          _ => panic!["unexpected scalar in TreeRef bound"]
-        };)+
+        offset += $write_point_bytes(&r.bounds, &mut buf[offset..])?;
       }
       Ok(offset)
     }
@@ -0,0 +1,296 @@
+use desert::{ToBytes,FromBytes,CountBytes,varint};
+use crate::{Coord,Scalar,Value,Error,tree::{TreeRef,TreeN,BranchN,NodeN}};
+use async_std::sync::Arc;
+use std::collections::HashMap;
+use super::{coord_kind,kinds_len,write_kind,read_kind,write_coord_values,read_coord,
+  count_coord_values};
+
+// points are written as a varint dimension count, a 4-bit kind for each dimension,
+// and then the coordinates. otherwise the layout is the same as the fixed-dimension trees.
+
+impl<X,V> ToBytes for TreeN<X,V> where X: Scalar, V: Value {
+  fn to_bytes(&self) -> Result<Vec<u8>,Error> {
+    let hsize = self.root.count_bytes();
+    let (alloc,size) = allocate(&self.root, hsize);
+    let mut buf = vec![0u8;size];
+    let mut offset = 0;
+    match self.root.as_ref() {
+      NodeN::Data(data,refs) => {
+        write_data_bytes(data, refs, &mut buf[offset..])?;
+      },
+      NodeN::Branch(branch) => {
+        offset += ((hsize*2) as u32).write_bytes(&mut buf[offset..])?;
+        write_branch_bytes(branch, &alloc, offset, &mut buf)?;
+      },
+    }
+    Ok(buf)
+  }
+}
+
+fn allocate<X,V>(root: &NodeN<X,V>, hsize: usize) -> (HashMap<usize,(usize,usize)>,usize)
+where X: Scalar, V: Value {
+  let mut alloc: HashMap<usize,(usize,usize)> = HashMap::new(); // index => (offset, size)
+  let mut cursors = vec![root];
+  let mut index = 0;
+  let mut cindex = 0;
+  let mut offset = hsize;
+  while let Some(node) = cursors.get(cindex) {
+    if let NodeN::Branch(branch) = node {
+      let size = branch.count_bytes();
+      alloc.insert(index, (offset,size));
+      offset += size;
+      for (_,b) in branch.intersections.iter() {
+        if let NodeN::Branch(_) = b.as_ref() {
+          cursors.push(b);
+        }
+      }
+      for b in branch.nodes.iter() {
+        if let NodeN::Branch(_) = b.as_ref() {
+          cursors.push(b);
+        }
+      }
+      index += 1;
+    }
+    cindex += 1;
+  }
+  (alloc,offset)
+}
+
+fn write_branch_bytes<X,V>(root: &BranchN<X,V>, alloc: &HashMap<usize,(usize,usize)>,
+i_offset: usize, buf: &mut [u8]) -> Result<usize,Error> where X: Scalar, V: Value {
+  let mut cursors = vec![root];
+  let mut offset = i_offset;
+  let mut index = 0;
+  let mut next_index = 1;
+  while let Some(branch) = cursors.get(index) {
+    let pivot_len = branch.pivots.len();
+    offset += branch.pivots.write_bytes(&mut buf[offset..])?;
+    offset += varint::encode(branch.intersections.len() as u64, &mut buf[offset..])?;
+    let ibitfield = &mut buf[offset..offset+(pivot_len*branch.intersections.len()+7)/8];
+    ibitfield.fill(0);
+    offset += ibitfield.len();
+    {
+      let mut i = 0;
+      for (bitfield,_) in branch.intersections.iter() {
+        for j in 0..pivot_len {
+          ibitfield[i/8] |= (((bitfield>>j)&1) as u8)<<(i%8);
+          i += 1;
+        }
+      }
+    }
+    let mut xcursors = vec![];
+    let children = branch.intersections.iter().map(|(_,b)| b).chain(branch.nodes.iter());
+    for b in children {
+      match b.as_ref() {
+        NodeN::Branch(br) => {
+          let (j,_size) = alloc.get(&next_index).unwrap();
+          offset += (((*j)*2) as u32).write_bytes(&mut buf[offset..])?;
+          next_index += 1;
+          xcursors.push(br);
+        },
+        NodeN::Data(data, refs) => {
+          offset += write_data_bytes(data, refs, &mut buf[offset..])?;
+        },
+      }
+    }
+    cursors.extend(xcursors);
+    index += 1;
+  }
+  Ok(offset)
+}
+
+fn write_point_bytes<X>(pt: &[Coord<X>], buf: &mut [u8]) -> Result<usize,Error> where X: Scalar {
+  let mut offset = varint::encode(pt.len() as u64, buf)?;
+  let klen = kinds_len(pt.len());
+  let (kinds,rest) = buf[offset..].split_at_mut(klen);
+  kinds.fill(0);
+  offset += klen;
+  let mut size = 0;
+  for (i,c) in pt.iter().enumerate() {
+    write_kind(kinds, i, coord_kind(c));
+    size += write_coord_values(c, &mut rest[size..])?;
+  }
+  Ok(offset + size)
+}
+
+fn write_data_bytes<X,V>(rows: &[(Vec<Coord<X>>,V)], refs: &[TreeRef<Vec<Coord<X>>>],
+buf: &mut [u8]) -> Result<usize,Error> where X: Scalar, V: Value {
+  let mut offset = 0;
+  let n = ((rows.len()<<1) + (refs.len()<<17) + 1) as u32;
+  offset += n.write_bytes(&mut buf[offset..])?;
+  for row in rows.iter() {
+    offset += write_point_bytes(&row.0, &mut buf[offset..])?;
+    offset += row.1.write_bytes(&mut buf[offset..])?;
+  }
+  for r in refs.iter() {
+    offset += varint::encode(r.id, &mut buf[offset..])?;
+    offset += write_point_bytes(&r.bounds, &mut buf[offset..])?;
+  }
+  Ok(offset)
+}
+
+impl<X,V> FromBytes for TreeN<X,V> where X: Scalar, V: Value {
+  fn from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+    let mut offset = 0;
+    let (s,n) = u32::from_bytes(&src[offset..])?;
+    offset += s;
+    let root = match n%2 {
+      0 => parse_branch(src, (n/2) as usize)?,
+      _ => {
+        let (s,data) = parse_data(&src[offset..], n as usize)?;
+        offset += s;
+        data
+      },
+    };
+    Ok((offset, TreeN::new(Arc::new(root))))
+  }
+}
+
+fn parse_branch<X,V>(src: &[u8], xoffset: usize) -> Result<NodeN<X,V>,Error>
+where X: Scalar, V: Value {
+  let mut offset = xoffset;
+  let (s,pivots) = <Vec<X>>::from_bytes(&src[offset..])?;
+  offset += s;
+  let pivot_len = pivots.len();
+  let (s,ilen64) = varint::decode(&src[offset..])?;
+  let ilen = ilen64 as usize;
+  offset += s;
+  let ibf = &src[offset..offset+(ilen*pivot_len+7)/8];
+  let mut ibfi = 0;
+  offset += (ilen*pivot_len+7)/8;
+  let mut intersections = Vec::with_capacity(ilen);
+  for _ in 0..ilen {
+    let mut bitfield: u32 = 0;
+    for j in 0..pivot_len {
+      bitfield |= (((ibf[ibfi/8]>>(ibfi%8))&1) as u32) << j;
+      ibfi += 1;
+    }
+    let (s,node) = parse_node(src, offset)?;
+    offset += s;
+    intersections.push((bitfield,Arc::new(node)));
+  }
+  let mut nodes = Vec::with_capacity(pivot_len+1);
+  for _ in 0..pivot_len+1 {
+    let (s,node) = parse_node(src, offset)?;
+    offset += s;
+    nodes.push(Arc::new(node));
+  }
+  Ok(NodeN::Branch(BranchN::new(pivots, intersections, nodes)))
+}
+
+// parse a child node at offset: either a pointer to a branch or inline data
+fn parse_node<X,V>(src: &[u8], xoffset: usize) -> Result<(usize,NodeN<X,V>),Error>
+where X: Scalar, V: Value {
+  let (s,n) = u32::from_bytes(&src[xoffset..])?;
+  match n%2 {
+    0 => Ok((s, parse_branch(src, (n/2) as usize)?)),
+    _ => {
+      let (size,data) = parse_data(&src[xoffset+s..], n as usize)?;
+      Ok((s+size, data))
+    },
+  }
+}
+
+fn parse_point<X>(src: &[u8]) -> Result<(usize,Vec<Coord<X>>),Error> where X: Scalar {
+  let (mut offset,dims64) = varint::decode(src)?;
+  let dims = dims64 as usize;
+  let kinds = &src[offset..offset+kinds_len(dims)];
+  offset += kinds.len();
+  let mut point = Vec::with_capacity(dims);
+  for i in 0..dims {
+    let (s,c) = read_coord(read_kind(kinds, i), &src[offset..])?;
+    offset += s;
+    point.push(c);
+  }
+  Ok((offset,point))
+}
+
+fn parse_data<X,V>(src: &[u8], n: usize) -> Result<(usize,NodeN<X,V>),Error>
+where X: Scalar, V: Value {
+  let mut offset = 0;
+  let (data_len,ref_len) = ((n>>1)&0xffff,n>>17);
+  let mut data = Vec::with_capacity(data_len);
+  let mut refs = Vec::with_capacity(ref_len);
+  for _ in 0..data_len {
+    let (s,point) = parse_point(&src[offset..])?;
+    offset += s;
+    let (s,value) = V::from_bytes(&src[offset..])?;
+    offset += s;
+    data.push((point,value));
+  }
+  for _ in 0..ref_len {
+    let (s,id) = varint::decode(&src[offset..])?;
+    offset += s;
+    let (s,bounds) = parse_point(&src[offset..])?;
+    offset += s;
+    refs.push(TreeRef { id, bounds });
+  }
+  Ok((offset,NodeN::Data(data,refs)))
+}
+
+impl<X,V> CountBytes for TreeN<X,V> where X: Scalar, V: Value {
+  fn count_bytes(&self) -> usize {
+    let mut bytes = self.root.count_bytes();
+    let mut cursors = vec![&*self.root];
+    while let Some(node) = cursors.pop() {
+      match node {
+        NodeN::Branch(branch) => {
+          bytes += branch.count_bytes();
+          for b in branch.intersections.iter().map(|(_,b)| b).chain(branch.nodes.iter()) {
+            if let NodeN::Branch(_) = b.as_ref() {
+              cursors.push(b);
+            }
+          }
+        },
+        // only the root can be a data node here and it was counted above
+        NodeN::Data(_,_) => {},
+      }
+    }
+    bytes
+  }
+  fn count_from_bytes(_src: &[u8]) -> Result<usize,Error> {
+    unimplemented![]
+  }
+}
+
+impl<X,V> CountBytes for BranchN<X,V> where X: Scalar, V: Value {
+  fn count_bytes(&self) -> usize {
+    let pivot_len = self.pivots.len();
+    let mut size = self.pivots.count_bytes();
+    size += varint::length(self.intersections.len() as u64);
+    size += (self.intersections.len()*pivot_len+7)/8;
+    for (_,b) in self.intersections.iter() {
+      size += b.count_bytes();
+    }
+    for b in self.nodes.iter() {
+      size += b.count_bytes();
+    }
+    size
+  }
+  fn count_from_bytes(_src: &[u8]) -> Result<usize,Error> {
+    unimplemented![]
+  }
+}
+
+impl<X,V> CountBytes for NodeN<X,V> where X: Scalar, V: Value {
+  fn count_bytes(&self) -> usize {
+    match &self {
+      NodeN::Branch(_branch) => 4,
+      NodeN::Data(rows,refs) => 4
+        + rows.iter().fold(0usize, |sum,row| {
+          sum + count_point_bytes(&row.0) + row.1.count_bytes()
+        })
+        + refs.iter().fold(0usize, |sum,r| {
+          sum + varint::length(r.id) + count_point_bytes(&r.bounds)
+        }),
+    }
+  }
+  fn count_from_bytes(_src: &[u8]) -> Result<usize,Error> {
+    unimplemented![]
+  }
+}
+
+fn count_point_bytes<X>(pt: &[Coord<X>]) -> usize where X: Scalar {
+  varint::length(pt.len() as u64) + kinds_len(pt.len())
+    + pt.iter().map(count_coord_values).sum::<usize>()
+}
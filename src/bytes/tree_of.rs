@@ -0,0 +1,29 @@
+use desert::{ToBytes,FromBytes,CountBytes};
+use crate::{TuplePoint,Value,Error,tree::TreeOf};
+
+// a TreeOf is written exactly like the Tree{N} it wraps
+
+impl<P,V> ToBytes for TreeOf<P,V> where P: TuplePoint, V: Value {
+  fn to_bytes(&self) -> Result<Vec<u8>,Error> {
+    self.tree.to_bytes()
+  }
+  fn write_bytes(&self, buf: &mut [u8]) -> Result<usize,Error> {
+    self.tree.write_bytes(buf)
+  }
+}
+
+impl<P,V> FromBytes for TreeOf<P,V> where P: TuplePoint, V: Value {
+  fn from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+    let (size,tree) = P::Tree::<V>::from_bytes(src)?;
+    Ok((size,Self::new(tree)))
+  }
+}
+
+impl<P,V> CountBytes for TreeOf<P,V> where P: TuplePoint, V: Value {
+  fn count_bytes(&self) -> usize {
+    self.tree.count_bytes()
+  }
+  fn count_from_bytes(src: &[u8]) -> Result<usize,Error> {
+    P::Tree::<V>::count_from_bytes(src)
+  }
+}
@@ -0,0 +1,121 @@
+use crate::{DB,Tree,TreeId,Point,Value,Error,RA,Storage,MetaFile,tree,stats::Counters};
+use async_std::sync::Arc;
+use std::collections::HashMap;
+
+/// Result of `DB::copy_to()`.
+#[derive(Debug,Clone,PartialEq)]
+pub struct CopyReport {
+  /// Number of tree files written. Trees already in the destination with the same contents are
+  /// not written again.
+  pub trees: usize,
+  /// Number of bytes written, including the meta file.
+  pub bytes: u64,
+}
+
+async fn write_file<S2>(storage: &mut Box<dyn Storage<S2>>, name: &str, bytes: &[u8])
+-> Result<(),Error> where S2: RA {
+  let mut s = storage.open(name).await?;
+  s.write(0, bytes).await?;
+  // files left over from an earlier copy may be longer
+  s.truncate(bytes.len() as u64).await?;
+  s.sync_all().await?;
+  Ok(())
+}
+
+// depth-first walk so that a tree is written after its children, once their ids in the
+// destination are known
+enum Step {
+  Enter(TreeId),
+  Leave(TreeId),
+}
+
+impl<S,T,P,V> DB<S,T,P,V>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  /// Copy the meta file and every tree reachable from the roots into `storage`, which may use a
+  /// different backend than this database. The destination can be opened like any other database
+  /// with the same tree type.
+  ///
+  /// The copy is taken from the version of the database at the start of the call, including
+  /// changes that have not been synced yet. Batches and syncs from clones of this database can
+  /// run while the copy is written, but files of trees they remove are kept until the first sync
+  /// after the copy finishes.
+  ///
+  /// Tree files are written before the meta file, so an interrupted copy never leaves a meta file
+  /// that points at missing trees. When `storage` already holds a database, such as an earlier
+  /// copy, tree files its meta may refer to are never overwritten: a tree whose file exists with
+  /// different contents is written under a new id instead. Files the copy doesn't overwrite are
+  /// left in place.
+  pub async fn copy_to<S2>(&self, mut storage: Box<dyn Storage<S2>>) -> Result<CopyReport,Error>
+  where S2: RA {
+    let _pin = self.trees.pin();
+    // batches hold the meta write lock while they put trees, so the meta and the trees pending at
+    // this point belong together. synced trees never change under their id.
+    let (mut meta, pending) = {
+      let meta = self.meta.read().await;
+      (meta.clone(), self.trees.pending_trees().await)
+    };
+    let (mut meta_file,dest,_) = MetaFile::<S2>::open::<P>(&mut storage).await?;
+    let mut next_tree = meta.next_tree.max(dest.map_or(0, |m| m.next_tree));
+    let mut report = CopyReport { trees: 0, bytes: 0 };
+    let mut ids: HashMap<TreeId,TreeId> = HashMap::new();
+    let mut entered = HashMap::new();
+    let mut steps = meta.roots.iter().filter_map(|r| r.as_ref().map(|r| Step::Enter(r.id)))
+      .collect::<Vec<_>>();
+    while let Some(step) = steps.pop() {
+      match step {
+        Step::Enter(id) => {
+          if entered.contains_key(&id) || ids.contains_key(&id) { continue }
+          let t = match pending.get(&id) {
+            Some(t) => Arc::clone(t),
+            None => self.trees.get_stored(&id).await?,
+          };
+          steps.push(Step::Leave(id));
+          steps.extend(t.lock().await.list_refs().iter().map(|r| Step::Enter(r.id)));
+          entered.insert(id, t);
+        },
+        Step::Leave(id) => {
+          let t = entered.remove(&id).unwrap();
+          let bytes = {
+            let mut t = t.lock().await;
+            let refs = t.list_refs();
+            let mut moved: Option<T> = None;
+            for r in refs.iter() {
+              if let Some(to) = ids.get(&r.id).filter(|to| **to != r.id) {
+                moved = Some(moved.as_ref().unwrap_or(&*t).replace_ref(r.id, *to));
+              }
+            }
+            match moved {
+              Some(m) => m.to_bytes()?,
+              None => t.to_bytes()?,
+            }
+          };
+          let mut s = storage.open(&tree::get_file_from_id(&id)).await?;
+          let len = s.len().await?;
+          let to = if len == 0 {
+            id
+          } else if len == bytes.len() as u64 && s.read(0, len).await? == bytes {
+            // already in place from an earlier copy
+            ids.insert(id, id);
+            continue;
+          } else {
+            next_tree += 1;
+            next_tree - 1
+          };
+          write_file(&mut storage, &tree::get_file_from_id(&to), &bytes).await?;
+          ids.insert(id, to);
+          report.trees += 1;
+          report.bytes += bytes.len() as u64;
+        },
+      }
+    }
+    for r in meta.roots.iter_mut().flatten() {
+      r.id = ids[&r.id];
+    }
+    meta.next_tree = next_tree;
+    // the meta is written as the next generation after any database already in storage, so that
+    // it replaces the newest meta there
+    report.bytes += meta_file.write(&meta).await?;
+    Counters::add(&self.trees.counters.bytes_written, report.bytes);
+    Ok(report)
+  }
+}
@@ -1,5 +1,14 @@
+use crate::{TreeId,tree::get_file_from_id};
+
+/// Receive debug events from a database set up with `Setup::debug()`.
+///
+/// Implement `event()` to inspect the structured `DebugEvent`s, or `send()` to receive their
+/// string form. Closures of `FnMut(&str)` receive the string form.
 pub trait Debugger {
-  fn send(&mut self, _msg: &str);
+  fn send(&mut self, _msg: &str) {}
+  fn event(&mut self, event: &DebugEvent) {
+    self.send(&event.to_string())
+  }
 }
 
 impl<F> Debugger for F where F: FnMut(&str) {
@@ -7,3 +16,114 @@ impl<F> Debugger for F where F: FnMut(&str) {
     (self)(msg)
   }
 }
+
+/// Events emitted while a database runs. The `Display` form is the message passed to
+/// `Debugger::send()`.
+#[derive(Debug,Clone,PartialEq)]
+pub enum DebugEvent {
+  /// The database is being opened.
+  Open,
+  /// No meta file was found, so a new database was initialized.
+  OpenNew,
+  /// An existing database was found with a meta file of `bytes` length.
+  OpenExisting { bytes: u64 },
+  /// A tree was read from the set of trees written since the last sync.
+  GetUpdated { id: TreeId },
+  /// A tree was requested after it was removed, which is an error.
+  GetRemoved { id: TreeId },
+  /// A tree was found in the tree cache.
+  CacheHit { id: TreeId },
+  /// A tree was not in the cache and will be read from storage.
+  CacheMiss { id: TreeId },
+  /// `bytes` were read from storage for a tree.
+  Read { id: TreeId, bytes: u64 },
+  /// A tree was written to the cache, to be written to storage on the next sync.
+  Put { id: TreeId },
+  /// A tree was removed, to be removed from storage on the next sync.
+  Remove { id: TreeId },
+  /// Changes since a checkpoint were discarded.
+  RestoreCheckpoint,
+  /// A sync started.
+  SyncBegin,
+  /// An updated tree is being written to storage.
+  SyncTree { id: TreeId },
+  /// A removed tree is being removed from storage.
+  SyncRemove { id: TreeId },
+  /// A sync finished.
+  SyncComplete,
+  /// Trees are being rebuilt from `inputs` root trees into a tree of `rows` rows and refs.
+  Merge { inputs: usize, rows: usize },
+  /// `batch_stream()` is writing `rows` rows.
+  BatchStream { rows: usize },
+  /// A query started for the bounding box `bbox`, in its `Debug` form.
+  QueryStart { bbox: String },
+  /// A query is searching the root tree at `index`.
+  QueryRoot { index: usize, id: TreeId },
+  /// A query finished searching the root tree at `index`.
+  QueryEnd { index: usize, id: TreeId, bounds: String },
+  /// A transaction started.
+  BeginTransaction,
+  /// A transaction was committed.
+  CommitTransaction,
+  /// A transaction was rolled back.
+  RollbackTransaction,
+  /// `bulk_load()` wrote a sorted run of `rows` rows to `file`.
+  BulkWriteRun { file: String, rows: usize },
+  /// `bulk_load()` is building partition `index` from `rows` rows.
+  BulkBuildPartition { index: usize, rows: usize },
+  /// `bulk_load()` finished.
+  BulkComplete,
+}
+
+impl std::fmt::Display for DebugEvent {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DebugEvent::Open => write![f, "opening db"],
+      DebugEvent::OpenNew => write![f, "no existing db found. initialized new meta"],
+      DebugEvent::OpenExisting { bytes } => {
+        write![f, "existing db found. reading {} bytes from meta store", bytes]
+      },
+      DebugEvent::GetUpdated { id } => {
+        write![f, "get tree id={} file={}: updated", id, get_file_from_id(id)]
+      },
+      DebugEvent::GetRemoved { id } => {
+        write![f, "get tree id={} file={}: removed (error)", id, get_file_from_id(id)]
+      },
+      DebugEvent::CacheHit { id } => {
+        write![f, "get tree id={} file={}: cached", id, get_file_from_id(id)]
+      },
+      DebugEvent::CacheMiss { id } => {
+        write![f, "get tree id={} file={}: not cached", id, get_file_from_id(id)]
+      },
+      DebugEvent::Read { id, bytes } => write![f, "read {} bytes from tree id={}", bytes, id],
+      DebugEvent::Put { id } => write![f, "put tree id={}", id],
+      DebugEvent::Remove { id } => write![f, "remove tree id={}", id],
+      DebugEvent::RestoreCheckpoint => write![f, "restore checkpoint"],
+      DebugEvent::SyncBegin => write![f, "sync begin"],
+      DebugEvent::SyncTree { id } => {
+        write![f, "sync tree (updated) id={} file={}", id, get_file_from_id(id)]
+      },
+      DebugEvent::SyncRemove { id } => {
+        write![f, "sync tree (remove) id={} file={}", id, get_file_from_id(id)]
+      },
+      DebugEvent::SyncComplete => write![f, "sync complete"],
+      DebugEvent::Merge { inputs, rows } => write![f, "merge inputs={} rows={}", inputs, rows],
+      DebugEvent::BatchStream { rows } => write![f, "batch_stream writing {} rows", rows],
+      DebugEvent::QueryStart { bbox } => write![f, "query bbox={}", bbox],
+      DebugEvent::QueryRoot { index, id } => write![f, "query root i={} id={}", index, id],
+      DebugEvent::QueryEnd { index, id, bounds } => {
+        write![f, "query end root_index={} root.id={} root.bounds={}", index, id, bounds]
+      },
+      DebugEvent::BeginTransaction => write![f, "begin transaction"],
+      DebugEvent::CommitTransaction => write![f, "commit transaction"],
+      DebugEvent::RollbackTransaction => write![f, "rollback transaction"],
+      DebugEvent::BulkWriteRun { file, rows } => {
+        write![f, "bulk_load write run file={} rows={}", file, rows]
+      },
+      DebugEvent::BulkBuildPartition { index, rows } => {
+        write![f, "bulk_load build partition i={} rows={}", index, rows]
+      },
+      DebugEvent::BulkComplete => write![f, "bulk_load complete"],
+    }
+  }
+}
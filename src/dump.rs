@@ -0,0 +1,211 @@
+use crate::{DB,Tree,Point,Value,Error,RA,TreeId};
+use std::collections::{HashSet,VecDeque};
+use std::fmt::Write;
+
+/// Shape of a node inside a tree file, from `Tree::dump()`.
+/// Scalars and bounds are kept in their `Debug` form so that any `Point` type can be dumped.
+#[derive(Debug,Clone,PartialEq)]
+pub enum DumpNode {
+  Branch {
+    /// Depth of this branch inside its tree file.
+    level: usize,
+    /// Dimension that the pivots split.
+    dimension: usize,
+    pivots: Vec<String>,
+    /// Nodes for rows that intersect pivots, with a bit set for each pivot they intersect.
+    intersections: Vec<(u32,DumpNode)>,
+    /// Nodes for rows between pivots, in order.
+    nodes: Vec<DumpNode>,
+  },
+  Data {
+    rows: usize,
+    /// Encoded size of this data block.
+    bytes: usize,
+    /// External trees that this block refers to.
+    refs: Vec<DumpRef>,
+  },
+}
+
+/// Link from a data block to an external tree.
+#[derive(Debug,Clone,PartialEq)]
+pub struct DumpRef {
+  pub id: TreeId,
+  pub bounds: String,
+}
+
+/// Node hierarchy of a single tree file.
+#[derive(Debug,Clone,PartialEq)]
+pub struct DumpTree {
+  pub id: TreeId,
+  pub root: DumpNode,
+}
+
+/// Trees collected by `DB::dump()` or `DB::dump_tree()`, which can be rendered as JSON with
+/// `to_json()` or as a Graphviz digraph with `to_dot()`.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Dump {
+  /// Index into the database meta and tree id of each live root included in this dump.
+  pub roots: Vec<(usize,TreeId)>,
+  pub trees: Vec<DumpTree>,
+}
+
+impl Dump {
+  /// Render as a JSON object with `roots` and `trees` keys.
+  pub fn to_json(&self) -> String {
+    let mut out = String::new();
+    out.push_str("{\"roots\":[");
+    for (i,(index,id)) in self.roots.iter().enumerate() {
+      if i > 0 { out.push(',') }
+      write![out, "{{\"index\":{},\"id\":{}}}", index, id].unwrap();
+    }
+    out.push_str("],\"trees\":[");
+    for (i,t) in self.trees.iter().enumerate() {
+      if i > 0 { out.push(',') }
+      write![out, "{{\"id\":{},\"root\":", t.id].unwrap();
+      node_json(&t.root, &mut out);
+      out.push('}');
+    }
+    out.push_str("]}");
+    out
+  }
+  /// Render as a Graphviz digraph with a cluster for each tree file. Dashed edges link data blocks
+  /// to the external trees they refer to.
+  pub fn to_dot(&self) -> String {
+    let mut out = String::new();
+    out.push_str("digraph eyros {\n  node [shape=box,fontname=monospace];\n");
+    let ids = self.trees.iter().map(|t| t.id).collect::<HashSet<TreeId>>();
+    let mut missing = vec![];
+    for (index,id) in self.roots.iter() {
+      writeln![out, "  root{} [shape=plaintext,label=\"root {}\"];\n  root{} -> t{}_0;",
+        index, index, index, id].unwrap();
+    }
+    for t in self.trees.iter() {
+      writeln![out, "  subgraph cluster_t{} {{\n    label=\"tree {}\";", t.id, t.id].unwrap();
+      let mut refs = vec![];
+      node_dot(t.id, &t.root, &mut 0, &mut out, &mut refs);
+      out.push_str("  }\n");
+      for (from,r) in refs {
+        writeln![out, "  {} -> t{}_0 [style=dashed,label=\"{}\"];",
+          from, r.id, escape_dot(&r.bounds)].unwrap();
+        if !ids.contains(&r.id) { missing.push(r.id) }
+      }
+    }
+    for id in missing {
+      writeln![out, "  t{}_0 [shape=folder,label=\"tree {}\"];", id, id].unwrap();
+    }
+    out.push_str("}\n");
+    out
+  }
+}
+
+fn node_json(node: &DumpNode, out: &mut String) {
+  match node {
+    DumpNode::Branch { level, dimension, pivots, intersections, nodes } => {
+      write![out, "{{\"type\":\"branch\",\"level\":{},\"dimension\":{},\"pivots\":[",
+        level, dimension].unwrap();
+      for (i,p) in pivots.iter().enumerate() {
+        if i > 0 { out.push(',') }
+        write![out, "\"{}\"", escape_json(p)].unwrap();
+      }
+      out.push_str("],\"intersections\":[");
+      for (i,(bitfield,n)) in intersections.iter().enumerate() {
+        if i > 0 { out.push(',') }
+        write![out, "{{\"bitfield\":{},\"node\":", bitfield].unwrap();
+        node_json(n, out);
+        out.push('}');
+      }
+      out.push_str("],\"nodes\":[");
+      for (i,n) in nodes.iter().enumerate() {
+        if i > 0 { out.push(',') }
+        node_json(n, out);
+      }
+      out.push_str("]}");
+    },
+    DumpNode::Data { rows, bytes, refs } => {
+      write![out, "{{\"type\":\"data\",\"rows\":{},\"bytes\":{},\"refs\":[", rows, bytes].unwrap();
+      for (i,r) in refs.iter().enumerate() {
+        if i > 0 { out.push(',') }
+        write![out, "{{\"id\":{},\"bounds\":\"{}\"}}", r.id, escape_json(&r.bounds)].unwrap();
+      }
+      out.push_str("]}");
+    },
+  }
+}
+
+// write a node and its children into a cluster, collecting links to external trees.
+// returns the graphviz name of the node.
+fn node_dot<'a>(id: TreeId, node: &'a DumpNode, next: &mut usize, out: &mut String,
+refs: &mut Vec<(String,&'a DumpRef)>) -> String {
+  let name = format!["t{}_{}", id, next];
+  *next += 1;
+  match node {
+    DumpNode::Branch { level, dimension, pivots, intersections, nodes } => {
+      writeln![out, "    {} [label=\"branch level={} dim={}\\npivots: {}\"];", name, level,
+        dimension, escape_dot(&pivots.join(", "))].unwrap();
+      for (bitfield,n) in intersections.iter() {
+        let child = node_dot(id, n, next, out, refs);
+        writeln![out, "    {} -> {} [label=\"{:b}\"];", name, child, bitfield].unwrap();
+      }
+      for (i,n) in nodes.iter().enumerate() {
+        let child = node_dot(id, n, next, out, refs);
+        writeln![out, "    {} -> {} [label=\"{}\",style=bold];", name, child, i].unwrap();
+      }
+    },
+    DumpNode::Data { rows, bytes, refs: rs } => {
+      writeln![out, "    {} [shape=ellipse,label=\"data rows={} bytes={}\"];",
+        name, rows, bytes].unwrap();
+      refs.extend(rs.iter().map(|r| (name.clone(),r)));
+    },
+  }
+  name
+}
+
+fn escape_json(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      c if (c as u32) < 0x20 => write![out, "\\u{:04x}", c as u32].unwrap(),
+      c => out.push(c),
+    }
+  }
+  out
+}
+
+fn escape_dot(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub async fn dump<S,T,P,V>(db: &DB<S,T,P,V>, ids: Option<&[TreeId]>) -> Result<Dump,Error>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  let roots = db.meta.read().await.roots.iter().enumerate()
+    .filter_map(|(i,r)| r.as_ref().map(|r| (i,r.id)))
+    .collect::<Vec<_>>();
+  let mut dump = Dump { roots: vec![], trees: vec![] };
+  let mut cursors = VecDeque::new();
+  match ids {
+    // only the listed trees, without following their refs
+    Some(ids) => {
+      dump.roots = roots.into_iter().filter(|(_,id)| ids.contains(id)).collect();
+      for id in ids {
+        let root = db.trees.get(id).await?.lock().await.dump();
+        dump.trees.push(DumpTree { id: *id, root });
+      }
+    },
+    None => {
+      cursors.extend(roots.iter().map(|(_,id)| *id));
+      dump.roots = roots;
+    },
+  }
+  let mut seen = HashSet::new();
+  while let Some(id) = cursors.pop_front() {
+    if !seen.insert(id) { continue }
+    let t = db.trees.get(&id).await?;
+    let mut tree = t.lock().await;
+    cursors.extend(tree.list_refs().iter().map(|r| r.id));
+    dump.trees.push(DumpTree { id, root: tree.dump() });
+  }
+  Ok(dump)
+}
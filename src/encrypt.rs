@@ -0,0 +1,225 @@
+use crate::{Storage,Setup,Error,RA,EyrosErrorKind};
+use async_std::sync::{Arc,Mutex};
+use chacha20poly1305::{XChaCha20Poly1305,aead::{Aead,NewAead,Payload}};
+use std::convert::TryInto;
+use futures::io::AsyncWrite;
+use random_access_storage::RandomAccess;
+
+// plaintext bytes per block
+const BLOCK: u64 = 4096;
+const NONCE: u64 = 24;
+const TAG: u64 = 16;
+// stored bytes for a full block
+const SEALED: u64 = NONCE + BLOCK + TAG;
+
+/// `Storage` adapter that encrypts every file of an inner `Storage` with XChaCha20-Poly1305.
+///
+/// Files are split into blocks of 4096 bytes. Each block is stored as a random 24-byte nonce,
+/// the ciphertext and a 16-byte tag, and is authenticated along with the file name, the block
+/// index and whether it is the last block of the file, so blocks can't be swapped between
+/// positions or files, and a file can't be truncated, without failing to decrypt.
+/// Reads and writes at any offset only touch the blocks they overlap, along with the previous last
+/// block when a write or truncate moves the end of the file to another block.
+///
+/// Every file in the storage must be written through this adapter with the same 32-byte key,
+/// which should come from a random source or a key derivation function rather than a password.
+/// Encryption hides the contents of files but not their names, sizes or when they change,
+/// and a whole file can still be replaced by an older version of itself.
+///
+/// Use `Setup::encrypt()` to encrypt the storage of a database,
+/// or `EncryptedStorage::new()` to encrypt the destination of `DB::copy_to()`.
+pub struct EncryptedStorage<S> where S: RA {
+  storage: Arc<Mutex<Box<dyn Storage<S>>>>,
+  cipher: Arc<XChaCha20Poly1305>,
+}
+
+impl<S> EncryptedStorage<S> where S: RA {
+  pub fn new(storage: Box<dyn Storage<S>>, key: &[u8;32]) -> Self {
+    Self::from_shared(Arc::new(Mutex::new(storage)), key)
+  }
+  fn from_shared(storage: Arc<Mutex<Box<dyn Storage<S>>>>, key: &[u8;32]) -> Self {
+    Self {
+      storage,
+      cipher: Arc::new(XChaCha20Poly1305::new(&(*key).into())),
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl<S> Storage<EncryptedRandomAccess<S>> for EncryptedStorage<S> where S: RA {
+  async fn open(&mut self, name: &str) -> Result<EncryptedRandomAccess<S>,Error> {
+    Ok(EncryptedRandomAccess {
+      store: self.storage.lock().await.open(name).await?,
+      cipher: Arc::clone(&self.cipher),
+      name: name.to_string(),
+    })
+  }
+  async fn remove(&mut self, name: &str) -> Result<(),Error> {
+    self.storage.lock().await.remove(name).await
+  }
+}
+
+impl<S> Setup<S> where S: RA {
+  /// Encrypt every tree and meta file with `key`. See `EncryptedStorage` for details.
+  /// Opening an encrypted database with a different key fails with a decryption error.
+  pub fn encrypt(self, key: &[u8;32]) -> Setup<EncryptedRandomAccess<S>> {
+    Setup {
+      storage: Arc::new(Mutex::new(Box::new(EncryptedStorage::from_shared(self.storage, key)))),
+      fields: self.fields,
+    }
+  }
+}
+
+/// Random access file opened by `EncryptedStorage`.
+pub struct EncryptedRandomAccess<S> where S: RA {
+  store: S,
+  cipher: Arc<XChaCha20Poly1305>,
+  name: String,
+}
+
+impl<S> EncryptedRandomAccess<S> where S: RA {
+  // the last flag works like the STREAM construction: a file cut at a block boundary ends with a
+  // block that was sealed as not being last
+  fn aad(&self, block: u64, last: bool) -> Vec<u8> {
+    let mut aad = self.name.as_bytes().to_vec();
+    aad.extend_from_slice(&block.to_le_bytes());
+    aad.push(last as u8);
+    aad
+  }
+  fn seal(&self, block: u64, last: bool, plain: &[u8]) -> Result<Vec<u8>,Error> {
+    let mut nonce = [0u8;NONCE as usize];
+    if let Err(e) = getrandom::getrandom(&mut nonce) {
+      return EyrosErrorKind::Encrypt { file: self.name.clone(), reason: e.to_string() }.raise();
+    }
+    let payload = Payload { msg: plain, aad: &self.aad(block, last) };
+    match self.cipher.encrypt(&nonce.into(), payload) {
+      Ok(sealed) => Ok([&nonce[..],&sealed].concat()),
+      Err(e) => EyrosErrorKind::Encrypt { file: self.name.clone(), reason: e.to_string() }.raise(),
+    }
+  }
+  fn open_block(&self, block: u64, last: bool, sealed: &[u8]) -> Result<Vec<u8>,Error> {
+    if (sealed.len() as u64) < NONCE + TAG {
+      return EyrosErrorKind::Decrypt { file: self.name.clone(), block }.raise();
+    }
+    let (nonce,msg) = sealed.split_at(NONCE as usize);
+    let nonce: [u8;NONCE as usize] = nonce.try_into()?;
+    let payload = Payload { msg, aad: &self.aad(block, last) };
+    match self.cipher.decrypt(&nonce.into(), payload) {
+      Ok(plain) => Ok(plain),
+      Err(_) => EyrosErrorKind::Decrypt { file: self.name.clone(), block }.raise(),
+    }
+  }
+  // stored length of the inner file and plaintext length
+  async fn lens(&self) -> Result<(u64,u64),Error> {
+    let n = self.store.len().await?;
+    if n == 0 { return Ok((0,0)) }
+    let blocks = (n + SEALED - 1) / SEALED;
+    let last = n - (blocks-1)*SEALED;
+    if last <= NONCE + TAG {
+      return EyrosErrorKind::Decrypt { file: self.name.clone(), block: blocks-1 }.raise();
+    }
+    Ok((n, (blocks-1)*BLOCK + last - NONCE - TAG))
+  }
+  // decrypt every block from `start` up to and including `end`
+  async fn read_blocks(&mut self, start: u64, end: u64, stored: u64) -> Result<Vec<u8>,Error> {
+    let offset = start*SEALED;
+    let sealed = self.store.read(offset, ((end+1)*SEALED).min(stored) - offset).await?;
+    let last = (stored + SEALED - 1) / SEALED - 1;
+    let mut plain = Vec::with_capacity(((end-start+1)*BLOCK) as usize);
+    for (i,chunk) in sealed.chunks(SEALED as usize).enumerate() {
+      let block = start + i as u64;
+      plain.extend(self.open_block(block, block == last, chunk)?);
+    }
+    Ok(plain)
+  }
+}
+
+#[async_trait::async_trait]
+impl<S> RandomAccess for EncryptedRandomAccess<S> where S: RA {
+  type Error = Error;
+  async fn write(&mut self, offset: u64, data: &[u8]) -> Result<(),Error> {
+    if data.is_empty() { return Ok(()) }
+    let (stored,len) = self.lens().await?;
+    // fill any gap past the end with zeros so that every block before the write exists
+    let (offset,data) = if offset > len {
+      let mut buf = vec![0u8;(offset-len) as usize];
+      buf.extend_from_slice(data);
+      (len,buf)
+    } else {
+      (offset,data.to_vec())
+    };
+    let end = offset + data.len() as u64;
+    let (mut start_block,end_block) = (offset/BLOCK, (end-1)/BLOCK);
+    let last = (end.max(len)-1)/BLOCK;
+    if len > 0 {
+      // the previous last block is sealed again as not being last when the file grows past it
+      start_block = start_block.min((len-1)/BLOCK);
+    }
+    // blocks that are only partly overwritten keep the rest of their existing bytes
+    let mut plain = if start_block*BLOCK < len {
+      self.read_blocks(start_block, end_block.min((len-1)/BLOCK), stored).await?
+    } else {
+      vec![]
+    };
+    let from = (offset - start_block*BLOCK) as usize;
+    if plain.len() < from + data.len() {
+      plain.resize(from + data.len(), 0);
+    }
+    plain[from..from+data.len()].copy_from_slice(&data);
+    let overhead = (end_block-start_block+1)*(NONCE+TAG);
+    let mut sealed = Vec::with_capacity(plain.len() + overhead as usize);
+    for (i,chunk) in plain.chunks(BLOCK as usize).enumerate() {
+      let block = start_block + i as u64;
+      sealed.extend(self.seal(block, block == last, chunk)?);
+    }
+    self.store.write(start_block*SEALED, &sealed).await
+  }
+  async fn read(&mut self, offset: u64, length: u64) -> Result<Vec<u8>,Error> {
+    let (stored,len) = self.lens().await?;
+    if offset + length > len {
+      return EyrosErrorKind::EncryptedReadOutOfBounds {
+        file: self.name.clone(), offset, length, len,
+      }.raise();
+    }
+    if length == 0 { return Ok(vec![]) }
+    let start = offset/BLOCK;
+    let plain = self.read_blocks(start, (offset+length-1)/BLOCK, stored).await?;
+    let from = (offset - start*BLOCK) as usize;
+    Ok(plain[from..from+length as usize].to_vec())
+  }
+  // the writer isn't Unpin, so it can't be written to without unsafe code. eyros never calls this.
+  async fn read_to_writer(&mut self, _offset: u64, _length: u64,
+  _buf: &mut (impl AsyncWrite + Send)) -> Result<(),Error> {
+    Err("read_to_writer is not supported for encrypted files".into())
+  }
+  async fn del(&mut self, offset: u64, length: u64) -> Result<(),Error> {
+    let len = self.len().await?;
+    if offset >= len { return Ok(()) }
+    self.write(offset, &vec![0u8;(length.min(len-offset)) as usize]).await
+  }
+  async fn truncate(&mut self, length: u64) -> Result<(),Error> {
+    let (stored,len) = self.lens().await?;
+    if length >= len {
+      return self.write(len, &vec![0u8;(length-len) as usize]).await;
+    }
+    if length == 0 {
+      return self.store.truncate(0).await;
+    }
+    // the block holding the new end is sealed again as the last block
+    let (block,rem) = ((length-1)/BLOCK, (length-1)%BLOCK + 1);
+    let mut plain = self.read_blocks(block, block, stored).await?;
+    plain.truncate(rem as usize);
+    let sealed = self.seal(block, true, &plain)?;
+    self.store.write(block*SEALED, &sealed).await?;
+    self.store.truncate(block*SEALED + sealed.len() as u64).await
+  }
+  async fn len(&self) -> Result<u64,Error> {
+    Ok(self.lens().await?.1)
+  }
+  async fn is_empty(&mut self) -> Result<bool,Error> {
+    Ok(self.len().await? == 0)
+  }
+  async fn sync_all(&mut self) -> Result<(),Error> {
+    self.store.sync_all().await
+  }
+}
@@ -11,11 +11,30 @@ pub struct EyrosError {
 #[derive(Debug)]
 pub enum EyrosErrorKind {
   MetaBitfieldInsufficientBytes {},
+  FormatVersion { version: u32 },
+  MetaChecksum {},
+  ValueInsufficientBytes { expected: usize, received: usize },
   ScalarInBounds {},
+  UnboundedInBounds {},
   IntervalSides { dimension: usize, min: String, max: String },
+  NonFinite { dimension: usize, value: String },
   TreeRemoved { id: TreeId },
   TreeEmpty { id: TreeId, file: String },
-  RemoveIdsMissing { ids: Vec<String> }
+  RemoveIdsMissing { ids: Vec<String> },
+  BulkLoadNotEmpty {},
+  BulkLoadRunCorrupt { file: String, offset: u64 },
+  BulkLoadUnsorted {},
+  NoDimensions {},
+  DimensionMismatch { expected: usize, received: usize },
+  PointType { expected: String, stored: String },
+  CoordKindInvalid { kind: u8 },
+  ExportInvalid { reason: String },
+  ExportVersion { version: u32 },
+  ExportPointType { expected: String, received: String },
+  ExportRowCount { expected: u64, received: u64 },
+  Encrypt { file: String, reason: String },
+  Decrypt { file: String, block: u64 },
+  EncryptedReadOutOfBounds { file: String, offset: u64, length: u64, len: u64 },
 }
 
 impl EyrosErrorKind {
@@ -27,6 +46,12 @@ impl EyrosErrorKind {
   }
 }
 
+impl EyrosError {
+  pub fn kind(&self) -> &EyrosErrorKind {
+    &self.kind
+  }
+}
+
 impl std::error::Error for EyrosError {
   fn backtrace(&'_ self) -> Option<&'_ Backtrace> {
     Some(&self.backtrace)
@@ -39,13 +64,31 @@ impl std::fmt::Display for EyrosError {
       EyrosErrorKind::MetaBitfieldInsufficientBytes {} => {
         write![f, "not enough bytes to construct roots bitfield for Meta"]
       },
+      EyrosErrorKind::ValueInsufficientBytes { expected, received } => {
+        write![f, "expected {} bytes for a value but received {} bytes", expected, received]
+      },
+      EyrosErrorKind::FormatVersion { version } => {
+        write![f, "database has format version {} but this version of eyros reads format \
+          version {} and upgrades format version 1 databases of Tree{{N}} points",
+          version, crate::bytes::FORMAT_VERSION]
+      },
+      EyrosErrorKind::MetaChecksum {} => {
+        write![f, "no meta slot holds a complete meta file with a matching checksum"]
+      },
       EyrosErrorKind::ScalarInBounds {} => {
         write![f, "scalar found in bounds"]
       },
+      EyrosErrorKind::UnboundedInBounds {} => {
+        write![f, "unbounded coordinate found in bounds"]
+      },
       EyrosErrorKind::IntervalSides { dimension, min, max } => {
         write![f, "!(min <= max) dimension {} for Coord::Interval({:?},{:?})",
           dimension, min, max]
       },
+      EyrosErrorKind::NonFinite { dimension, value } => {
+        write![f, "non-finite value {} in dimension {}. use Coord::From, Coord::To, or Coord::All \
+          for ranges without a limit", value, dimension]
+      },
       EyrosErrorKind::TreeRemoved { id } => {
         write![f, "attempted to load tree scheduled for removal with id={}", id]
       },
@@ -55,6 +98,51 @@ impl std::fmt::Display for EyrosError {
       EyrosErrorKind::RemoveIdsMissing { ids } => {
         write![f, "ids not found during remove(): {}", ids.join(", ")]
       },
+      EyrosErrorKind::BulkLoadNotEmpty {} => {
+        write![f, "bulk_load() requires an empty database"]
+      },
+      EyrosErrorKind::BulkLoadRunCorrupt { file, offset } => {
+        write![f, "unexpected end of bulk load run file={} at offset={}", file, offset]
+      },
+      EyrosErrorKind::BulkLoadUnsorted {} => {
+        write![f, "bulk_load() can't sort points that Point::cmp_dim() doesn't compare"]
+      },
+      EyrosErrorKind::NoDimensions {} => {
+        write![f, "point has no dimensions"]
+      },
+      EyrosErrorKind::DimensionMismatch { expected, received } => {
+        write![f, "expected a point with {} dimensions but received {} dimensions",
+          expected, received]
+      },
+      EyrosErrorKind::PointType { expected, stored } => {
+        write![f, "database stores points with scalars {} but was opened with scalars {}",
+          stored, expected]
+      },
+      EyrosErrorKind::CoordKindInvalid { kind } => {
+        write![f, "unknown coordinate kind={} while parsing a point", kind]
+      },
+      EyrosErrorKind::ExportInvalid { reason } => {
+        write![f, "invalid export stream: {}", reason]
+      },
+      EyrosErrorKind::ExportVersion { version } => {
+        write![f, "unsupported export format version={}", version]
+      },
+      EyrosErrorKind::ExportPointType { expected, received } => {
+        write![f, "expected exported points with scalars {} but received {}", expected, received]
+      },
+      EyrosErrorKind::ExportRowCount { expected, received } => {
+        write![f, "export stream ended with {} rows but declared {} rows", received, expected]
+      },
+      EyrosErrorKind::Encrypt { file, reason } => {
+        write![f, "could not encrypt {}: {}", file, reason]
+      },
+      EyrosErrorKind::Decrypt { file, block } => {
+        write![f, "could not decrypt block {} of {}: wrong key or corrupted file", block, file]
+      },
+      EyrosErrorKind::EncryptedReadOutOfBounds { file, offset, length, len } => {
+        write![f, "read of {} bytes at offset {} is past the end of {} (length {})",
+          length, offset, file, len]
+      },
     }
   }
 }
@@ -0,0 +1,364 @@
+use crate::{DB,Tree,Point,Value,Setup,Row,Error,EyrosErrorKind,RA,Transaction};
+use async_std::io::{Read,Write,BufReader,BufWriter,prelude::*};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+
+const MAGIC: &[u8;8] = b"EYROSEXP";
+const VERSION: u32 = 2;
+const ROW: u8 = 1;
+const END: u8 = 0;
+
+/// Header of the stream written by `DB::export()`.
+///
+/// The stream is a versioned binary format where every integer is little-endian:
+///
+/// * magic bytes `EYROSEXP`
+/// * `u32` format version, currently 2
+/// * `u32` length of the header block, followed by the header block:
+///   * `u32` number of dimensions, or 0 when unknown
+///   * `u16` number of scalar kinds, one per dimension, each a `u16` length and utf-8 name
+///     from `ScalarKind::name()`
+///   * `u16` number of setup fields, each a `u16` length and utf-8 name followed by a `u64`
+/// * one record per row: the byte `1`, a `u32` length and the point, then a `u32` length
+///   and the encoded value
+/// * the byte `0` followed by a `u64` count of the rows written
+///
+/// Readers skip any bytes at the end of the header block that they don't know about,
+/// so later versions can add to it.
+///
+/// A point is its coordinates in order. Each coordinate is a byte for the variant
+/// (0 `Scalar`, 1 `Interval`, 2 `From`, 3 `To`, 4 `All`) followed by its values, min first.
+/// Values of the built-in scalar kinds are little-endian at their fixed width, and
+/// `ScalarKind::Other` values are a `u32` length and the `ToBytes` encoding of the scalar.
+/// Point types without scalar kinds, such as custom `Point` implementations, write the
+/// encoding of `Point::write_point_bytes()` after a `u32` length instead.
+///
+/// Values use their `ToBytes` encoding. They are length-prefixed so that they can be read as a
+/// different type with the same encoding, such as the byte values of the wasm build and `Vec<u8>`.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ExportHeader {
+  pub version: u32,
+  pub dimensions: usize,
+  /// Scalar kind of each dimension. Imports require the same kinds.
+  pub scalars: Vec<ScalarKind>,
+  /// Setup fields of the exporting database, such as `branch_factor` and `max_records`.
+  pub fields: Vec<(String,u64)>,
+}
+
+/// Result of `DB::import()`.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ImportReport {
+  pub header: ExportHeader,
+  /// Number of rows inserted.
+  pub rows: u64,
+}
+
+/// Kind of the scalar in one dimension of a point, as recorded in type descriptors such as
+/// the header of `DB::export()`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ScalarKind {
+  F32, F64,
+  U8, U16, U32, U64, U128,
+  I8, I16, I32, I64, I128,
+  /// A scalar type other than the built-in integers and floats.
+  Other,
+}
+
+const SCALAR_KINDS: [(ScalarKind,&str);13] = [
+  (ScalarKind::F32,"f32"), (ScalarKind::F64,"f64"),
+  (ScalarKind::U8,"u8"), (ScalarKind::U16,"u16"), (ScalarKind::U32,"u32"),
+  (ScalarKind::U64,"u64"), (ScalarKind::U128,"u128"),
+  (ScalarKind::I8,"i8"), (ScalarKind::I16,"i16"), (ScalarKind::I32,"i32"),
+  (ScalarKind::I64,"i64"), (ScalarKind::I128,"i128"),
+  (ScalarKind::Other,"other"),
+];
+
+impl ScalarKind {
+  /// Name of this kind, such as `"f32"`, or `"other"`.
+  pub fn name(&self) -> &'static str {
+    SCALAR_KINDS.iter().find(|(k,_)| k == self).unwrap().1
+  }
+  /// Kind with the given `name()`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    SCALAR_KINDS.iter().find(|(_,n)| *n == name).map(|(k,_)| *k)
+  }
+  /// Names of `kinds` separated by commas, such as `"f32,f32"`.
+  pub fn describe(kinds: &[Self]) -> String {
+    if kinds.is_empty() { return "point codec".to_string() }
+    kinds.iter().map(|k| k.name()).collect::<Vec<_>>().join(",")
+  }
+  // index in SCALAR_KINDS, which the meta stores to check the point type on open.
+  // keep the table in this order so stored codes stay valid.
+  pub(crate) fn code(&self) -> u8 {
+    SCALAR_KINDS.iter().position(|(k,_)| k == self).unwrap() as u8
+  }
+  pub(crate) fn from_code(code: u8) -> Option<Self> {
+    SCALAR_KINDS.get(code as usize).map(|(k,_)| *k)
+  }
+}
+
+// bytes of the export format at the start of src, or an error when src is too short
+pub(crate) fn take_export(src: &[u8], len: usize) -> Result<&[u8],Error> {
+  match src.get(..len) {
+    Some(bytes) => Ok(bytes),
+    None => EyrosErrorKind::ExportInvalid { reason: "row too short".to_string() }.raise(),
+  }
+}
+
+// length-prefixed bytes for values without a fixed layout in the export format
+pub(crate) fn write_other(bytes: &[u8], buf: &mut Vec<u8>) {
+  buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+  buf.extend_from_slice(bytes);
+}
+
+pub(crate) fn read_other(src: &[u8]) -> Result<(usize,&[u8]),Error> {
+  let len = u32::from_le_bytes(take_export(src, 4)?.try_into()?) as usize;
+  Ok((4+len,take_export(&src[4..], len)?))
+}
+
+async fn read_u32<R>(reader: &mut R) -> Result<u32,Error> where R: Read+Unpin {
+  let mut buf = [0u8;4];
+  reader.read_exact(&mut buf).await?;
+  Ok(u32::from_le_bytes(buf))
+}
+
+async fn read_u64<R>(reader: &mut R) -> Result<u64,Error> where R: Read+Unpin {
+  let mut buf = [0u8;8];
+  reader.read_exact(&mut buf).await?;
+  Ok(u64::from_le_bytes(buf))
+}
+
+async fn read_block<R>(reader: &mut R, len: usize) -> Result<Vec<u8>,Error> where R: Read+Unpin {
+  let mut buf = vec![0u8;len];
+  reader.read_exact(&mut buf).await?;
+  Ok(buf)
+}
+
+fn push_str(buf: &mut Vec<u8>, s: &str) {
+  buf.extend_from_slice(&(s.len() as u16).to_le_bytes());
+  buf.extend_from_slice(s.as_bytes());
+}
+
+// read a length-prefixed string from the header block at `offset`
+fn take_str(src: &[u8], offset: &mut usize) -> Result<String,Error> {
+  let len = u16::from_le_bytes(take(src, offset, 2)?.try_into()?) as usize;
+  Ok(String::from_utf8(take(src, offset, len)?.to_vec())?)
+}
+
+fn take<'a>(src: &'a [u8], offset: &mut usize, len: usize) -> Result<&'a [u8],Error> {
+  if *offset + len > src.len() {
+    return EyrosErrorKind::ExportInvalid { reason: "header block too short".to_string() }.raise();
+  }
+  *offset += len;
+  Ok(&src[*offset-len..*offset])
+}
+
+impl ExportHeader {
+  fn new<P>(dimensions: usize, setup: &crate::SetupFields) -> Self where P: Point {
+    Self {
+      version: VERSION,
+      dimensions,
+      scalars: P::scalar_kinds(dimensions),
+      fields: vec![
+        ("branch_factor".to_string(), setup.branch_factor as u64),
+        ("max_depth".to_string(), setup.max_depth as u64),
+        ("max_records".to_string(), setup.max_records as u64),
+        ("ext_records".to_string(), setup.ext_records as u64),
+        ("inline".to_string(), setup.inline as u64),
+        ("inline_max_bytes".to_string(), setup.inline_max_bytes as u64),
+        ("rebuild_depth".to_string(), setup.rebuild_depth as u64),
+        ("total_order".to_string(), setup.total_order as u64),
+      ],
+    }
+  }
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut block = vec![];
+    block.extend_from_slice(&(self.dimensions as u32).to_le_bytes());
+    block.extend_from_slice(&(self.scalars.len() as u16).to_le_bytes());
+    for kind in self.scalars.iter() {
+      push_str(&mut block, kind.name());
+    }
+    block.extend_from_slice(&(self.fields.len() as u16).to_le_bytes());
+    for (name,value) in self.fields.iter() {
+      push_str(&mut block, name);
+      block.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut buf = MAGIC.to_vec();
+    buf.extend_from_slice(&self.version.to_le_bytes());
+    buf.extend_from_slice(&(block.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&block);
+    buf
+  }
+  /// Read the header from the start of an exported stream, leaving `reader` at the first row.
+  pub async fn read<R>(reader: &mut R) -> Result<Self,Error> where R: Read+Unpin {
+    let mut magic = [0u8;8];
+    reader.read_exact(&mut magic).await?;
+    if &magic != MAGIC {
+      return EyrosErrorKind::ExportInvalid { reason: "missing magic bytes".to_string() }.raise();
+    }
+    let version = read_u32(reader).await?;
+    // version 1 wrote points with the internal codec of the exporting build
+    if version != VERSION {
+      return EyrosErrorKind::ExportVersion { version }.raise();
+    }
+    let len = read_u32(reader).await? as usize;
+    let block = read_block(reader, len).await?;
+    let mut offset = 0;
+    let dimensions = u32::from_le_bytes(take(&block, &mut offset, 4)?.try_into()?) as usize;
+    let n = u16::from_le_bytes(take(&block, &mut offset, 2)?.try_into()?);
+    let mut scalars = Vec::with_capacity(n as usize);
+    for _ in 0..n {
+      let name = take_str(&block, &mut offset)?;
+      match ScalarKind::from_name(&name) {
+        Some(kind) => scalars.push(kind),
+        None => {
+          return EyrosErrorKind::ExportInvalid { reason: format!["unknown scalar kind {}", name] }
+            .raise();
+        },
+      }
+    }
+    let n = u16::from_le_bytes(take(&block, &mut offset, 2)?.try_into()?);
+    let mut fields = Vec::with_capacity(n as usize);
+    for _ in 0..n {
+      let name = take_str(&block, &mut offset)?;
+      let value = u64::from_le_bytes(take(&block, &mut offset, 8)?.try_into()?);
+      fields.push((name,value));
+    }
+    Ok(Self { version, dimensions, scalars, fields })
+  }
+  /// Apply the setup fields from this header to `setup`, for restoring an export into a new database
+  /// with the settings of the original.
+  pub fn setup<S>(&self, mut setup: Setup<S>) -> Setup<S> where S: RA {
+    for (name,value) in self.fields.iter() {
+      let n = *value as usize;
+      setup = match name.as_str() {
+        "branch_factor" => setup.branch_factor(n),
+        "max_depth" => setup.max_depth(n),
+        "max_records" => setup.max_records(n),
+        "ext_records" => setup.ext_records(n),
+        "inline" => setup.inline(n),
+        "inline_max_bytes" => setup.inline_max_bytes(n),
+        "rebuild_depth" => setup.rebuild_depth(n),
+        "total_order" => setup.total_order(n != 0),
+        _ => setup,
+      };
+    }
+    setup
+  }
+}
+
+impl<S,T,P,V> DB<S,T,P,V>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  /// Write every `(point,value)` row to `writer` in the portable format described by
+  /// `ExportHeader`, along with the setup fields and scalar kinds of this database.
+  /// Rows are read tree by tree from the roots present when the export starts.
+  /// Returns the number of rows written.
+  pub async fn export<W>(&self, writer: W) -> Result<u64,Error> where W: Write+Unpin {
+    let mut writer = BufWriter::new(writer);
+    let (roots,dimensions) = {
+      let meta = self.meta.read().await;
+      (meta.roots.clone(), meta.dimensions)
+    };
+    let header = ExportHeader::new::<P>(dimensions, &self.fields);
+    writer.write_all(&header.to_bytes()).await?;
+    let mut count = 0u64;
+    let mut cursors = roots.iter().filter_map(|r| r.as_ref().map(|r| r.id))
+      .collect::<VecDeque<_>>();
+    while let Some(id) = cursors.pop_front() {
+      let (rows,refs) = self.trees.get(&id).await?.lock().await.list();
+      cursors.extend(refs.iter().map(|r| r.id));
+      for (point,value) in rows.iter() {
+        let mut pbytes = vec![];
+        point.write_export(&mut pbytes)?;
+        let vbytes = value.to_bytes()?;
+        let mut buf = Vec::with_capacity(9 + pbytes.len() + vbytes.len());
+        buf.push(ROW);
+        write_other(&pbytes, &mut buf);
+        write_other(&vbytes, &mut buf);
+        writer.write_all(&buf).await?;
+        count += 1;
+      }
+    }
+    writer.write_all(&[END]).await?;
+    writer.write_all(&count.to_le_bytes()).await?;
+    writer.flush().await?;
+    Ok(count)
+  }
+
+  /// Insert every row from a stream written by `export()` in a transaction, in batches of
+  /// `stream_batch_size` rows, and commit. The exported scalar kinds and number of dimensions must
+  /// match this database. When the stream can't be read, the transaction is rolled back so that
+  /// none of its rows are inserted.
+  /// Values are decoded with the `FromBytes` of `V`, which may differ from the exported value type.
+  pub async fn import<R>(&mut self, reader: R) -> Result<ImportReport,Error> where R: Read+Unpin {
+    let mut reader = BufReader::new(reader);
+    let header = ExportHeader::read(&mut reader).await?;
+    let dimensions = self.meta.read().await.dimensions;
+    if dimensions > 0 && header.dimensions > 0 && dimensions != header.dimensions {
+      return EyrosErrorKind::DimensionMismatch {
+        expected: dimensions,
+        received: header.dimensions,
+      }.raise();
+    }
+    let scalars = P::scalar_kinds(dimensions.max(header.dimensions));
+    if header.scalars != scalars {
+      return EyrosErrorKind::ExportPointType {
+        expected: ScalarKind::describe(&scalars),
+        received: ScalarKind::describe(&header.scalars),
+      }.raise();
+    }
+    let size = self.fields.stream_batch_size.max(1);
+    let mut tx = self.begin().await?;
+    match import_rows(&mut tx, &mut reader, header.dimensions, size).await {
+      Ok(rows) => {
+        tx.commit().await?;
+        Ok(ImportReport { header, rows })
+      },
+      Err(err) => {
+        tx.rollback().await?;
+        Err(err)
+      },
+    }
+  }
+}
+
+async fn import_rows<S,T,P,V,R>(
+  tx: &mut Transaction<'_,S,T,P,V>, reader: &mut R, dimensions: usize, size: usize
+) -> Result<u64,Error>
+where S: RA, P: Point, V: Value, T: Tree<P,V>, R: Read+Unpin {
+  let mut batch = Vec::with_capacity(size);
+  let mut count = 0u64;
+  loop {
+    let mut tag = [0u8;1];
+    reader.read_exact(&mut tag).await?;
+    match tag[0] {
+      ROW => {
+        let len = read_u32(reader).await? as usize;
+        let (_,point) = P::read_export(&read_block(reader, len).await?, dimensions)?;
+        let len = read_u32(reader).await? as usize;
+        let (_,value) = V::from_bytes(&read_block(reader, len).await?)?;
+        batch.push(Row::Insert(point,value));
+        count += 1;
+      },
+      END => {
+        let expected = read_u64(reader).await?;
+        if expected != count {
+          return EyrosErrorKind::ExportRowCount { expected, received: count }.raise();
+        }
+        break;
+      },
+      t => {
+        return EyrosErrorKind::ExportInvalid { reason: format!["unknown record tag {}", t] }
+          .raise();
+      },
+    }
+    if batch.len() >= size {
+      tx.batch(&batch).await?;
+      batch.clear();
+    }
+  }
+  if !batch.is_empty() {
+    tx.batch(&batch).await?;
+  }
+  Ok(count)
+}
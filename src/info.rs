@@ -0,0 +1,107 @@
+use crate::{DB,Tree,Point,Value,Error,RA,TreeId};
+use std::collections::VecDeque;
+
+/// Summary of the trees in a database, from `DB::info()`.
+#[derive(Debug,Clone)]
+pub struct Info<P> where P: Point {
+  /// Every live root in the database meta.
+  pub roots: Vec<RootInfo<P>>,
+  /// Number of trees reachable from the roots, including the roots.
+  pub trees: usize,
+  /// Number of records in every tree.
+  pub records: usize,
+  /// Number of trees at each depth, where `depths[0]` counts the roots and `depths[1]` counts
+  /// the external trees they refer to, and so on.
+  pub depths: Vec<usize>,
+  /// Records stored inside root trees.
+  pub inline_records: usize,
+  /// Records stored in external trees below the roots.
+  pub external_records: usize,
+  /// Bytes used by both meta slots and the tree files. Trees written since the last sync
+  /// are counted by their encoded size.
+  pub bytes: u64,
+  /// Smallest point that covers every root, or `None` for an empty database or for a point type
+  /// without `Point::union()`.
+  pub bounds: Option<P>,
+}
+
+impl<P> Info<P> where P: Point {
+  /// Fraction of records stored inside root trees, or `0.0` for an empty database.
+  pub fn inline_ratio(&self) -> f64 {
+    if self.records == 0 { return 0.0 }
+    (self.inline_records as f64) / (self.records as f64)
+  }
+}
+
+/// Summary of the trees under one root, part of `Info`.
+#[derive(Debug,Clone)]
+pub struct RootInfo<P> where P: Point {
+  /// Position of this root in the database meta.
+  pub index: usize,
+  /// Tree id of the root.
+  pub id: TreeId,
+  /// Number of records in this root and the trees below it.
+  pub records: usize,
+  /// Number of trees in this root, including the root itself.
+  pub trees: usize,
+  /// Deepest level of external trees below this root, where 0 means the root has no refs.
+  pub depth: usize,
+  /// Bounds of the root from the database meta.
+  pub bounds: P,
+}
+
+pub async fn info<S,T,P,V>(db: &DB<S,T,P,V>) -> Result<Info<P>,Error>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  let roots = db.meta.read().await.roots.clone();
+  let mut info: Info<P> = Info {
+    roots: vec![],
+    trees: 0,
+    records: 0,
+    depths: vec![],
+    inline_records: 0,
+    external_records: 0,
+    bytes: db.meta_file.lock().await.len().await?,
+    bounds: None,
+  };
+  for (index,root) in roots.iter().enumerate() {
+    let r = match root {
+      Some(r) => r,
+      None => continue,
+    };
+    let mut rinfo = RootInfo {
+      index,
+      id: r.id,
+      records: 0,
+      trees: 0,
+      depth: 0,
+      bounds: r.bounds.clone(),
+    };
+    let mut cursors = VecDeque::new();
+    cursors.push_back((0,r.id));
+    while let Some((depth,id)) = cursors.pop_front() {
+      let (rows,refs) = db.trees.get(&id).await?.lock().await.list();
+      rinfo.records += rows.len();
+      rinfo.trees += 1;
+      rinfo.depth = rinfo.depth.max(depth);
+      if depth == 0 {
+        info.inline_records += rows.len();
+      } else {
+        info.external_records += rows.len();
+      }
+      if info.depths.len() <= depth {
+        info.depths.resize(depth+1, 0);
+      }
+      info.depths[depth] += 1;
+      info.bytes += db.trees.stored_len(&id).await?;
+      cursors.extend(refs.iter().map(|r| (depth+1,r.id)));
+    }
+    info.trees += rinfo.trees;
+    info.records += rinfo.records;
+    info.bounds = match info.roots.is_empty() {
+      true => Some(r.bounds.clone()),
+      false => info.bounds.and_then(|b| b.union(&r.bounds)),
+    };
+    info.roots.push(rinfo);
+  }
+  Ok(info)
+}
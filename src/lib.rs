@@ -16,36 +16,174 @@ pub use query::QTrace;
 mod unfold;
 mod tree_file;
 use tree_file::TreeFile;
+mod meta_file;
+use meta_file::MetaFile;
+mod upgrade;
 mod value;
 pub use value::Value;
+#[cfg(feature="serde")] mod serde_value;
+#[cfg(feature="serde")] pub use serde_value::{SerdeValue,SerdeId,SelfId};
 #[cfg(feature="wasm")]
 mod wasm;
 mod batch;
-pub use batch::{BatchFields,BatchOptions};
+pub use batch::{BatchFields,BatchOptions,BatchReport};
 mod debugger;
-pub use debugger::Debugger;
+pub use debugger::{Debugger,DebugEvent};
+mod transaction;
+pub use transaction::Transaction;
+mod bulk;
+mod stats;
+pub use stats::Stats;
+use stats::Counters;
+mod info;
+pub use info::{Info,RootInfo};
+mod dump;
+pub use dump::{Dump,DumpTree,DumpNode,DumpRef};
+mod verify;
+pub use verify::{Verify,Problem};
+mod export;
+pub use export::{ExportHeader,ImportReport,ScalarKind};
+mod copy;
+pub use copy::CopyReport;
+#[cfg(feature="encryption")] mod encrypt;
+#[cfg(feature="encryption")] pub use encrypt::{EncryptedStorage,EncryptedRandomAccess};
 
 use async_std::{sync::{Arc,Mutex,RwLock}};
 use random_access_storage::RandomAccess;
 use desert::{ToBytes,FromBytes,CountBytes};
-use core::ops::{Add,Div};
 use std::fmt::Debug;
-use std::collections::VecDeque;
+use std::collections::{HashMap,VecDeque};
+use futures::stream::{Stream,StreamExt};
 
 /// All coordinate values must implement this collection of traits.
-pub trait Scalar: Clone+PartialOrd+From<u8>+Debug
-  +Send+Sync+'static+PartialEq
-  +ToBytes+CountBytes+FromBytes
-  +Add<Output=Self>+Div<Output=Self> {}
-impl Scalar for f32 {}
-impl Scalar for f64 {}
-impl Scalar for u8 {}
-impl Scalar for u16 {}
-impl Scalar for u32 {}
-impl Scalar for u64 {}
-impl Scalar for i16 {}
-impl Scalar for i32 {}
-impl Scalar for i64 {}
+///
+/// `Scalar` is implemented for the built-in integer and float types.
+/// Newtypes such as timestamps or fixed-point degrees can implement `Scalar` by choosing a
+/// midpoint, which places the pivots that split a tree, and by forwarding the byte codecs
+/// to the wrapped value:
+///
+/// ```rust
+/// use eyros::Scalar;
+/// use desert::{ToBytes,FromBytes,CountBytes};
+///
+/// /// Milliseconds since the unix epoch.
+/// #[derive(Debug,Clone,Copy,PartialEq,PartialOrd)]
+/// struct Timestamp(i64);
+///
+/// impl Scalar for Timestamp {
+///   fn midpoint(a: &Self, b: &Self) -> Self {
+///     Timestamp(<i64 as Scalar>::midpoint(&a.0, &b.0))
+///   }
+/// }
+///
+/// impl ToBytes for Timestamp {
+///   fn to_bytes(&self) -> Result<Vec<u8>,desert::Error> {
+///     self.0.to_bytes()
+///   }
+///   fn write_bytes(&self, buf: &mut [u8]) -> Result<usize,desert::Error> {
+///     self.0.write_bytes(buf)
+///   }
+/// }
+///
+/// impl CountBytes for Timestamp {
+///   fn count_bytes(&self) -> usize {
+///     self.0.count_bytes()
+///   }
+///   fn count_from_bytes(buf: &[u8]) -> Result<usize,desert::Error> {
+///     i64::count_from_bytes(buf)
+///   }
+/// }
+///
+/// impl FromBytes for Timestamp {
+///   fn from_bytes(src: &[u8]) -> Result<(usize,Self),desert::Error> {
+///     let (size,x) = i64::from_bytes(src)?;
+///     Ok((size,Timestamp(x)))
+///   }
+/// }
+///
+/// assert_eq![Timestamp::midpoint(&Timestamp(3), &Timestamp(6)), Timestamp(4)];
+/// ```
+///
+/// A fixed-point type such as `struct Degrees(i32)` in units of 1e-7 degrees works the same way.
+pub trait Scalar: Clone+PartialOrd+Debug
+  +Send+Sync+'static
+  +ToBytes+CountBytes+FromBytes {
+  /// Return a value between `a` and `b`, inclusive, used to place pivots when building trees.
+  fn midpoint(a: &Self, b: &Self) -> Self;
+  /// Order two values that `partial_cmp()` can't compare, used when building trees with
+  /// `Setup::total_order()`. By default, values that can't be compared with themselves,
+  /// like NaN, sort after every other value.
+  fn cmp_total(&self, other: &Self) -> std::cmp::Ordering {
+    self.partial_cmp(other).unwrap_or_else(|| {
+      self.partial_cmp(self).is_none().cmp(&other.partial_cmp(other).is_none())
+    })
+  }
+  /// Whether this value is a finite number. Only floats can be NaN or infinite.
+  fn is_finite(&self) -> bool { true }
+  /// Kind of this scalar in type descriptors, such as the header of `DB::export()`.
+  /// Custom scalars are `ScalarKind::Other` unless they set their own kind.
+  const KIND: ScalarKind = ScalarKind::Other;
+  /// Append this value to `buf` in the export format. The built-in types are written
+  /// little-endian at their fixed width and the default writes a `u32` length followed by the
+  /// `ToBytes` encoding.
+  fn write_export(&self, buf: &mut Vec<u8>) -> Result<(),Error> {
+    export::write_other(&self.to_bytes()?, buf);
+    Ok(())
+  }
+  /// Read a value written by `write_export()`, returning the number of bytes read.
+  fn read_export(src: &[u8]) -> Result<(usize,Self),Error> {
+    let (size,bytes) = export::read_other(src)?;
+    Ok((size,Self::from_bytes(bytes)?.1))
+  }
+}
+
+// export methods for the built-in scalars, used inside their Scalar impls
+macro_rules! scalar_export {
+  ($T:ty, $kind:ident) => {
+    const KIND: ScalarKind = ScalarKind::$kind;
+    fn write_export(&self, buf: &mut Vec<u8>) -> Result<(),Error> {
+      buf.extend_from_slice(&self.to_le_bytes());
+      Ok(())
+    }
+    fn read_export(src: &[u8]) -> Result<(usize,Self),Error> {
+      let n = std::mem::size_of::<$T>();
+      let bytes = export::take_export(src, n)?;
+      Ok((n,<$T>::from_le_bytes(std::convert::TryInto::try_into(bytes)?)))
+    }
+  };
+}
+
+// an infinite or NaN end has no midpoint with the other end (-inf and +inf would give NaN), so
+// take the other end when it is finite. halving each side before adding keeps finite sums from
+// overflowing.
+macro_rules! impl_scalar_float {
+  ($($T:ty: $kind:ident),+) => {$(
+    impl Scalar for $T {
+      fn midpoint(a: &Self, b: &Self) -> Self {
+        match (a.is_finite(), b.is_finite()) {
+          (true,true) => a/2.0 + b/2.0,
+          (true,false) => *a,
+          (false,_) => *b,
+        }
+      }
+      fn is_finite(&self) -> bool { <$T>::is_finite(*self) }
+      scalar_export![$T, $kind];
+    }
+  )+}
+}
+impl_scalar_float![f32: F32, f64: F64];
+
+// halve each side before adding so the sum can't overflow, then add back the remainders
+macro_rules! impl_scalar_int {
+  ($($T:ty: $kind:ident),+) => {$(
+    impl Scalar for $T {
+      fn midpoint(a: &Self, b: &Self) -> Self { a/2 + b/2 + (a%2 + b%2)/2 }
+      scalar_export![$T, $kind];
+    }
+  )+}
+}
+impl_scalar_int![u8: U8, u16: U16, u32: U32, u64: U64, u128: U128,
+  i8: I8, i16: I16, i32: I32, i64: I64, i128: I128];
 
 #[doc(hidden)] pub trait RA: RandomAccess<Error=Error>+Unpin+Send+Sync+'static {}
 impl<S> RA for S where S: RandomAccess<Error=Error>+Unpin+Send+Sync+'static {}
@@ -53,10 +191,95 @@ impl<S> RA for S where S: RandomAccess<Error=Error>+Unpin+Send+Sync+'static {}
 /// The `Coord` enum represents the value for a dimension instead of a `Point` tuple.
 /// Use `Coord::Scalar(x)` to represent a single value and `Coord::Interval(min,max)`
 /// to represent a range of values from `min` to `max`, inclusive.
+/// Ranges without an end use `Coord::From(min)` for every value at or above `min`,
+/// `Coord::To(max)` for every value at or below `max`, and `Coord::All` for every value.
 #[derive(Debug,Clone,PartialEq,PartialOrd)]
 pub enum Coord<X> where X: Scalar {
   Scalar(X),
-  Interval(X,X)
+  Interval(X,X),
+  From(X),
+  To(X),
+  All,
+}
+
+impl<X> Coord<X> where X: Scalar {
+  /// Lower end of this coordinate, or `None` when there is no lower limit.
+  pub fn min(&self) -> Option<&X> {
+    match self {
+      Coord::Scalar(x) => Some(x),
+      Coord::Interval(x,_) => Some(x),
+      Coord::From(x) => Some(x),
+      Coord::To(_) => None,
+      Coord::All => None,
+    }
+  }
+  /// Upper end of this coordinate, or `None` when there is no upper limit.
+  pub fn max(&self) -> Option<&X> {
+    match self {
+      Coord::Scalar(x) => Some(x),
+      Coord::Interval(_,x) => Some(x),
+      Coord::From(_) => None,
+      Coord::To(x) => Some(x),
+      Coord::All => None,
+    }
+  }
+  /// Build a range from a lower and upper end where `None` is unbounded.
+  pub fn from_ends(min: Option<X>, max: Option<X>) -> Self {
+    match (min,max) {
+      (Some(min),Some(max)) => Coord::Interval(min,max),
+      (Some(min),None) => Coord::From(min),
+      (None,Some(max)) => Coord::To(max),
+      (None,None) => Coord::All,
+    }
+  }
+  /// Append this coordinate to `buf` in the export format: a byte for the variant
+  /// (0 `Scalar`, 1 `Interval`, 2 `From`, 3 `To`, 4 `All`) followed by its values
+  /// with `Scalar::write_export()`.
+  pub fn write_export(&self, buf: &mut Vec<u8>) -> Result<(),Error> {
+    buf.push(bytes::coord_kind(self));
+    match self {
+      Coord::Scalar(x) | Coord::From(x) | Coord::To(x) => x.write_export(buf),
+      Coord::Interval(min,max) => {
+        min.write_export(buf)?;
+        max.write_export(buf)
+      },
+      Coord::All => Ok(()),
+    }
+  }
+  /// Read a coordinate written by `write_export()`, returning the number of bytes read.
+  pub fn read_export(src: &[u8]) -> Result<(usize,Self),Error> {
+    let kind = export::take_export(src, 1)?[0];
+    let mut offset = 1;
+    let read = |offset: &mut usize| -> Result<X,Error> {
+      let (size,x) = X::read_export(&src[*offset..])?;
+      *offset += size;
+      Ok(x)
+    };
+    let c = match kind {
+      0 => Coord::Scalar(read(&mut offset)?),
+      1 => {
+        let min = read(&mut offset)?;
+        Coord::Interval(min, read(&mut offset)?)
+      },
+      2 => Coord::From(read(&mut offset)?),
+      3 => Coord::To(read(&mut offset)?),
+      4 => Coord::All,
+      _ => return EyrosErrorKind::CoordKindInvalid { kind }.raise(),
+    };
+    Ok((offset,c))
+  }
+  /// Smallest range that covers both coordinates.
+  pub fn union(&self, other: &Self) -> Self {
+    let min = match (self.min(),other.min()) {
+      (Some(a),Some(b)) => Some(if cmp_scalar(b,a).is_lt() { b.clone() } else { a.clone() }),
+      _ => None,
+    };
+    let max = match (self.max(),other.max()) {
+      (Some(a),Some(b)) => Some(if cmp_scalar(b,a).is_gt() { b.clone() } else { a.clone() }),
+      _ => None,
+    };
+    Coord::from_ends(min,max)
+  }
 }
 
 /// The `Point` trait represents the geometric coordinates of a feature.
@@ -64,15 +287,120 @@ pub enum Coord<X> where X: Scalar {
 /// `Points` and `Bounds` are converted between each other with the `to_bounds()` and
 /// `from_bounds()` methods.
 #[async_trait::async_trait]
-pub trait Point: 'static+Overlap+Clone+Send+Sync+Debug{
+pub trait Point: 'static+Overlap+Clone+Send+Sync+Debug {
   type Bounds: Clone+Send+Sync+Debug+ToBytes+FromBytes+CountBytes+Overlap;
+  /// Number of dimensions shared by every point of this type,
+  /// or `None` when the number of dimensions is only known at runtime.
+  const DIMENSIONS: Option<usize> = None;
   /// Convert to a `Bounds`, which may not be possible.
   fn to_bounds(&self) -> Result<Self::Bounds,Error>;
   /// Convert a `Bounds` into a `Point`.
   fn from_bounds(bounds: &Self::Bounds) -> Self;
   /// Return an Error when the current `Point` is invalid.
-  /// For example, for an interval `(min,max)` it may be that `min > max`.
+  /// For example, for an interval `(min,max)` it may be that `min > max`,
+  /// or a float coordinate may be NaN or infinite.
   fn check(&self) -> Result<(),Error>;
+  /// Check a point for a database built with `Setup::total_order()`, where NaN and infinite
+  /// coordinates are allowed. The default is `check()`.
+  fn check_total_order(&self) -> Result<(),Error> { self.check() }
+  /// Compare two points along dimension `dim`, using the minimum of intervals.
+  /// `bulk_load()` and `batch_stream()` sort rows with this method and return an error when it
+  /// returns `None`, which the default always does.
+  fn cmp_dim(&self, _other: &Self, _dim: usize) -> Option<std::cmp::Ordering> { None }
+  /// Number of dimensions in this point. Defaults to `DIMENSIONS`, or 0 when that is `None`.
+  fn dimensions(&self) -> usize { Self::DIMENSIONS.unwrap_or(0) }
+  /// Smallest point that covers both points in every dimension,
+  /// or `None` (the default) when the point type has no such union.
+  fn union(&self, _other: &Self) -> Option<Self> { None }
+  /// Size in bytes of this point as written to tree refs and the database meta.
+  /// The default stores the point as its `Bounds`, which rules out points that
+  /// `to_bounds()` can't convert.
+  fn count_point_bytes(&self) -> usize {
+    self.to_bounds().map(|b| b.count_bytes()).unwrap_or(0)
+  }
+  /// Write this point into `buf`, returning the number of bytes written.
+  fn write_point_bytes(&self, buf: &mut [u8]) -> Result<usize,Error> {
+    self.to_bounds()?.write_bytes(buf)
+  }
+  /// Read a point written by `write_point_bytes()`, returning the number of bytes read.
+  fn point_from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+    let (size,bounds) = Self::Bounds::from_bytes(src)?;
+    Ok((size,Self::from_bounds(&bounds)))
+  }
+  /// Scalar kind of each dimension for a database with `dimensions` dimensions, which
+  /// describes the point type in exports. The default is empty, for point types that
+  /// export their points with the codec above instead of by coordinate.
+  fn scalar_kinds(_dimensions: usize) -> Vec<ScalarKind> { vec![] }
+  /// Append this point to `buf` in the export format. Points with `scalar_kinds()` write each
+  /// coordinate in order with `Coord::write_export()`. The default writes a `u32` length
+  /// followed by `write_point_bytes()`.
+  fn write_export(&self, buf: &mut Vec<u8>) -> Result<(),Error> {
+    export::write_other(&point_to_bytes(self)?, buf);
+    Ok(())
+  }
+  /// Read a point written by `write_export()` for a database with `dimensions` dimensions,
+  /// returning the number of bytes read.
+  fn read_export(src: &[u8], _dimensions: usize) -> Result<(usize,Self),Error> {
+    let (size,bytes) = export::read_other(src)?;
+    Ok((size,Self::point_from_bytes(bytes)?.1))
+  }
+}
+
+// encode a point with the byte codec from the Point trait
+pub(crate) fn point_to_bytes<P>(point: &P) -> Result<Vec<u8>,Error> where P: Point {
+  let mut buf = vec![0u8;point.count_point_bytes()];
+  point.write_point_bytes(&mut buf)?;
+  Ok(buf)
+}
+
+// check one coordinate of a point. with total_order, NaN and infinite values pass and are
+// ordered by cmp_scalar()
+fn check_coord<X>(dimension: usize, c: &Coord<X>, total_order: bool) -> Result<(),Error>
+where X: Scalar {
+  if !total_order {
+    for x in c.min().into_iter().chain(c.max()) {
+      if !x.is_finite() {
+        return EyrosErrorKind::NonFinite { dimension, value: format!["{:?}", x] }.raise();
+      }
+    }
+  }
+  if let Coord::Interval(min,max) = c {
+    let sides = if total_order { Some(cmp_scalar(min,max)) } else { min.partial_cmp(max) };
+    if matches![sides, None | Some(std::cmp::Ordering::Greater)] {
+      return EyrosErrorKind::IntervalSides {
+        dimension,
+        min: format!["{:?}", min],
+        max: format!["{:?}", max],
+      }.raise();
+    }
+  }
+  Ok(())
+}
+
+// order two scalars, falling back to cmp_total() for values partial_cmp() can't compare. those
+// values only get into databases built with Setup::total_order(), so this is the partial order
+// everywhere else.
+pub(crate) fn cmp_scalar<X>(a: &X, b: &X) -> std::cmp::Ordering where X: Scalar {
+  a.partial_cmp(b).unwrap_or_else(|| a.cmp_total(b))
+}
+
+// check a point for a batch, which accepts NaN and infinite coordinates with total_order
+pub(crate) fn check_point<P>(fields: &SetupFields, p: &P) -> Result<(),Error> where P: Point {
+  if fields.total_order { p.check_total_order() } else { p.check() }
+}
+
+/// Points that are stored as a tuple of `Coord` in a `Tree{N}`, by way of a `TreeOf`.
+/// This is usually implemented with `#[derive(eyros::Point)]` on a struct of `Coord` fields
+/// (with the `derive` feature), which lets a struct be used as the point type of a database.
+pub trait TuplePoint: Point {
+  /// Tuple of coordinates, such as `(Coord<f32>,Coord<f32>)`.
+  type Tuple: Point<Bounds=Self::Bounds>;
+  /// `Tree{N}` that stores the tuples.
+  type Tree<V: Value>: tree::Tree<Self::Tuple,V>;
+  /// Convert this point into its tuple of coordinates.
+  fn to_tuple(&self) -> Self::Tuple;
+  /// Convert a tuple of coordinates back into this point.
+  fn from_tuple(tuple: Self::Tuple) -> Self;
 }
 
 /// Intersection tests used by `Point` and `Point::Bounds`.
@@ -88,6 +416,7 @@ macro_rules! impl_point {
     #[async_trait::async_trait]
     impl<$($T),+> Point for ($(Coord<$T>),+) where $($T: Scalar),+ {
       type Bounds = (($($T),+),($($T),+));
+      const DIMENSIONS: Option<usize> = Some([$($i),+].len());
       fn to_bounds(&self) -> Result<Self::Bounds,Error> {
         Ok((
           ($(match &self.$i {
@@ -95,12 +424,18 @@ macro_rules! impl_point {
               return EyrosErrorKind::ScalarInBounds {}.raise();
             },
             Coord::Interval(min,_) => min.clone(),
+            _ => {
+              return EyrosErrorKind::UnboundedInBounds {}.raise();
+            },
           }),+),
           ($(match &self.$i {
             Coord::Scalar(_) => {
               return EyrosErrorKind::ScalarInBounds {}.raise();
             },
             Coord::Interval(_,max) => max.clone(),
+            _ => {
+              return EyrosErrorKind::UnboundedInBounds {}.raise();
+            },
           }),+),
         ))
       }
@@ -108,23 +443,50 @@ macro_rules! impl_point {
         ($(Coord::Interval((bounds.0).$i.clone(),(bounds.1).$i.clone())),+)
       }
       fn check(&self) -> Result<(),Error> {
-        $(match &(self.$i) {
-          Coord::Interval(min,max) => {
-            match min.partial_cmp(&max) {
-              None | Some(std::cmp::Ordering::Greater) => {
-                return EyrosErrorKind::IntervalSides {
-                  dimension: $i,
-                  min: format!["{:?}", min],
-                  max: format!["{:?}", max],
-                }.raise();
-              },
-              _ => {},
-            }
-          },
-          _ => {}
-        };)+
+        $(check_coord($i, &self.$i, false)?;)+
+        Ok(())
+      }
+      fn check_total_order(&self) -> Result<(),Error> {
+        $(check_coord($i, &self.$i, true)?;)+
+        Ok(())
+      }
+      fn cmp_dim(&self, other: &Self, dim: usize) -> Option<std::cmp::Ordering> {
+        match dim {
+          $($i => tree::sort_cmp(&self.$i, &other.$i, true),)+
+          _ => None,
+        }
+      }
+      fn dimensions(&self) -> usize {
+        [$($i),+].len()
+      }
+      fn union(&self, other: &Self) -> Option<Self> {
+        Some(($(self.$i.union(&other.$i)),+))
+      }
+      fn count_point_bytes(&self) -> usize {
+        self.count_bytes()
+      }
+      fn write_point_bytes(&self, buf: &mut [u8]) -> Result<usize,Error> {
+        self.write_bytes(buf)
+      }
+      fn point_from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+        Self::from_bytes(src)
+      }
+      fn scalar_kinds(_dimensions: usize) -> Vec<ScalarKind> {
+        vec![$(<$T as Scalar>::KIND),+]
+      }
+      fn write_export(&self, buf: &mut Vec<u8>) -> Result<(),Error> {
+        $(self.$i.write_export(buf)?;)+
         Ok(())
       }
+      fn read_export(src: &[u8], _dimensions: usize) -> Result<(usize,Self),Error> {
+        let mut offset = 0;
+        let p = ($({
+          let (size,c) = Coord::<$T>::read_export(&src[offset..])?;
+          offset += size;
+          c
+        }),+);
+        Ok((offset,p))
+      }
     }
   }
 }
@@ -137,6 +499,118 @@ macro_rules! impl_point {
 #[cfg(feature="7d")] impl_point![Tree7,open_from_path7,(P0,P1,P2,P3,P4,P5,P6),(0,1,2,3,4,5,6)];
 #[cfg(feature="8d")] impl_point![Tree8,open_from_path8,(P0,P1,P2,P3,P4,P5,P6,P7),(0,1,2,3,4,5,6,7)];
 
+#[cfg(feature="nd")] pub use tree::TreeN;
+pub use tree::TreeOf;
+#[cfg(not(feature="wasm"))] pub use store::open_from_path_of;
+#[cfg(feature="derive")] pub use eyros_derive::{Point,Value};
+#[doc(hidden)] pub use desert;
+#[cfg(all(feature="nd",not(feature="wasm")))] pub use store::open_from_path_n;
+
+/// Points with a number of dimensions chosen at runtime, stored in a `TreeN`.
+/// Every point in a database must have the same number of dimensions,
+/// which is recorded in the database meta by the first batch.
+#[cfg(feature="nd")]
+#[async_trait::async_trait]
+impl<X> Point for Vec<Coord<X>> where X: Scalar {
+  type Bounds = (Vec<X>,Vec<X>);
+  const DIMENSIONS: Option<usize> = None;
+  fn to_bounds(&self) -> Result<Self::Bounds,Error> {
+    let mut bounds = (Vec::with_capacity(self.len()),Vec::with_capacity(self.len()));
+    for c in self.iter() {
+      match c {
+        Coord::Scalar(_) => {
+          return EyrosErrorKind::ScalarInBounds {}.raise();
+        },
+        Coord::Interval(min,max) => {
+          bounds.0.push(min.clone());
+          bounds.1.push(max.clone());
+        },
+        _ => {
+          return EyrosErrorKind::UnboundedInBounds {}.raise();
+        },
+      }
+    }
+    Ok(bounds)
+  }
+  fn from_bounds(bounds: &Self::Bounds) -> Self {
+    bounds.0.iter().zip(bounds.1.iter())
+      .map(|(min,max)| Coord::Interval(min.clone(),max.clone()))
+      .collect()
+  }
+  fn check(&self) -> Result<(),Error> {
+    if self.is_empty() {
+      return EyrosErrorKind::NoDimensions {}.raise();
+    }
+    for (i,c) in self.iter().enumerate() {
+      check_coord(i, c, false)?;
+    }
+    Ok(())
+  }
+  fn check_total_order(&self) -> Result<(),Error> {
+    if self.is_empty() {
+      return EyrosErrorKind::NoDimensions {}.raise();
+    }
+    for (i,c) in self.iter().enumerate() {
+      check_coord(i, c, true)?;
+    }
+    Ok(())
+  }
+  fn cmp_dim(&self, other: &Self, dim: usize) -> Option<std::cmp::Ordering> {
+    tree::sort_cmp(self.get(dim)?, other.get(dim)?, true)
+  }
+  fn dimensions(&self) -> usize {
+    self.len()
+  }
+  fn union(&self, other: &Self) -> Option<Self> {
+    Some(self.iter().zip(other.iter()).map(|(a,b)| a.union(b)).collect())
+  }
+  fn count_point_bytes(&self) -> usize {
+    self.count_bytes()
+  }
+  fn write_point_bytes(&self, buf: &mut [u8]) -> Result<usize,Error> {
+    self.write_bytes(buf)
+  }
+  fn point_from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+    Self::from_bytes(src)
+  }
+  fn scalar_kinds(dimensions: usize) -> Vec<ScalarKind> {
+    vec![X::KIND;dimensions]
+  }
+  fn write_export(&self, buf: &mut Vec<u8>) -> Result<(),Error> {
+    for c in self.iter() {
+      c.write_export(buf)?;
+    }
+    Ok(())
+  }
+  fn read_export(src: &[u8], dimensions: usize) -> Result<(usize,Self),Error> {
+    let mut offset = 0;
+    let mut p = Vec::with_capacity(dimensions);
+    for _ in 0..dimensions {
+      let (size,c) = Coord::read_export(&src[offset..])?;
+      offset += size;
+      p.push(c);
+    }
+    Ok((offset,p))
+  }
+}
+
+// every point must have `expected` dimensions, or the same number of dimensions as the first point
+// when `expected` is 0. returns the number of dimensions.
+fn check_dimensions<'a,P,I>(expected: usize, points: I) -> Result<usize,Error>
+where P: Point, I: Iterator<Item=&'a P> {
+  if let Some(d) = P::DIMENSIONS { return Ok(d) }
+  let mut dims = expected;
+  for p in points {
+    let d = p.dimensions();
+    if dims == 0 {
+      dims = d;
+    } else if d != dims {
+      return EyrosErrorKind::DimensionMismatch { expected: dims, received: d }.raise();
+    }
+  }
+  Ok(dims)
+}
+
 /// Enum container for batch operations on the database.
 #[derive(Debug,Clone)]
 pub enum Row<P,V> where P: Point, V: Value {
@@ -151,6 +625,7 @@ pub type Root<P> = Option<tree::TreeRef<P>>;
 pub struct Meta<P> where P: Point {
   pub roots: Vec<Root<P>>,
   pub next_tree: TreeId,
+  pub dimensions: usize,
 }
 
 /// Top-level database API.
@@ -158,7 +633,7 @@ pub struct DB<S,T,P,V>
 where S: RA, P: Point, V: Value, T: Tree<P,V> {
   pub storage: Arc<Mutex<Box<dyn Storage<S>>>>,
   pub fields: Arc<SetupFields>,
-  pub meta_store: Arc<Mutex<S>>,
+  pub(crate) meta_file: Arc<Mutex<MetaFile<S>>>,
   pub meta: Arc<RwLock<Meta<P>>>,
   pub trees: Arc<TreeFile<S,T,P,V>>,
 }
@@ -169,7 +644,7 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
     Self {
       storage: self.storage.clone(),
       fields: self.fields.clone(),
-      meta_store: self.meta_store.clone(),
+      meta_file: self.meta_file.clone(),
       meta: self.meta.clone(),
       trees: self.trees.clone(),
     }
@@ -217,29 +692,44 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
   /// # Ok(()) }
   /// ```
   ///
-  /// Always open a database with the same types. There are no runtime checks yet
-  /// to ensure a database is opened with the same types that it was created with.
+  /// Always open a database with the same types. The meta records the kind of each scalar
+  /// in the point type, and opening a database with a different point type fails with
+  /// `EyrosErrorKind::PointType`. Value types and points with `ScalarKind::Other` scalars
+  /// are not checked.
+  ///
+  /// A database written by eyros 4 (format version 1) is upgraded to the current format when it
+  /// is opened with its `Tree{N}`, which rewrites every tree file. Older versions of eyros can't
+  /// open the database after that.
+  ///
   /// It's fine to change the Setup settings on a previously-created database,
   /// but those settings will only affect new operations.
   pub async fn open_from_setup(setup: Setup<S>) -> Result<Self,Error> {
     let fields = Arc::new(setup.fields);
-    fields.log("opening db").await?;
-    let mut meta_store = setup.storage.lock().await.open("meta").await?;
-    let meta = match meta_store.len().await? {
-      0 => {
-        fields.log("no existing db found. initialized new meta").await?;
-        Meta { roots: vec![], next_tree: 0 }
+    fields.log(DebugEvent::Open).await?;
+    let (mut meta_file,o_meta,meta_len) = MetaFile::open(&mut *setup.storage.lock().await)
+      .await?;
+    let mut bytes_written = 0;
+    let meta = match o_meta {
+      None => {
+        fields.log(DebugEvent::OpenNew).await?;
+        Meta { roots: vec![], next_tree: 0, dimensions: P::DIMENSIONS.unwrap_or(0) }
       },
-      n => {
-        fields.log(&format!["existing db found. reading {} bytes from meta store", n]).await?;
-        Meta::from_bytes(&meta_store.read(0,n).await?)?.1
+      Some(mut meta) => {
+        fields.log(DebugEvent::OpenExisting { bytes: meta_len }).await?;
+        if meta_file.version == 1 {
+          bytes_written = upgrade::upgrade_v1::<S,T,P,V>(&mut *setup.storage.lock().await,
+            &mut meta, &mut meta_file).await?;
+        }
+        meta
       },
     };
     let trees = TreeFile::new(Arc::clone(&fields), Arc::clone(&setup.storage));
+    Counters::add(&trees.counters.bytes_read, meta_len);
+    Counters::add(&trees.counters.bytes_written, bytes_written);
     Ok(Self {
       storage: Arc::clone(&setup.storage),
       fields,
-      meta_store: Arc::new(Mutex::new(meta_store)),
+      meta_file: Arc::new(Mutex::new(meta_file)),
       meta: Arc::new(RwLock::new(meta)),
       trees: Arc::new(trees),
     })
@@ -250,12 +740,16 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
     Setup::from_storage(storage).build().await
   }
   /// Write a collection of updates to the database with default options.
-  /// This does not sync the changes to disk: call `sync()` for that.
+  /// This does not sync the changes to disk: call `sync()` for that, or set `max_pending_bytes`
+  /// in the `Setup` to sync once enough changes have built up.
   /// Each update can be a `Row::Insert(point,value)` or a `Row::Delete(point,id)`
   /// (where the type of `id` is defined in `Value::Id`). For deletes, you need not
   /// have exactly the same `point` as the original record, only a point that will
   /// intersect it.
-  pub async fn batch(&mut self, rows: &[Row<P,V>]) -> Result<(),Error> {
+  ///
+  /// The returned `BatchReport` counts the records inserted and deleted and lists the trees that
+  /// were created, updated, and removed.
+  pub async fn batch(&mut self, rows: &[Row<P,V>]) -> Result<BatchReport<V>,Error> {
     let opts = BatchOptions::new().rebuild_depth(self.fields.rebuild_depth);
     self.batch_with_options(rows, &opts).await
   }
@@ -263,17 +757,28 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
   /// the `Setup`. A greater rebuild depth trades write performance for better query performance,
   /// which you can also obtain by calling `optimize()`.
   pub async fn batch_with_rebuild_depth(&mut self, rebuild_depth: usize, rows: &[Row<P,V>])
-  -> Result<(),Error> {
+  -> Result<BatchReport<V>,Error> {
     let opts = BatchOptions::new().rebuild_depth(rebuild_depth);
     self.batch_with_options(rows, &opts).await
   }
   /// Perform a batch update with explicit batch options.
-  pub async fn batch_with_options(&mut self, rows: &[Row<P,V>], opts: &BatchOptions) -> Result<(),Error> {
-    if rows.is_empty() { return Ok(()) }
+  pub async fn batch_with_options(&mut self, rows: &[Row<P,V>], opts: &BatchOptions)
+  -> Result<BatchReport<V>,Error> {
+    // max_pending_bytes is only checked between batches, so that each batch is synced whole or
+    // not at all
+    let report = self.batch_pending(rows, opts).await?;
+    self.sync_pending().await?;
+    Ok(report)
+  }
+  // batch without syncing for max_pending_bytes, for transactions that may roll back
+  pub(crate) async fn batch_pending(&mut self, rows: &[Row<P,V>], opts: &BatchOptions)
+  -> Result<BatchReport<V>,Error> {
+    let mut report = BatchReport::new();
+    if rows.is_empty() { return Ok(report) }
     for row in rows.iter() {
       match row {
-        Row::Insert(p,_) => p.check()?,
-        Row::Delete(p,_) => p.check()?,
+        Row::Insert(p,_) => check_point(&self.fields, p)?,
+        Row::Delete(p,_) => check_point(&self.fields, p)?,
       }
     }
     let inserts: Vec<(&P,&V)> = rows.iter()
@@ -294,6 +799,14 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
       .collect();
 
     let mut meta = self.meta.write().await;
+    // borrow the fields separately: merges advance next_tree while the roots are replaced
+    let meta = &mut *meta;
+    // the first batch of a TreeN database fixes its number of dimensions, but only once the
+    // batch has gone through
+    let dimensions = check_dimensions(meta.dimensions, rows.iter().map(|row| match row {
+      Row::Insert(p,_) => p,
+      Row::Delete(p,_) => p,
+    }))?;
     let merge_trees = Arc::new(
       meta.roots.iter()
         .take_while(|r| r.is_some())
@@ -304,17 +817,27 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
       fields: Arc::clone(&self.fields),
       inserts: inserts.as_slice(),
       deletes: Arc::new(deletes),
-      inputs: merge_trees.clone(),
+      inputs: merge_trees,
       roots: meta.roots.clone(),
       trees: self.trees.clone(),
       next_tree: &mut meta.next_tree,
       rebuild_depth: opts.fields.rebuild_depth,
       error_if_missing: opts.fields.error_if_missing,
     };
+    let removed = m.remove().await;
+    // trees that lost records may have moved to new ids, and their old ids are already removed,
+    // so the new roots apply even when remove() fails on missing ids
+    meta.roots = m.roots.clone();
+    let (deleted,missing,updated,moved) = removed?;
+    report.deleted = deleted;
+    report.missing = missing;
+    report.trees_updated = updated;
+    report.trees_removed = moved;
     if inserts.is_empty() {
-      m.remove().await?;
-      return Ok(());
+      meta.dimensions = dimensions;
+      return Ok(report);
     }
+    let merge_trees = Arc::clone(&m.inputs);
     let (tr,rm_trees,create_trees) = m.merge().await?;
     //eprintln!["root {}={} bytes", t.count_bytes(), t.to_bytes()?.len()];
     for r in rm_trees.iter() {
@@ -323,6 +846,9 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
     for (r,t) in create_trees.iter() {
       self.trees.put(r,Arc::clone(t)).await?;
     }
+    report.inserted = inserts.len();
+    report.trees_created = create_trees.keys().copied().collect();
+    report.trees_removed.extend(rm_trees);
     for i in 0..merge_trees.len() {
       if i < meta.roots.len() {
         meta.roots[i] = None;
@@ -335,11 +861,88 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
     } else {
       meta.roots.push(tr);
     }
+    meta.dimensions = dimensions;
+    Ok(report)
+  }
+  /// Write every row from an async `Stream` to the database.
+  ///
+  /// Consecutive inserts go through the same path as `bulk_load()`: they are written to sorted
+  /// runs in storage and built into one new tree, which takes a free root slot sized for its
+  /// partitions instead of merging with the trees already in the forest. Consecutive deletes are
+  /// applied with `batch()`. A delete finishes the tree for the inserts before it, so rows apply
+  /// in stream order. Memory holds at most `stream_batch_size` rows or `stream_batch_bytes`
+  /// bytes of rows at once, and trees are synced before the stream is read further whenever the
+  /// trees waiting for a sync reach `stream_batch_bytes`. Everything is synced at the end.
+  /// The returned `BatchReport` combines the reports from every step.
+  pub async fn batch_stream<R>(&mut self, mut rows: R) -> Result<BatchReport<V>,Error>
+  where R: Stream<Item=Row<P,V>>+Unpin+Send {
+    let mut report = BatchReport::new();
+    let mut bulk: Option<bulk::BulkBuilder<S,T,P,V>> = None;
+    let mut deletes = vec![];
+    let mut delete_bytes = 0;
+    while let Some(row) = rows.next().await {
+      match row {
+        Row::Insert(p,v) => {
+          if !deletes.is_empty() {
+            report.extend(self.batch_stream_deletes(&mut deletes).await?);
+            delete_bytes = 0;
+          }
+          if bulk.is_none() {
+            bulk = Some(bulk::BulkBuilder::new(self).await);
+          }
+          if let Some(b) = bulk.as_mut() {
+            b.push(self, (p,v)).await?;
+          }
+        },
+        Row::Delete(p,id) => {
+          if let Some(b) = bulk.take() {
+            report.extend(self.batch_stream_bulk(b).await?);
+          }
+          delete_bytes += p.count_point_bytes();
+          deletes.push(Row::Delete(p,id));
+          let max_bytes = self.fields.stream_batch_bytes;
+          if deletes.len() >= self.fields.stream_batch_size.max(1)
+          || (max_bytes > 0 && delete_bytes >= max_bytes) {
+            report.extend(self.batch_stream_deletes(&mut deletes).await?);
+            delete_bytes = 0;
+          }
+        },
+      }
+    }
+    if let Some(b) = bulk.take() {
+      report.extend(self.batch_stream_bulk(b).await?);
+    }
+    if !deletes.is_empty() {
+      report.extend(self.batch_stream_deletes(&mut deletes).await?);
+    }
+    self.sync().await?;
+    Ok(report)
+  }
+  async fn batch_stream_bulk(&mut self, b: bulk::BulkBuilder<S,T,P,V>)
+  -> Result<BatchReport<V>,Error> {
+    let report = b.finish(self).await?;
+    self.fields.log(DebugEvent::BatchStream { rows: report.inserted }).await?;
+    self.batch_stream_sync().await?;
+    Ok(report)
+  }
+  async fn batch_stream_deletes(&mut self, deletes: &mut Vec<Row<P,V>>)
+  -> Result<BatchReport<V>,Error> {
+    self.fields.log(DebugEvent::BatchStream { rows: deletes.len() }).await?;
+    let report = self.batch(deletes).await?;
+    deletes.clear();
+    self.batch_stream_sync().await?;
+    Ok(report)
+  }
+  async fn batch_stream_sync(&mut self) -> Result<(),Error> {
+    let max = self.fields.stream_batch_bytes as u64;
+    if max > 0 && self.trees.pending_bytes().await >= max {
+      self.sync().await?;
+    }
     Ok(())
   }
   /// Improve query performance by rebuilding the first `rebuild_depth` levels of the tree.
   /// A higher value for `rebuild_depth` will use more memory, as the trees are read into memory
-  /// during rebuilding and not written back out again until `sync()` is called.
+  /// during rebuilding and held until `optimize()` syncs each rebuilt tree as it goes.
   pub async fn optimize(&mut self, rebuild_depth: usize) -> Result<(),Error> {
     let mut refs = VecDeque::new();
     for root in self.meta.read().await.roots.iter() {
@@ -347,34 +950,28 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
         refs.push_back(r.clone());
       }
     }
+    // the tree that refers to each tree below a root, to point it at the tree that replaces it
+    let mut parents = HashMap::new();
     while let Some(tree_ref) = refs.pop_front() {
-      self.optimize_tree(&tree_ref, rebuild_depth).await?;
+      let id = self.optimize_tree(&tree_ref, &mut parents, rebuild_depth).await?;
       self.sync().await?;
-      //refs.extend(self.optimize_get_depth_refs(tree_ref.id, rebuild_depth).await?);
-      let rs = self.optimize_get_depth_refs(tree_ref.id, rebuild_depth).await?;
+      let rs = self.optimize_get_depth_refs(id, &mut parents, rebuild_depth).await?;
       refs.extend(rs);
     }
     Ok(())
   }
 
-  async fn optimize_tree(&mut self, tree_ref: &TreeRef<P>, rebuild_depth: usize) -> Result<(),Error> {
-    // copy tree_ref to a new tree slot and run merge on that
-    // so that refs to this tree still work
-    let mut n_ref = tree_ref.clone();
+  // rebuild a tree under a new id and return the id
+  async fn optimize_tree(
+    &mut self, tree_ref: &TreeRef<P>, parents: &mut HashMap<TreeId,TreeId>, rebuild_depth: usize
+  ) -> Result<TreeId,Error> {
     let mut meta = self.meta.write().await;
-    n_ref.id = meta.next_tree;
-    meta.next_tree += 1;
-    {
-      let t = self.trees.get(&tree_ref.id).await?;
-      self.trees.remove(&tree_ref.id).await?;
-      self.trees.put(&n_ref.id, t).await?;
-    }
-
+    let meta = &mut *meta;
     let mut m = Merge {
       fields: Arc::clone(&self.fields),
       inserts: &[],
       deletes: Arc::new(vec![]),
-      inputs: Arc::new(vec![n_ref]),
+      inputs: Arc::new(vec![tree_ref.clone()]),
       roots: vec![],
       trees: self.trees.clone(),
       next_tree: &mut meta.next_tree,
@@ -382,22 +979,46 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
       error_if_missing: true,
     };
     let (tr,rm_trees,create_trees) = m.merge().await?;
-    let tr_id = tr.map(|r| r.id);
+    let tr_id = match tr {
+      Some(r) => r.id,
+      None => return Ok(tree_ref.id),
+    };
     for r in rm_trees.iter() {
       self.trees.remove(r).await?;
     }
     for (r,t) in create_trees.iter() {
-      if tr_id == Some(*r) {
-        self.trees.put(&tree_ref.id,Arc::clone(t)).await?;
-      } else {
-        self.trees.put(r,Arc::clone(t)).await?;
+      self.trees.put(r,Arc::clone(t)).await?;
+    }
+    // point the parent or the root at the rebuilt tree. synced parents move to a new id too,
+    // up to the roots, so the files the synced meta refers to are never overwritten
+    let (mut from, mut to) = (tree_ref.id, tr_id);
+    while from != to {
+      let parent = match parents.remove(&from) {
+        Some(p) => p,
+        None => {
+          for r in meta.roots.iter_mut().flatten() {
+            if r.id == from { r.id = to }
+          }
+          break;
+        },
+      };
+      parents.insert(to, parent);
+      let t = self.trees.get(&parent).await?.lock().await.replace_ref(from, to);
+      let n = self.trees.replacement_id(&parent, &mut meta.next_tree).await?;
+      self.trees.put(&n, Arc::new(Mutex::new(t))).await?;
+      if n != parent {
+        for p in parents.values_mut() {
+          if *p == parent { *p = n }
+        }
       }
+      from = parent;
+      to = n;
     }
-    Ok(())
+    Ok(tr_id)
   }
 
   async fn optimize_get_depth_refs(
-    &mut self, tree_id: TreeId, rebuild_depth: usize
+    &mut self, tree_id: TreeId, parents: &mut HashMap<TreeId,TreeId>, rebuild_depth: usize
   ) -> Result<Vec<TreeRef<P>>,Error> {
     let mut cursors = VecDeque::new();
     cursors.push_back((0,tree_id));
@@ -405,6 +1026,7 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
     while let Some((level,id)) = cursors.pop_front() {
       let tree = self.trees.get(&id).await?;
       let refs = tree.lock().await.list_refs();
+      parents.extend(refs.iter().map(|r| (r.id,id)));
       if level+1 < rebuild_depth {
         cursors.extend(refs.iter().map(|r| (level+1,r.id)).collect::<Vec<_>>());
       } else {
@@ -414,28 +1036,101 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
     Ok(depth_refs)
   }
 
+  /// Start a transaction. Batches written through the returned `Transaction` can be written to
+  /// storage all at once with `commit()` or discarded with `rollback()`.
+  pub async fn begin(&mut self) -> Result<Transaction<'_,S,T,P,V>,Error> {
+    Transaction::new(self).await
+  }
+
+  /// Walk every tree from the roots to summarize the database: record counts, the depth of
+  /// external trees, bytes in storage and the overall bounds.
+  /// Every tree is loaded, so this can take a while for large databases.
+  pub async fn info(&self) -> Result<Info<P>,Error> {
+    info::info(self).await
+  }
+
+  /// Collect the branches and data blocks of every tree reachable from the roots, for inspection
+  /// with `Dump::to_json()` or `Dump::to_dot()`.
+  /// Every tree is loaded, so this can take a while for large databases.
+  pub async fn dump(&self) -> Result<Dump,Error> {
+    dump::dump(self, None).await
+  }
+
+  /// Collect the branches and data blocks of a single tree by its id,
+  /// without following its refs to other trees.
+  pub async fn dump_tree(&self, id: TreeId) -> Result<Dump,Error> {
+    dump::dump(self, Some(&[id])).await
+  }
+
+  /// Read every tree reachable from the roots and check that records and refs fall inside the
+  /// bounds that point to them, that points are valid, and that no tree is referred to twice.
+  /// Trees that can't be read are reported as problems instead of returning an error.
+  pub async fn verify(&self) -> Result<Verify<P>,Error> {
+    verify::verify(self).await
+  }
+
+  /// Snapshot of the counters kept since this database was opened, such as cache hits and misses
+  /// and the bytes read and written. Clones of a database share the same counters.
+  pub fn stats(&self) -> Stats {
+    self.trees.counters.snapshot()
+  }
+
   /// Write the changes made to the database to file storage.
+  ///
+  /// New trees are written first, then the meta file that refers to them, and the files of
+  /// removed trees are deleted last. Batches and `optimize()` never rewrite a synced tree in place,
+  /// they write the changed tree under a new id. The meta goes to the older of two slots along
+  /// with a checksum, so a crash at any point leaves the previous or the new version of the meta
+  /// readable with every tree it refers to. Batches from clones of this database wait until the
+  /// sync finishes.
   pub async fn sync(&mut self) -> Result<(),Error> {
-    self.trees.sync().await?;
-    let rbytes = self.meta.read().await.to_bytes()?;
-    self.meta_store.lock().await.write(0, &rbytes).await?;
+    // hold the meta read lock so that batches can't add trees the written meta doesn't cover
+    let meta = self.meta.read().await;
+    self.trees.write_updated().await?;
+    let n = self.meta_file.lock().await.write(&meta).await?;
+    Counters::add(&self.trees.counters.bytes_written, n);
+    self.trees.remove_files().await
+  }
+
+  /// Total size in bytes of the trees written by batches and optimizations that are held in memory
+  /// until the next `sync()`. With `max_pending_bytes` set in the `Setup`, batches sync
+  /// automatically when this reaches the limit.
+  pub async fn pending_bytes(&self) -> u64 {
+    self.trees.pending_bytes().await
+  }
+
+  // sync when the trees waiting for a sync reach the max_pending_bytes limit
+  async fn sync_pending(&mut self) -> Result<(),Error> {
+    let max = self.fields.max_pending_bytes as u64;
+    if max > 0 && self.trees.pending_bytes().await >= max {
+      self.sync().await?;
+    }
+    Ok(())
+  }
+  async fn check_bbox(&self, bbox: &P::Bounds) -> Result<(),Error> {
+    let dims = self.meta.read().await.dimensions;
+    if dims > 0 {
+      check_dimensions(dims, std::iter::once(&P::from_bounds(bbox)))?;
+    }
     Ok(())
   }
   /// Query the database for every feature that intersects `bbox`. Results are provided as a
   /// readable stream of `(point,value)` records.
   pub async fn query(&mut self, bbox: &P::Bounds) -> Result<query::QStream<P,V>,Error> {
-    self.fields.log(&format!["query bbox={:?}", bbox]).await?;
+    self.fields.log(DebugEvent::QueryStart { bbox: format!["{:?}", bbox] }).await?;
+    self.check_bbox(bbox).await?;
+    Counters::add(&self.trees.counters.queries, 1);
     let mut queries = vec![];
     for (i,root) in self.meta.read().await.roots.iter().enumerate() {
       if let Some(r) = root {
-        self.fields.log(&format!["query root i={} id={}", i, r.id]).await?;
+        self.fields.log(DebugEvent::QueryRoot { index: i, id: r.id }).await?;
         let t = self.trees.get(&r.id).await?;
         queries.push(t.lock().await.query(
           self.trees.clone(), bbox, Arc::clone(&self.fields), i, r
         ));
       }
     }
-    query::from_queries(queries)
+    query::from_queries(queries, Arc::clone(&self.trees.counters))
   }
   /// Query the database for every feature that intersects `bbox`.
   /// The provided `trace` will be called right before a tree file is opened with the corresponding
@@ -445,12 +1140,14 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
     bbox: &P::Bounds,
     trace: Box<dyn query::QTrace<P>>,
   ) -> Result<query::QStream<P,V>,Error> {
-    self.fields.log(&format!["query bbox={:?}", bbox]).await?;
+    self.fields.log(DebugEvent::QueryStart { bbox: format!["{:?}", bbox] }).await?;
+    self.check_bbox(bbox).await?;
+    Counters::add(&self.trees.counters.queries, 1);
     let mut queries = vec![];
     let trace_r = Arc::new(Mutex::new(trace));
     for (i,root) in self.meta.read().await.roots.iter().enumerate() {
       if let Some(r) = root {
-        self.fields.log(&format!["query root i={} id={}", i, r.id]).await?;
+        self.fields.log(DebugEvent::QueryRoot { index: i, id: r.id }).await?;
         let t = self.trees.get(&r.id).await?;
         queries.push(t.lock().await.query_trace(
           self.trees.clone(),
@@ -462,6 +1159,6 @@ where S: RA, P: Point, V: Value, T: Tree<P,V> {
         ));
       }
     }
-    query::from_queries(queries)
+    query::from_queries(queries, Arc::clone(&self.trees.counters))
   }
 }
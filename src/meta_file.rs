@@ -0,0 +1,81 @@
+use crate::{Meta,Point,Storage,RA,Error,EyrosErrorKind,bytes};
+use desert::{ToBytes,FromBytes};
+
+// the meta is written to two slots in turn, each with a generation and a checksum. a write
+// always goes to the slot that doesn't hold the newest meta, so a crash during the write leaves
+// the previous meta readable from the other slot. the first write goes to `meta`, which is
+// where format version 1 kept the whole meta.
+const SLOTS: [&str;2] = ["meta","meta.1"];
+
+pub struct MetaFile<S> where S: RA {
+  slots: Vec<S>,
+  generation: u64,
+  // format version of the meta that was read, which is 1 until a database from that version has
+  // been upgraded
+  pub version: u32,
+}
+
+impl<S> MetaFile<S> where S: RA {
+  // open both slots and decode the newest complete meta along with the bytes read.
+  // the meta is None for a new database.
+  pub async fn open<P>(storage: &mut Box<dyn Storage<S>>)
+  -> Result<(Self,Option<Meta<P>>,u64),Error> where P: Point {
+    let mut slots = Vec::with_capacity(SLOTS.len());
+    let mut newest: Option<(u64,Vec<u8>)> = None;
+    let mut read = 0;
+    let (mut legacy, mut corrupt) = (None, false);
+    for (i,name) in SLOTS.iter().enumerate() {
+      let mut s = storage.open(name).await?;
+      let len = s.len().await?;
+      if len > 0 {
+        let buf = s.read(0, len).await?;
+        read += len;
+        match bytes::decode_slot(&buf)? {
+          Some((generation,body)) => {
+            if newest.as_ref().map_or(true, |(g,_)| generation > *g) {
+              newest = Some((generation,body.to_vec()));
+            }
+          },
+          None if i == 0 && !buf.starts_with(bytes::MAGIC) => legacy = Some(buf),
+          None => corrupt = true,
+        }
+      }
+      slots.push(s);
+    }
+    match newest {
+      Some((generation,body)) => {
+        let meta = Meta::from_bytes(&body)?.1;
+        Ok((Self { slots, generation, version: bytes::FORMAT_VERSION }, Some(meta), read))
+      },
+      // the first write after a version 1 meta goes to `meta.1`, so that the old meta stays
+      // readable until the upgrade is complete
+      None => match legacy {
+        Some(buf) => {
+          let meta = bytes::decode_meta_v1(&buf)?;
+          Ok((Self { slots, generation: 1, version: 1 }, Some(meta), read))
+        },
+        None if corrupt => EyrosErrorKind::MetaChecksum {}.raise(),
+        None => Ok((Self { slots, generation: 0, version: bytes::FORMAT_VERSION }, None, read)),
+      },
+    }
+  }
+  // write the meta to the older slot and return the number of bytes written
+  pub async fn write<P>(&mut self, meta: &Meta<P>) -> Result<u64,Error> where P: Point {
+    let generation = self.generation + 1;
+    let buf = bytes::encode_slot(generation, &meta.to_bytes()?)?;
+    let s = &mut self.slots[((generation - 1) % 2) as usize];
+    s.write(0, &buf).await?;
+    s.sync_all().await?;
+    self.generation = generation;
+    self.version = bytes::FORMAT_VERSION;
+    Ok(buf.len() as u64)
+  }
+  // total size of both slots in storage
+  pub async fn len(&mut self) -> Result<u64,Error> {
+    let mut len = 0;
+    for s in self.slots.iter_mut() {
+      len += s.len().await?;
+    }
+    Ok(len)
+  }
+}
@@ -1,5 +1,6 @@
-use crate::{Error,Point,Value,tree::TreeRef};
-use async_std::{stream::Stream};
+use crate::{Error,Point,Value,tree::TreeRef,stats::Counters};
+use async_std::{stream::Stream,sync::Arc};
+use futures::stream::StreamExt;
 use std::marker::Unpin;
 
 pub type QStream<P,V> = Box<dyn Stream<Item=Result<(P,V),Error>>+Send+Unpin>;
@@ -12,6 +13,9 @@ impl<F,P> QTrace<P> for F where F: FnMut(TreeRef<P>)+Send+Sync+'static, P: Point
     (self)(tr)
   }
 }
-pub fn from_queries<P:Point,V:Value>(queries: Vec<QStream<P,V>>) -> Result<QStream<P,V>,Error> {
-  Ok(Box::new(futures::stream::select_all(queries.into_iter())))
+pub fn from_queries<P:Point,V:Value>(queries: Vec<QStream<P,V>>, counters: Arc<Counters>)
+-> Result<QStream<P,V>,Error> {
+  Ok(Box::new(futures::stream::select_all(queries).inspect(move |r| {
+    if r.is_ok() { Counters::add(&counters.rows_returned, 1) }
+  })))
 }
@@ -0,0 +1,137 @@
+use crate::{Value,Error,EyrosErrorKind};
+use desert::{ToBytes,FromBytes,CountBytes,varint};
+use serde::{Serialize,de::DeserializeOwned};
+use bincode::Options;
+use core::{fmt::Debug,hash::{Hash,Hasher},marker::PhantomData};
+
+/// Select the id of a value stored in a `SerdeValue`, usually from one of its fields.
+///
+/// ```
+/// #[derive(Debug,Clone,serde::Serialize,serde::Deserialize)]
+/// struct Feature {
+///   id: u64,
+///   name: String,
+///   tags: Option<Vec<String>>,
+/// }
+///
+/// struct FeatureId;
+/// impl eyros::SerdeId<Feature> for FeatureId {
+///   type Id = u64;
+///   fn get_id(feature: &Feature) -> u64 { feature.id }
+/// }
+///
+/// type V = eyros::SerdeValue<Feature,FeatureId>;
+/// ```
+pub trait SerdeId<T>: 'static {
+  type Id: Clone+Hash+Eq+Debug+Send+Sync+'static;
+  fn get_id(value: &T) -> Self::Id;
+}
+
+/// Use the whole value as its id. The value type must implement `Hash` and `Eq`.
+pub struct SelfId;
+
+impl<T> SerdeId<T> for SelfId where T: Clone+Hash+Eq+Debug+Send+Sync+'static {
+  type Id = T;
+  fn get_id(value: &T) -> T { value.clone() }
+}
+
+/// Store any serde type as a `Value`, without implementing the desert traits by hand.
+/// Values are written as a varint length followed by a compact binary (bincode) encoding.
+/// The id of each value comes from `I`, which defaults to the whole value.
+pub struct SerdeValue<T,I=SelfId> {
+  pub value: T,
+  id: PhantomData<fn () -> I>,
+}
+
+impl<T,I> SerdeValue<T,I> {
+  pub fn new(value: T) -> Self {
+    Self { value, id: PhantomData }
+  }
+  pub fn into_inner(self) -> T {
+    self.value
+  }
+}
+
+impl<T,I> From<T> for SerdeValue<T,I> {
+  fn from(value: T) -> Self {
+    Self::new(value)
+  }
+}
+
+impl<T,I> Clone for SerdeValue<T,I> where T: Clone {
+  fn clone(&self) -> Self {
+    Self::new(self.value.clone())
+  }
+}
+
+impl<T,I> Debug for SerdeValue<T,I> where T: Debug {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    self.value.fmt(f)
+  }
+}
+
+impl<T,I> PartialEq for SerdeValue<T,I> where T: PartialEq {
+  fn eq(&self, other: &Self) -> bool {
+    self.value == other.value
+  }
+}
+
+impl<T,I> Eq for SerdeValue<T,I> where T: Eq {}
+
+// hash the id so that value types don't need to implement Hash themselves
+impl<T,I> Hash for SerdeValue<T,I> where I: SerdeId<T> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    I::get_id(&self.value).hash(state)
+  }
+}
+
+impl<T,I> Value for SerdeValue<T,I>
+where T: Serialize+DeserializeOwned+Clone+Debug+Send+Sync+'static, I: SerdeId<T> {
+  type Id = I::Id;
+  fn get_id(&self) -> Self::Id {
+    I::get_id(&self.value)
+  }
+}
+
+fn options() -> impl Options {
+  bincode::DefaultOptions::new()
+}
+
+impl<T,I> ToBytes for SerdeValue<T,I> where T: Serialize {
+  fn to_bytes(&self) -> Result<Vec<u8>,Error> {
+    let data = options().serialize(&self.value)?;
+    let mut buf = vec![0u8;varint::length(data.len() as u64)+data.len()];
+    let offset = varint::encode(data.len() as u64, &mut buf)?;
+    buf[offset..].copy_from_slice(&data);
+    Ok(buf)
+  }
+  fn write_bytes(&self, buf: &mut [u8]) -> Result<usize,Error> {
+    let data = self.to_bytes()?;
+    buf[0..data.len()].copy_from_slice(&data);
+    Ok(data.len())
+  }
+}
+
+impl<T,I> FromBytes for SerdeValue<T,I> where T: DeserializeOwned {
+  fn from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+    let (offset,len) = varint::decode(src)?;
+    let end = offset + len as usize;
+    if end > src.len() {
+      return EyrosErrorKind::ValueInsufficientBytes { expected: end, received: src.len() }.raise();
+    }
+    let value = options().deserialize(&src[offset..end])?;
+    Ok((end,Self::new(value)))
+  }
+}
+
+impl<T,I> CountBytes for SerdeValue<T,I> where T: Serialize {
+  fn count_bytes(&self) -> usize {
+    // a value that fails to serialize is counted as empty. writing it returns the error.
+    let len = options().serialized_size(&self.value).unwrap_or(0);
+    varint::length(len) + len as usize
+  }
+  fn count_from_bytes(src: &[u8]) -> Result<usize,Error> {
+    let (offset,len) = varint::decode(src)?;
+    Ok(offset + len as usize)
+  }
+}
@@ -1,4 +1,4 @@
-use crate::{DB,Tree,Storage,Point,Value,Error,RA,Debugger};
+use crate::{DB,Tree,Storage,Point,Value,Error,RA,Debugger,DebugEvent};
 use async_std::{sync::{Arc,Mutex},channel::{unbounded,Sender}};
 
 #[cfg(not(feature="wasm"))] use async_std::task::spawn;
@@ -14,8 +14,14 @@ pub struct SetupFields {
   pub inline: usize,
   pub inline_max_bytes: usize,
   pub tree_cache_size: usize,
+  pub tree_cache_bytes: usize,
+  pub max_pending_bytes: usize,
   pub rebuild_depth: usize,
-  pub debug: Option<Sender<String>>,
+  pub stream_batch_size: usize,
+  pub stream_batch_bytes: usize,
+  pub build_threads: usize,
+  pub total_order: bool,
+  pub debug: Option<Sender<DebugEvent>>,
 }
 
 impl std::fmt::Debug for SetupFields {
@@ -28,7 +34,13 @@ impl std::fmt::Debug for SetupFields {
       .field("inline", &self.inline)
       .field("inline_max_bytes", &self.inline_max_bytes)
       .field("tree_cache_size", &self.tree_cache_size)
+      .field("tree_cache_bytes", &self.tree_cache_bytes)
+      .field("max_pending_bytes", &self.max_pending_bytes)
       .field("rebuild_depth", &self.rebuild_depth)
+      .field("stream_batch_size", &self.stream_batch_size)
+      .field("stream_batch_bytes", &self.stream_batch_bytes)
+      .field("build_threads", &self.build_threads)
+      .field("total_order", &self.total_order)
       .field("debug", &format_args!["{}", match &self.debug {
         Some(_) => "[enabled]",
         None => "[not enabled]",
@@ -37,6 +49,20 @@ impl std::fmt::Debug for SetupFields {
   }
 }
 
+#[cfg(not(feature="wasm"))]
+fn default_build_threads() -> usize {
+  std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+// wasm has no threads to build trees with
+#[cfg(feature="wasm")]
+fn default_build_threads() -> usize { 1 }
+
+// threads to build trees with from the setting, which is always 1 for wasm
+#[cfg(not(feature="wasm"))]
+pub(crate) fn build_threads(n: usize) -> usize { n.max(1) }
+#[cfg(feature="wasm")]
+pub(crate) fn build_threads(_n: usize) -> usize { 1 }
+
 impl SetupFields {
   pub fn default() -> Self {
     Self {
@@ -47,13 +73,19 @@ impl SetupFields {
       inline: 50,
       inline_max_bytes: 20_000,
       tree_cache_size: 1000,
+      tree_cache_bytes: 0,
+      max_pending_bytes: 0,
       rebuild_depth: 2,
+      stream_batch_size: 100_000,
+      stream_batch_bytes: 64_000_000,
+      build_threads: default_build_threads(),
+      total_order: false,
       debug: None,
     }
   }
-  pub async fn log(&self, msg: &str) -> Result<(),Error> {
+  pub async fn log(&self, event: DebugEvent) -> Result<(),Error> {
     if let Some(d) = &self.debug {
-      d.send(msg.into()).await?;
+      d.send(event).await?;
     }
     Ok(())
   }
@@ -83,7 +115,13 @@ impl SetupFields {
 ///   .inline(50)
 ///   .inline_max_bytes(20_000)
 ///   .tree_cache_size(1000)
+///   .tree_cache_bytes(256_000_000)
+///   .max_pending_bytes(64_000_000)
 ///   .rebuild_depth(2)
+///   .stream_batch_size(100_000)
+///   .stream_batch_bytes(64_000_000)
+///   .build_threads(4)
+///   .total_order(false)
 ///   .debug(|msg: &str| eprintln!["[debug] {}", msg])
 ///   .build()
 ///   .await?;
@@ -131,17 +169,57 @@ impl<S> Setup<S> where S: RA {
     self.fields.tree_cache_size = n;
     self
   }
+  /// Limit the trees held in the cache to `n` bytes, as measured by their `CountBytes` size,
+  /// in addition to the limit on the number of trees from `tree_cache_size()`.
+  /// Least recently used trees are evicted until both limits are met. 0, the default, sets no
+  /// byte limit. Trees written since the last `sync()` stay in memory regardless of this limit.
+  pub fn tree_cache_bytes(mut self, n: usize) -> Self {
+    self.fields.tree_cache_bytes = n;
+    self
+  }
+  /// Sync after a batch when the trees held in memory since the last `sync()` reach `n` bytes,
+  /// as reported by `DB::pending_bytes()`. The limit is only checked between batches, so a
+  /// batch is never synced in part and a single batch larger than `n` bytes is held in memory
+  /// whole until it finishes. Split large jobs into several batches, or use
+  /// `DB::batch_stream()`, to keep memory bounded. 0, the default, never syncs automatically.
+  pub fn max_pending_bytes(mut self, n: usize) -> Self {
+    self.fields.max_pending_bytes = n;
+    self
+  }
   pub fn rebuild_depth(mut self, n: usize) -> Self {
     self.fields.rebuild_depth = n;
     self
   }
+  pub fn stream_batch_size(mut self, n: usize) -> Self {
+    self.fields.stream_batch_size = n;
+    self
+  }
+  /// Limit the rows `batch_stream()` and `bulk_load()` hold in memory at once to about `n` bytes,
+  /// along with the `stream_batch_size` limit on rows. 0 removes the byte limit.
+  pub fn stream_batch_bytes(mut self, n: usize) -> Self {
+    self.fields.stream_batch_bytes = n;
+    self
+  }
+  /// Build large trees on up to `n` threads. Defaults to the available parallelism, and is
+  /// always 1 for wasm.
+  pub fn build_threads(mut self, n: usize) -> Self {
+    self.fields.build_threads = build_threads(n);
+    self
+  }
+  /// Accept NaN and infinite coordinates, which batches otherwise reject. Values that can't be
+  /// compared, like NaN, sort after every other value when building trees, and queries with
+  /// finite bounds don't return them.
+  pub fn total_order(mut self, x: bool) -> Self {
+    self.fields.total_order = x;
+    self
+  }
+  /// Send a `DebugEvent` to `d` for each step the database takes, such as tree reads and syncs.
   pub fn debug(mut self, d: impl Debugger+Send+Sync+'static) -> Self {
     let debug = Arc::new(Mutex::new(d));
     let (sender,receiver) = unbounded();
     spawn(async move {
-      while let Ok(r) = receiver.recv().await {
-        let s: String = r;
-        debug.lock().await.send(&s);
+      while let Ok(event) = receiver.recv().await {
+        debug.lock().await.event(&event);
       }
     });
     self.fields.debug = Some(sender);
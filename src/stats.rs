@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicU64,Ordering};
+
+/// Snapshot of the counters that a database keeps while it runs, from `DB::stats()`.
+/// Counters start at zero when the database is opened.
+#[derive(Debug,Clone,Default,PartialEq,Eq)]
+pub struct Stats {
+  /// Trees found in memory, either in the LRU cache or written since the last sync.
+  pub cache_hits: u64,
+  /// Trees that were not in memory and had to be read from storage.
+  pub cache_misses: u64,
+  /// Trees read from storage.
+  pub trees_read: u64,
+  /// Bytes read from tree and meta files.
+  pub bytes_read: u64,
+  /// Bytes written to tree and meta files.
+  pub bytes_written: u64,
+  /// Trees created by batches and optimizations.
+  pub trees_created: u64,
+  /// Trees removed by batches and optimizations.
+  pub trees_removed: u64,
+  /// Queries started.
+  pub queries: u64,
+  /// Rows returned from query streams.
+  pub rows_returned: u64,
+}
+
+macro_rules! def_counters {
+  ($($field:ident),+) => {
+    // shared counters behind the Stats snapshot
+    #[derive(Debug,Default)]
+    pub(crate) struct Counters {
+      $(pub $field: AtomicU64),+
+    }
+    impl Counters {
+      pub fn snapshot(&self) -> Stats {
+        Stats { $($field: self.$field.load(Ordering::Relaxed)),+ }
+      }
+    }
+  }
+}
+
+def_counters![cache_hits,cache_misses,trees_read,bytes_read,bytes_written,
+  trees_created,trees_removed,queries,rows_returned];
+
+impl Counters {
+  pub fn add(counter: &AtomicU64, n: u64) {
+    counter.fetch_add(n, Ordering::Relaxed);
+  }
+}
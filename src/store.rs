@@ -89,3 +89,24 @@ macro_rules! impl_open {
 #[cfg(feature="6d")] impl_open![Tree6,open_from_path6,(P0,P1,P2,P3,P4,P5)];
 #[cfg(feature="7d")] impl_open![Tree7,open_from_path7,(P0,P1,P2,P3,P4,P5,P6)];
 #[cfg(feature="8d")] impl_open![Tree8,open_from_path8,(P0,P1,P2,P3,P4,P5,P6,P7)];
+
+#[cfg(all(feature="nd",not(feature="wasm")))]
+use crate::TreeN;
+#[cfg(all(feature="nd",not(feature="wasm")))]
+/// Open a database from a `path` with a number of dimensions chosen at runtime.
+/// Points are `Vec<Coord<X>>` and the first batch sets the number of dimensions.
+pub async fn open_from_path_n<X,V>(path: &Path)
+-> Result<DB<S,TreeN<X,V>,Vec<Coord<X>>,V>,Error>
+where X: Scalar, V: Value {
+  <DB<S,TreeN<X,V>,Vec<Coord<X>>,V>>::open_from_path(path).await
+}
+
+#[cfg(not(feature="wasm"))]
+use crate::{TreeOf,TuplePoint};
+#[cfg(not(feature="wasm"))]
+/// Open a database from a `path` for a point type that implements `TuplePoint`,
+/// such as a struct with `#[derive(eyros::Point)]`.
+pub async fn open_from_path_of<P,V>(path: &Path) -> Result<DB<S,TreeOf<P,V>,P,V>,Error>
+where P: TuplePoint, V: Value {
+  <DB<S,TreeOf<P,V>,P,V>>::open_from_path(path).await
+}
@@ -0,0 +1,70 @@
+use crate::{DB,Tree,Point,Value,Row,Meta,Error,RA,BatchOptions,BatchReport,query::QStream,
+  DebugEvent,tree_file::Checkpoint};
+
+/// Group several batches together so they can be written out with `commit()`
+/// or discarded with `rollback()`.
+///
+/// Create a transaction with `DB::begin()`:
+///
+/// ```rust,no_run
+/// # use eyros::{DB,Coord,Row};
+/// # use std::path::PathBuf;
+/// # #[async_std::main]
+/// # async fn main () -> Result<(),Box<dyn std::error::Error+Sync+Send>> {
+/// # type P = (Coord<f32>,Coord<f32>);
+/// # type V = u32;
+/// let mut db: DB<_,_,P,V> = eyros::open_from_path2(&PathBuf::from("/tmp/eyros-db/")).await?;
+/// let mut tx = db.begin().await?;
+/// tx.batch(&[Row::Insert((Coord::Scalar(1.0),Coord::Scalar(2.0)),333)]).await?;
+/// tx.batch(&[Row::Insert((Coord::Scalar(3.0),Coord::Scalar(4.0)),444)]).await?;
+/// tx.commit().await?;
+/// # Ok(()) }
+/// ```
+///
+/// A rollback restores the database to the state it was in when `begin()` was called,
+/// including any changes from before `begin()` that had not yet been synced.
+/// Dropping a transaction without calling `commit()` or `rollback()` leaves its changes pending,
+/// the same as calling `batch()` without `sync()`.
+/// Batches in a transaction never sync automatically for `max_pending_bytes`,
+/// so every change stays in memory until the transaction ends.
+pub struct Transaction<'a,S,T,P,V>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  db: &'a mut DB<S,T,P,V>,
+  meta: Meta<P>,
+  checkpoint: Checkpoint<T>,
+}
+
+impl<'a,S,T,P,V> Transaction<'a,S,T,P,V>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  pub(crate) async fn new(db: &'a mut DB<S,T,P,V>) -> Result<Transaction<'a,S,T,P,V>,Error> {
+    db.fields.log(DebugEvent::BeginTransaction).await?;
+    let meta = db.meta.read().await.clone();
+    let checkpoint = db.trees.checkpoint().await;
+    Ok(Self { db, meta, checkpoint })
+  }
+  /// Write a collection of updates as part of this transaction with default options.
+  pub async fn batch(&mut self, rows: &[Row<P,V>]) -> Result<BatchReport<V>,Error> {
+    let opts = BatchOptions::new().rebuild_depth(self.db.fields.rebuild_depth);
+    self.db.batch_pending(rows, &opts).await
+  }
+  /// Write a collection of updates as part of this transaction with explicit batch options.
+  pub async fn batch_with_options(&mut self, rows: &[Row<P,V>], opts: &BatchOptions)
+  -> Result<BatchReport<V>,Error> {
+    self.db.batch_pending(rows, opts).await
+  }
+  /// Query the database, including the changes made so far in this transaction.
+  pub async fn query(&mut self, bbox: &P::Bounds) -> Result<QStream<P,V>,Error> {
+    self.db.query(bbox).await
+  }
+  /// Write every change made to the database to file storage.
+  pub async fn commit(self) -> Result<(),Error> {
+    self.db.fields.log(DebugEvent::CommitTransaction).await?;
+    self.db.sync().await
+  }
+  /// Discard the changes made in this transaction.
+  pub async fn rollback(self) -> Result<(),Error> {
+    self.db.fields.log(DebugEvent::RollbackTransaction).await?;
+    *self.db.meta.write().await = self.meta;
+    self.db.trees.restore(self.checkpoint).await
+  }
+}
@@ -1,13 +1,23 @@
 use desert::{ToBytes,FromBytes,CountBytes};
-use crate::{Scalar,Point,Value,Coord,Error,EyrosErrorKind,Overlap,RA,Root,
-  query::{QStream,QTrace}, tree_file::TreeFile, SetupFields};
+use crate::{Scalar,Point,Value,Coord,Error,EyrosErrorKind,Overlap,RA,Root,cmp_scalar,
+  query::{QStream,QTrace}, tree_file::TreeFile, SetupFields, DebugEvent,
+  dump::{DumpNode,DumpRef}};
 use async_std::{sync::{Arc,Mutex},channel};
 #[cfg(not(feature="wasm"))] use async_std::task::spawn;
 #[cfg(feature="wasm")] use async_std::task::{spawn_local as spawn};
 use crate::unfold::unfold;
 use std::collections::{HashMap,HashSet,VecDeque};
+use std::sync::atomic::{AtomicU64,Ordering};
 use futures::future::join_all;
 
+#[cfg(feature="nd")] mod n;
+#[cfg(feature="nd")] pub use n::{TreeN,BranchN,NodeN,MStateN};
+mod of;
+pub use of::TreeOf;
+
+// minimum number of rows before sorting or building child nodes is split across threads
+const PARALLEL_BUILD_MIN: usize = 50_000;
+
 pub type TreeId = u64;
 #[derive(Debug,Clone,PartialEq)]
 pub struct TreeRef<P> {
@@ -36,672 +46,633 @@ pub enum InsertValue<'a,P,V> where P: Point, V: Value {
   Ref(TreeRef<P>),
 }
 
-macro_rules! impl_tree {
-  ($Tree:ident,$Branch:ident,$Node:ident,$MState:ident,$get_bounds:ident,$build_data:ident,
-  ($($T:tt),+),($($i:tt),+),($($u:ty),+),($($n:tt),+),$dim:expr) => {
-    #[derive(Debug,PartialEq)]
-    pub enum $Node<$($T),+,V> where $($T: Scalar),+, V: Value {
-      Branch($Branch<$($T),+,V>),
-      Data(Vec<(($(Coord<$T>),+),V)>,Vec<TreeRef<($(Coord<$T>),+)>>),
-    }
-    fn $build_data<'a,$($T),+,V>(rows: &[
-      (($(Coord<$T>),+),InsertValue<'a,($(Coord<$T>),+),V>)
-    ]) -> $Node<$($T),+,V> where $($T: Scalar),+, V: Value {
-      let (points, refs) = rows.iter().fold((vec![],vec![]),|(mut points, mut refs), pv| {
-        match &pv.1 {
-          InsertValue::Value(v) => points.push((pv.0.clone(),(*v).clone())),
-          InsertValue::Ref(r) => refs.push(r.clone()),
-        }
-        (points,refs)
+/// Operations on a single dimension of a point, which let one tree implementation serve every
+/// point type that is stored in a tree of pivots. Implemented for tuples of `Coord` and for
+/// `Vec<Coord<X>>`.
+#[doc(hidden)]
+pub trait TreePoint: Point {
+  /// Pivots of a branch along the dimension it splits.
+  type Pivots: std::fmt::Debug+Clone+PartialEq+Send+Sync+'static;
+  /// Compare two points along `dim` for sorting, falling back to a total order when enabled.
+  fn sort_cmp_dim(&self, other: &Self, dim: usize, total_order: bool)
+    -> Option<std::cmp::Ordering>;
+  /// Choose up to `n` pivots along `dim` for `rows` points sorted along `dim`, or `None` when
+  /// none of the points in `all` have a bounded end to pivot on.
+  #[allow(clippy::too_many_arguments)]
+  fn pick_pivots<'b,F,I>(point_at: F, rows: usize, n: usize, dim: usize, is_min: bool,
+    total_order: bool, all: I) -> Option<Self::Pivots>
+    where F: Fn(usize) -> &'b Self, I: Iterator<Item=&'b Self>;
+  /// Number of child nodes under a branch with `pivots`.
+  fn pivot_slots(pivots: &Self::Pivots, dim: usize) -> usize;
+  /// Bit `i` is set when the point intersects pivot `i`.
+  fn pivot_bitfield(&self, pivots: &Self::Pivots, dim: usize) -> u32;
+  /// Whether the point belongs to child node `slot` of a branch.
+  fn in_slot(&self, pivots: &Self::Pivots, dim: usize, slot: usize) -> bool;
+  /// Intersections and child nodes of a branch that can hold points inside `bbox`.
+  fn query_branch<'b,N>(pivots: &Self::Pivots, dim: usize, bbox: &Self::Bounds,
+    intersections: &'b [(u32,N)], nodes: &'b [N]) -> Vec<&'b N>;
+  fn intersect_bounds(&self, bbox: &Self::Bounds) -> bool;
+  fn bounds_dims(bbox: &Self::Bounds) -> usize;
+  fn pivot_strings(pivots: &Self::Pivots, dim: usize) -> Vec<String>;
+  /// Read a tree file written in format version 1. Only tuples of `Coord` were stored in that
+  /// format, so the default is an error.
+  fn tree_from_bytes_v1<V>(_src: &[u8]) -> Result<PointTree<Self,V>,Error> where V: Value {
+    EyrosErrorKind::FormatVersion { version: 1 }.raise()
+  }
+}
+
+#[derive(Debug,PartialEq)]
+pub enum PointNode<P,V> where P: TreePoint, V: Value {
+  Branch(PointBranch<P,V>),
+  Data(Vec<(P,V)>,Vec<TreeRef<P>>),
+}
+
+fn build_data<P,V>(rows: &[(P,InsertValue<'_,P,V>)]) -> PointNode<P,V>
+where P: TreePoint, V: Value {
+  let (points, refs) = rows.iter().fold((vec![],vec![]),|(mut points, mut refs), pv| {
+    match &pv.1 {
+      InsertValue::Value(v) => points.push((pv.0.clone(),(*v).clone())),
+      InsertValue::Ref(r) => refs.push(r.clone()),
+    }
+    (points,refs)
+  });
+  PointNode::Data(points, refs)
+}
+
+/// Branch of a tree. The dimension of the `pivots` is the depth of the branch modulo the
+/// number of dimensions.
+#[derive(Debug,PartialEq)]
+pub struct PointBranch<P,V> where P: TreePoint, V: Value {
+  pub pivots: P::Pivots,
+  pub intersections: Vec<(u32,Arc<PointNode<P,V>>)>,
+  pub nodes: Vec<Arc<PointNode<P,V>>>,
+}
+
+pub struct MState<'a,P,V> where P: TreePoint, V: Value {
+  pub fields: Arc<SetupFields>,
+  pub inserts: &'a [(P,InsertValue<'a,P,V>)],
+  pub next_tree: Arc<AtomicU64>,
+  pub ext_trees: HashMap<TreeId,Arc<Mutex<PointTree<P,V>>>>,
+  pub sorted: Vec<usize>,
+  pub threads: usize,
+  pub dims: usize,
+}
+
+impl<'a,P,V> MState<'a,P,V> where P: TreePoint, V: Value, PointNode<P,V>: CountBytes {
+  fn next<F>(&mut self, build: &Build, index: &mut usize, f: F) -> Build
+  where F: Fn(&P,usize) -> bool {
+    let inserts = self.inserts;
+    // partition:
+    let n = self.sorted[build.range.0+*index..build.range.1]
+      .iter_mut().partition_in_place(|j| {
+        f(&inserts[*j].0, *j)
       });
-      $Node::Data(points, refs)
-    }
-
-    #[derive(Debug,PartialEq)]
-    pub struct $Branch<$($T),+,V> where $($T: Scalar),+, V: Value {
-      pub pivots: ($(Option<Vec<$T>>),+),
-      pub intersections: Vec<(u32,Arc<$Node<$($T),+,V>>)>,
-      pub nodes: Vec<Arc<$Node<$($T),+,V>>>,
-    }
-
-    pub struct $MState<'a,$($T),+,V> where $($T: Scalar),+, V: Value {
-      pub fields: Arc<SetupFields>,
-      pub inserts: &'a [(($(Coord<$T>),+),InsertValue<'a,($(Coord<$T>),+),V>)],
-      pub next_tree: TreeId,
-      pub ext_trees: HashMap<TreeId,Arc<Mutex<$Tree<$($T),+,V>>>>,
-      pub sorted: Vec<usize>,
-    }
-
-    impl<'a,$($T),+,V> $MState<'a,$($T),+,V> where $($T: Scalar),+, V: Value {
-      fn next<T>(&mut self, x: &T, build: &Build, index: &mut usize,
-      f: Box<dyn Fn (&T,&[(($(Coord<$T>),+),InsertValue<'a,($(Coord<$T>),+),V>)], &usize) -> bool>) -> Build {
-        let inserts = &self.inserts;
-        // partition:
-        let n = self.sorted[build.range.0+*index..build.range.1]
-          .iter_mut().partition_in_place(|j| {
-            f(x, inserts, j)
-          });
-        let range = (build.range.0+*index,build.range.0+*index+n);
-        // sort for next dimension:
-        match (build.level+1)%$dim {
-          $($i => self.sorted[range.0..range.1].sort_unstable_by(|a,b| {
-            match coord_cmp(&(inserts[*a].0).$i,&(inserts[*b].0).$i) {
-              Some(c) => c,
-              None => panic!["comparison failed for sorting (1). a={:?} b={:?}",
-                inserts[*a], inserts[*b]],
-            }
-          })),+,
-          _ => panic!["unexpected level modulo dimension"]
-        };
-        *index += n;
-        Build {
-          level: build.level + 1,
-          range,
-          count: build.count + (build.range.1-build.range.0) - (range.1-range.0),
-        }
+    let range = (build.range.0+*index,build.range.0+*index+n);
+    *index += n;
+    Build {
+      level: build.level + 1,
+      range,
+      count: build.count + (build.range.1-build.range.0) - (range.1-range.0),
+    }
+  }
+  // sort a range produced by next() for its dimension before building it
+  fn sort(&mut self, build: &Build) {
+    let inserts = self.inserts;
+    let dim = build.level % self.dims;
+    let total_order = self.fields.total_order;
+    self.sorted[build.range.0..build.range.1].sort_unstable_by(|a,b| {
+      match inserts[*a].0.sort_cmp_dim(&inserts[*b].0, dim, total_order) {
+        Some(c) => c,
+        None => panic!["comparison failed for sorting (1). a={:?} b={:?}",
+          inserts[*a], inserts[*b]],
       }
-      fn build(&mut self, build: &Build, is_rm: bool) -> $Node<$($T),+,V> {
-        let rlen = build.range.1 - build.range.0;
-        let mut build_ext = false;
-        if rlen == 0 {
-          return $Node::Data(vec![],vec![]);
-        } else if rlen < self.fields.inline || rlen <= 2 {
-          let inserts = &self.inserts;
-          let data = $build_data(&self.sorted[build.range.0..build.range.1].iter().map(|i| {
-            inserts[*i].clone()
-          }).collect::<Vec<(($(Coord<$T>),+),InsertValue<'_,($(Coord<$T>),+),V>)>>());
-          if data.count_bytes() >= self.fields.inline_max_bytes {
-            build_ext = true;
-          } else {
-            return data;
-          }
-        } else if !is_rm && rlen <= self.fields.ext_records {
-          build_ext = true;
-        }
-        if build_ext {
-          let r = self.next_tree;
-          let tr = TreeRef {
-            id: r,
-            bounds: $get_bounds(
-              self.sorted[build.range.0..build.range.1].iter().map(|i| *i),
-              self.inserts
-            ),
-          };
-          self.next_tree += 1;
-          let inserts = &self.inserts;
-          let root = $build_data(&self.sorted[build.range.0..build.range.1].iter().map(|i| {
-            inserts[*i].clone()
-          }).collect::<Vec<(($(Coord<$T>),+),InsertValue<'_,($(Coord<$T>),+),V>)>>());
-          let t = $Tree::new(Arc::new(root));
-          self.ext_trees.insert(r, Arc::new(Mutex::new(t)));
-          return $Node::Data(vec![],vec![tr]);
-        }
-        if build.level >= self.fields.max_depth || build.count >= self.fields.max_records {
-          let r = self.next_tree;
-          let tr = TreeRef {
-            id: r,
-            bounds: $get_bounds(
-              self.sorted[build.range.0..build.range.1].iter().map(|i| *i),
-              self.inserts
-            ),
+    });
+  }
+  // build the nodes for disjoint ranges, on separate threads when there is enough data
+  fn build_children(&mut self, builds: &[Build], is_rm: bool) -> Vec<Arc<PointNode<P,V>>> {
+    let rows: usize = builds.iter().map(|b| b.range.1 - b.range.0).sum();
+    if self.threads <= 1 || builds.len() <= 1 || rows < PARALLEL_BUILD_MIN {
+      return builds.iter().map(|b| {
+        self.sort(b);
+        Arc::new(self.build(b, is_rm))
+      }).collect();
+    }
+    let groups = self.threads.min(builds.len());
+    let threads = (self.threads / groups).max(1);
+    let results = crossbeam_utils::thread::scope(|scope| {
+      let handles = (0..groups).map(|g| {
+        // each child gets its own copy of its sorted range, rebased to start at 0
+        let jobs = builds.iter().enumerate().skip(g).step_by(groups).map(|(i,b)| {
+          let build = Build {
+            level: b.level,
+            range: (0, b.range.1 - b.range.0),
+            count: b.count,
           };
-          self.next_tree += 1;
-          let t = $Tree::new(Arc::new(self.build(&build.ext(), is_rm)));
-          self.ext_trees.insert(r, Arc::new(Mutex::new(t)));
-          return $Node::Data(vec![],vec![tr]);
-        }
-
-        let n = (self.fields.branch_factor-1).min(rlen-1); // number of pivots
-        let is_min = (build.level / $dim) % 2 != 0;
-        let mut pivots = ($($n),+);
-        match build.level % $dim {
-          $($i => {
-            let mut ps = match rlen {
-              0 => panic!["not enough data to create a branch"],
-              1 => match &(self.inserts[self.sorted[build.range.0]].0).$i {
-                Coord::Scalar(x) => {
-                  vec![find_separation(x,x,x,x,is_min)]
-                },
-                Coord::Interval(min,max) => {
-                  vec![find_separation(min,max,min,max,is_min)]
-                }
-              },
-              2 => {
-                let a = match &(self.inserts[self.sorted[build.range.0]].0).$i {
-                  Coord::Scalar(x) => (x,x),
-                  Coord::Interval(min,max) => (min,max),
-                };
-                let b = match &(self.inserts[self.sorted[build.range.0+1]].0).$i {
-                  Coord::Scalar(x) => (x,x),
-                  Coord::Interval(min,max) => (min,max),
-                };
-                vec![find_separation(a.0,a.1,b.0,b.1,is_min)]
-              },
-              _ => {
-                (0..n).map(|k| {
-                  //assert![n+1 > 0, "!(n+1 > 0). n={}", n];
-                  let m = k * rlen / (n+1);
-                  let a = match &(self.inserts[self.sorted[build.range.0+m+0]].0).$i {
-                    Coord::Scalar(x) => (x,x),
-                    Coord::Interval(min,max) => (min,max),
-                  };
-                  let b = match &(self.inserts[self.sorted[build.range.0+m+1]].0).$i {
-                    Coord::Scalar(x) => (x,x),
-                    Coord::Interval(min,max) => (min,max),
-                  };
-                  find_separation(a.0,a.1,b.0,b.1,is_min)
-                }).collect()
-              }
+          (i, build, self.sorted[b.range.0..b.range.1].to_vec())
+        }).collect::<Vec<_>>();
+        let fields = Arc::clone(&self.fields);
+        let inserts = self.inserts;
+        let next_tree = Arc::clone(&self.next_tree);
+        let dims = self.dims;
+        scope.spawn(move |_| {
+          jobs.into_iter().map(|(i,build,sorted)| {
+            let mut mstate = MState {
+              fields: Arc::clone(&fields),
+              inserts,
+              next_tree: Arc::clone(&next_tree),
+              ext_trees: HashMap::new(),
+              sorted,
+              threads,
+              dims,
             };
-            // pivots aren't always sorted. make sure they are:
-            ps.sort_unstable_by(|a,b| {
-              match a.partial_cmp(b) {
-                Some(c) => c,
-                None => panic!["comparison failed for sorting pivots. a={:?} b={:?}", a, b],
-              }
-            });
-            pivots.$i = Some(ps);
-          }),+,
-          _ => panic!["unexpected level modulo dimension"]
-        };
+            mstate.sort(&build);
+            let node = mstate.build(&build, is_rm);
+            (i, node, mstate.ext_trees)
+          }).collect::<Vec<_>>()
+        })
+      }).collect::<Vec<_>>();
+      handles.into_iter().flat_map(|h| {
+        h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))
+      }).collect::<Vec<_>>()
+    }).unwrap_or_else(|e| std::panic::resume_unwind(e));
+    let mut nodes = Vec::with_capacity(results.len());
+    for (i,node,ext_trees) in results {
+      nodes.push((i,Arc::new(node)));
+      self.ext_trees.extend(ext_trees);
+    }
+    nodes.sort_unstable_by_key(|(i,_)| *i);
+    nodes.into_iter().map(|(_,node)| node).collect()
+  }
+  // write the records in a range as a list into a separate tree
+  fn build_list(&mut self, build: &Build) -> PointNode<P,V> {
+    let r = self.next_tree.fetch_add(1, Ordering::SeqCst);
+    let tr = TreeRef {
+      id: r,
+      bounds: get_bounds(
+        self.sorted[build.range.0..build.range.1].iter().copied(),
+        self.inserts
+      ),
+    };
+    let inserts = self.inserts;
+    let root = build_data(&self.sorted[build.range.0..build.range.1].iter().map(|i| {
+      inserts[*i].clone()
+    }).collect::<Vec<_>>());
+    let t = PointTree::new(Arc::new(root));
+    self.ext_trees.insert(r, Arc::new(Mutex::new(t)));
+    PointNode::Data(vec![],vec![tr])
+  }
+  fn build(&mut self, build: &Build, is_rm: bool) -> PointNode<P,V> {
+    let rlen = build.range.1 - build.range.0;
+    let mut build_ext = false;
+    if rlen == 0 {
+      return PointNode::Data(vec![],vec![]);
+    } else if rlen < self.fields.inline || rlen <= 2 {
+      let inserts = self.inserts;
+      let data = build_data(&self.sorted[build.range.0..build.range.1].iter().map(|i| {
+        inserts[*i].clone()
+      }).collect::<Vec<_>>());
+      if data.count_bytes() >= self.fields.inline_max_bytes {
+        build_ext = true;
+      } else {
+        return data;
+      }
+    } else if !is_rm && rlen <= self.fields.ext_records {
+      build_ext = true;
+    }
+    if build_ext {
+      return self.build_list(build);
+    }
+    if build.level >= self.fields.max_depth || build.count >= self.fields.max_records {
+      let r = self.next_tree.fetch_add(1, Ordering::SeqCst);
+      let tr = TreeRef {
+        id: r,
+        bounds: get_bounds(
+          self.sorted[build.range.0..build.range.1].iter().copied(),
+          self.inserts
+        ),
+      };
+      let t = PointTree::new(Arc::new(self.build(&build.ext(), is_rm)));
+      self.ext_trees.insert(r, Arc::new(Mutex::new(t)));
+      return PointNode::Data(vec![],vec![tr]);
+    }
 
-        let mut index = 0;
-        let intersections: Vec<(u32,Arc<$Node<$($T),+,V>>)> = match build.level % $dim {
-          $($i => {
-            let ps = pivots.$i.as_ref().unwrap();
-            let mut ibuckets: HashMap<u32,HashSet<usize>> = HashMap::new();
-            for j in self.sorted[build.range.0..build.range.1].iter() {
-              let mut bitfield: u32 = 0;
-              for (i,pivot) in ps.iter().enumerate() {
-                if intersect_pivot(&(self.inserts[*j].0).$i, pivot) {
-                  bitfield |= (1 << i);
-                }
-              }
-              if bitfield > 0 && ibuckets.contains_key(&bitfield) {
-                ibuckets.get_mut(&bitfield).unwrap().insert(*j);
-              } else if bitfield > 0 {
-                let mut hset = HashSet::new();
-                hset.insert(*j);
-                ibuckets.insert(bitfield, hset);
-              }
-            }
-            ibuckets.iter_mut().map(|(bitfield,hset)| {
-              let next = self.next(
-                &hset,
-                build,
-                &mut index,
-                Box::new(|hset, _inserts, j: &usize| {
-                  hset.contains(j)
-                })
-              );
-              (*bitfield, Arc::new(self.build(&next, is_rm)))
-            }).collect()
-          }),+,
-          _ => panic!["unexpected level modulo dimension"]
-        };
+    let n = (self.fields.branch_factor-1).min(rlen-1); // number of pivots
+    let is_min = (build.level / self.dims) % 2 != 0;
+    let dim = build.level % self.dims;
+    let inserts = self.inserts;
+    let sorted = &self.sorted;
+    let pivots = match P::pick_pivots(
+      |k| &inserts[sorted[build.range.0+k]].0,
+      rlen, n, dim, is_min, self.fields.total_order,
+      inserts.iter().map(|(p,_)| p)
+    ) {
+      Some(pivots) => pivots,
+      None => return self.build_list(build),
+    };
 
-        let nodes = match build.level % $dim {
-          $($i => {
-            let pv = pivots.$i.as_ref().unwrap();
-            let mut nodes = Vec::with_capacity(pv.len()+1);
-            nodes.push({
-              let pivot = pv.first().unwrap();
-              let next = self.next(
-                pivot,
-                build,
-                &mut index,
-                Box::new(|pivot, inserts, j: &usize| {
-                  match &(inserts[*j].0).$i {
-                    Coord::Scalar(x) => x < &pivot,
-                    Coord::Interval(_,x) => x < &pivot,
-                  }
-                })
-              );
-              Arc::new(self.build(&next, is_rm))
-            });
-            let ranges = pv.iter().zip(pv.iter().skip(1));
-            for range in ranges {
-              let next = self.next(
-                &range,
-                build,
-                &mut index,
-                Box::new(|range, inserts, j: &usize| {
-                  intersect_coord(&(inserts[*j].0).$i, range.0, range.1)
-                })
-              );
-              nodes.push(Arc::new(self.build(&next, is_rm)));
-            }
-            if (pv.len() > 1) {
-              nodes.push({
-                let pivot = pv.last().unwrap();
-                let next = self.next(
-                  &pivot,
-                  build,
-                  &mut index,
-                  Box::new(|pivot, inserts, j: &usize| {
-                    match &(inserts[*j].0).$i {
-                      Coord::Scalar(x) => x > &pivot,
-                      Coord::Interval(x,_) => x > &pivot,
-                    }
-                  })
-                );
-                Arc::new(self.build(&next, is_rm))
-              });
-            }
-            nodes
-          }),+,
-          _ => panic!["unexpected level modulo dimension"]
-        };
-        assert![index == build.range.1 - build.range.0,
-          "{} leftover records not built into nodes or intersections",
-          (build.range.1 - build.range.0) - index
-        ];
-        $Node::Branch($Branch {
-          pivots,
-          intersections,
-          nodes,
-        })
+    let mut index = 0;
+    let mut ibuckets: HashMap<u32,HashSet<usize>> = HashMap::new();
+    for j in self.sorted[build.range.0..build.range.1].iter() {
+      let bitfield = inserts[*j].0.pivot_bitfield(&pivots, dim);
+      if bitfield > 0 {
+        ibuckets.entry(bitfield).or_default().insert(*j);
       }
     }
+    let (bitfields, mut builds): (Vec<u32>,Vec<Build>) = ibuckets.iter().map(|(bitfield,hset)| {
+      (*bitfield, self.next(build, &mut index, |_,j| hset.contains(&j)))
+    }).unzip();
+    let n_intersections = builds.len();
+    for slot in 0..P::pivot_slots(&pivots, dim) {
+      builds.push(self.next(build, &mut index, |p,_| p.in_slot(&pivots, dim, slot)));
+    }
+    assert![index == build.range.1 - build.range.0,
+      "{} leftover records not built into nodes or intersections",
+      (build.range.1 - build.range.0) - index
+    ];
+    let mut children = self.build_children(&builds, is_rm);
+    let nodes = children.split_off(n_intersections);
+    let intersections = bitfields.into_iter().zip(children).collect();
+    PointNode::Branch(PointBranch {
+      pivots,
+      intersections,
+      nodes,
+    })
+  }
+}
 
-    impl<$($T),+,V> $Branch<$($T),+,V> where $($T: Scalar),+, V: Value {
-      pub fn new(pivots: ($(Option<Vec<$T>>),+), intersections: Vec<(u32,Arc<$Node<$($T),+,V>>)>,
-      nodes: Vec<Arc<$Node<$($T),+,V>>>) -> Self {
-        Self {
-          pivots,
-          intersections,
-          nodes,
+impl<P,V> PointBranch<P,V> where P: TreePoint, V: Value, PointNode<P,V>: CountBytes {
+  pub fn new(pivots: P::Pivots, intersections: Vec<(u32,Arc<PointNode<P,V>>)>,
+  nodes: Vec<Arc<PointNode<P,V>>>) -> Self {
+    Self {
+      pivots,
+      intersections,
+      nodes,
+    }
+  }
+  pub fn build<'a>(
+    fields: Arc<SetupFields>,
+    inserts: &[(P,InsertValue<'a,P,V>)],
+    next_tree: &mut TreeId,
+    is_rm: bool,
+  ) -> (Option<TreeRef<P>>,CreateTrees<PointTree<P,V>>) {
+    if inserts.is_empty() { return (None, HashMap::new()) }
+    let threads = crate::setup::build_threads(fields.build_threads);
+    let total_order = fields.total_order;
+    let shared_next_tree = Arc::new(AtomicU64::new(*next_tree));
+    let mut mstate = MState {
+      fields,
+      inserts,
+      next_tree: Arc::clone(&shared_next_tree),
+      ext_trees: HashMap::new(),
+      sorted: {
+        let mut xs: Vec<usize> = (0..inserts.len()).collect();
+        let cmp = |a: &usize, b: &usize| {
+          match inserts[*a].0.sort_cmp_dim(&inserts[*b].0, 0, total_order) {
+            Some(c) => c,
+            None => panic!["comparison failed for sorting (2). a={:?} b={:?}",
+              inserts[*a], inserts[*b]],
+          }
+        };
+        if threads > 1 && xs.len() >= PARALLEL_BUILD_MIN {
+          // sort chunks on separate threads. the stable sort afterward detects the sorted
+          // runs and only needs to merge them
+          let chunk_size = (xs.len() + threads - 1) / threads;
+          crossbeam_utils::thread::scope(|scope| {
+            for chunk in xs.chunks_mut(chunk_size) {
+              scope.spawn(move |_| chunk.sort_unstable_by(cmp));
+            }
+          }).unwrap_or_else(|e| std::panic::resume_unwind(e));
+          xs.sort_by(cmp);
+        } else {
+          xs.sort_unstable_by(cmp);
         }
+        xs
+      },
+      threads,
+      dims: inserts[0].0.dimensions(),
+    };
+    //assert![mstate.sorted.len() >= 1, "sorted.len()={}, must be >= 1", mstate.sorted.len()];
+    let bounds = get_bounds(
+      mstate.sorted.iter().copied(),
+      mstate.inserts
+    );
+    let root = mstate.build(&Build {
+      range: (0, inserts.len()),
+      level: 0,
+      count: 0,
+    }, is_rm);
+    *next_tree = shared_next_tree.load(Ordering::SeqCst);
+    let tr = TreeRef {
+      id: *next_tree,
+      bounds,
+    };
+    *next_tree += 1;
+    mstate.ext_trees.insert(tr.id, Arc::new(Mutex::new(PointTree {
+      root: Arc::new(root)
+    })));
+    (Some(tr), mstate.ext_trees)
+  }
+}
+
+/// Tree of pivots for any `TreePoint`. Use the `Tree{N}` and `TreeN` aliases to name a tree
+/// for `DB<_,T,_,_>`.
+#[derive(Debug,PartialEq)]
+pub struct PointTree<P,V> where P: TreePoint, V: Value {
+  pub root: Arc<PointNode<P,V>>
+}
+impl<P,V> PointTree<P,V> where P: TreePoint, V: Value {
+  pub fn new(root: Arc<PointNode<P,V>>) -> Self {
+    Self { root }
+  }
+}
+
+impl<P,V> PointNode<P,V> where P: TreePoint, V: Value, Self: CountBytes {
+  // number of dimensions from the first row or ref found under this node
+  fn dims(&self) -> Option<usize> {
+    match self {
+      PointNode::Branch(branch) => branch.intersections.iter().map(|(_,b)| b)
+        .chain(branch.nodes.iter())
+        .find_map(|b| b.dims()),
+      PointNode::Data(data,refs) => data.first().map(|(p,_)| p.dimensions())
+        .or_else(|| refs.first().map(|r| r.bounds.dimensions())),
+    }
+  }
+  fn dump(&self, level: usize, dims: usize) -> DumpNode {
+    match self {
+      PointNode::Branch(branch) => DumpNode::Branch {
+        level,
+        dimension: level % dims,
+        pivots: P::pivot_strings(&branch.pivots, level % dims),
+        intersections: branch.intersections.iter()
+          .map(|(bitfield,b)| (*bitfield,b.dump(level+1,dims)))
+          .collect(),
+        nodes: branch.nodes.iter().map(|b| b.dump(level+1,dims)).collect(),
+      },
+      PointNode::Data(data,refs) => DumpNode::Data {
+        rows: data.len(),
+        bytes: self.count_bytes(),
+        refs: refs.iter().map(|r| DumpRef {
+          id: r.id,
+          bounds: format!["{:?}",r.bounds],
+        }).collect(),
+      },
+    }
+  }
+  // copy of this node with refs to tree `id` pointing at tree `to`. nodes without such a ref
+  // are shared with the original.
+  fn replace_ref(node: &Arc<Self>, id: TreeId, to: TreeId) -> Arc<Self> {
+    match node.as_ref() {
+      PointNode::Branch(branch) => Arc::new(PointNode::Branch(PointBranch {
+        pivots: branch.pivots.clone(),
+        intersections: branch.intersections.iter()
+          .map(|(bitfield,b)| (*bitfield,Self::replace_ref(b,id,to)))
+          .collect(),
+        nodes: branch.nodes.iter().map(|b| Self::replace_ref(b,id,to)).collect(),
+      })),
+      PointNode::Data(data,refs) if refs.iter().any(|r| r.id == id) => {
+        let refs = refs.iter().map(|r| TreeRef {
+          id: if r.id == id { to } else { r.id },
+          bounds: r.bounds.clone(),
+        }).collect();
+        Arc::new(PointNode::Data(data.clone(),refs))
+      },
+      PointNode::Data(_,_) => Arc::clone(node),
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl<P,V> Tree<P,V> for PointTree<P,V>
+where P: TreePoint, V: Value, Self: ToBytes+FromBytes+CountBytes, PointNode<P,V>: CountBytes {
+  fn empty() -> Self {
+    Self { root: Arc::new(PointNode::Data(vec![],vec![])) }
+  }
+  fn from_rows(rows: Vec<(P,V)>) -> Self {
+    Self { root: Arc::new(PointNode::Data(rows,vec![])) }
+  }
+  fn build<'a>(
+    fields: Arc<SetupFields>,
+    rows: &[(P,InsertValue<'a,P,V>)],
+    next_tree: &mut TreeId,
+    is_rm: bool,
+  ) -> (Option<TreeRef<P>>,HashMap<TreeId,Arc<Mutex<Self>>>) {
+    PointBranch::build(fields, rows, next_tree, is_rm)
+  }
+  fn from_bytes_v1(src: &[u8]) -> Result<Self,Error> {
+    P::tree_from_bytes_v1(src)
+  }
+  fn list(&mut self) -> (Vec<(P,V)>,Vec<TreeRef<P>>) {
+    let mut cursors = VecDeque::new();
+    cursors.push_back(self.root.clone());
+    let mut rows = vec![];
+    let mut refs = vec![];
+    while let Some(c) = cursors.pop_front() {
+      match c.as_ref() {
+        PointNode::Branch(branch) => {
+          for (_bitfield,b) in branch.intersections.iter() {
+            cursors.push_back(b.clone());
+          }
+          for b in branch.nodes.iter() {
+            cursors.push_back(b.clone());
+          }
+        },
+        PointNode::Data(data,rs) => {
+          rows.extend_from_slice(data);
+          refs.extend_from_slice(rs);
+        },
       }
-      pub fn build<'a>(
-        fields: Arc<SetupFields>,
-        inserts: &[(($(Coord<$T>),+),InsertValue<'a,($(Coord<$T>),+),V>)],
-        next_tree: &mut TreeId,
-        is_rm: bool,
-      ) -> (Option<TreeRef<($(Coord<$T>),+)>>,CreateTrees<$Tree<$($T),+,V>>) {
-        if inserts.is_empty() { return (None, HashMap::new()) }
-        let mut mstate = $MState {
-          fields,
-          inserts,
-          next_tree: *next_tree,
-          ext_trees: HashMap::new(),
-          sorted: {
-            let mut xs: Vec<usize> = (0..inserts.len()).collect();
-            xs.sort_unstable_by(|a,b| {
-              match coord_cmp(&(inserts[*a].0).0,&(inserts[*b].0).0) {
-                Some(c) => c,
-                None => panic!["comparison failed for sorting (2). a={:?} b={:?}",
-                  inserts[*a], inserts[*b]],
-              }
-            });
-            xs
+    }
+    (rows,refs)
+  }
+  fn replace_ref(&self, id: TreeId, to: TreeId) -> Self {
+    Self { root: PointNode::replace_ref(&self.root, id, to) }
+  }
+  fn list_refs(&mut self) -> Vec<TreeRef<P>> {
+    let mut cursors = VecDeque::new();
+    cursors.push_back(self.root.clone());
+    let mut refs = vec![];
+    while let Some(c) = cursors.pop_front() {
+      match c.as_ref() {
+        PointNode::Branch(branch) => {
+          for (_bitfield,b) in branch.intersections.iter() {
+            cursors.push_back(b.clone());
           }
-        };
-        //assert![mstate.sorted.len() >= 1, "sorted.len()={}, must be >= 1", mstate.sorted.len()];
-        let bounds = $get_bounds(
-          mstate.sorted.iter().map(|i| *i),
-          mstate.inserts
-        );
-        let root = mstate.build(&Build {
-          range: (0, inserts.len()),
-          level: 0,
-          count: 0,
-        }, is_rm);
-        *next_tree = mstate.next_tree;
-        let tr = TreeRef {
-          id: *next_tree,
-          bounds,
-        };
-        *next_tree += 1;
-        mstate.ext_trees.insert(tr.id, Arc::new(Mutex::new($Tree {
-          root: Arc::new(root)
-        })));
-        (Some(tr), mstate.ext_trees)
+          for b in branch.nodes.iter() {
+            cursors.push_back(b.clone());
+          }
+        },
+        PointNode::Data(_data,rs) => {
+          refs.extend_from_slice(rs);
+        },
       }
     }
+    refs
+  }
+  fn dump(&self) -> DumpNode {
+    self.root.dump(0, P::DIMENSIONS.or_else(|| self.root.dims()).unwrap_or(1))
+  }
+  fn query_local(&mut self, bbox: &P::Bounds) -> (Vec<(P,V)>,Vec<TreeRef<P>>) {
+    let dims = P::bounds_dims(bbox);
+    let mut rows = vec![];
+    let mut refs = vec![];
+    let mut cursors = VecDeque::new();
+    cursors.push_back((0,self.root.clone()));
 
-    /// Tree in N dimensions. You might need to manually specify the appropriate
-    /// Tree{N} as T for `DB<_,T,_,_>`.
-    #[derive(Debug,PartialEq)]
-    pub struct $Tree<$($T),+,V> where $($T: Scalar),+, V: Value {
-      pub root: Arc<$Node<$($T),+,V>>
-    }
-    impl<$($T),+,V> $Tree<$($T),+,V> where $($T: Scalar),+, V: Value {
-      pub fn new(root: Arc<$Node<$($T),+,V>>) -> Self {
-        Self { root }
+    while let Some((level,c)) = cursors.pop_front() {
+      match c.as_ref() {
+        PointNode::Branch(branch) => {
+          let bs = P::query_branch(&branch.pivots, level % dims, bbox,
+            &branch.intersections, &branch.nodes);
+          for b in bs {
+            cursors.push_back((level+1,Arc::clone(b)));
+          }
+        },
+        PointNode::Data(data,rs) => {
+          rows.extend(data.iter()
+            .filter(|pv| pv.0.intersect_bounds(bbox))
+            .cloned()
+          );
+          refs.extend(rs.iter()
+            .filter(|r| r.bounds.intersect_bounds(bbox))
+            .cloned()
+          );
+        },
       }
     }
+    (rows,refs)
+  }
+
+  fn query<S>(
+    &mut self,
+    trees: Arc<TreeFile<S,Self,P,V>>,
+    bbox: &P::Bounds,
+    fields: Arc<SetupFields>,
+    root_index: usize,
+    root: &TreeRef<P>,
+  ) -> QStream<P,V> where S: RA {
+    self.query_trace(trees, bbox, fields, root_index, root, None)
+  }
 
-    #[async_trait::async_trait]
-    impl<$($T),+,V> Tree<($(Coord<$T>),+),V> for $Tree<$($T),+,V>
-    where $($T: Scalar),+, V: Value {
-      fn empty() -> Self {
-        Self { root: Arc::new($Node::Data(vec![],vec![])) }
+  fn query_trace<S>(
+    &mut self,
+    trees: Arc<TreeFile<S,Self,P,V>>,
+    bbox: &P::Bounds,
+    fields: Arc<SetupFields>,
+    root_index: usize,
+    root: &TreeRef<P>,
+    o_trace: Option<Arc<Mutex<Box<dyn QTrace<P>>>>>,
+  ) -> QStream<P,V> where S: RA {
+    query_tree(self, trees, bbox, fields, root_index, root, o_trace)
+  }
+
+  async fn remove<S>(&mut self, xids: Arc<Mutex<HashMap<V::Id,P>>>)
+  -> (Option<(Vec<(P,V)>,Vec<TreeRef<P>>)>,Vec<TreeId>) where S: RA {
+    let (mut list, refs) = self.list();
+    let len = list.len();
+    let mut ids = xids.lock().await;
+    list.drain_filter(|(_,v)| {
+      let id = v.get_id();
+      let x = ids.contains_key(&id);
+      if x {
+        ids.remove(&id);
       }
-      fn build<'a>(
-        fields: Arc<SetupFields>,
-        rows: &[(($(Coord<$T>),+),InsertValue<'a,($(Coord<$T>),+),V>)],
-        next_tree: &mut TreeId,
-        is_rm: bool,
-      ) -> (Option<TreeRef<($(Coord<$T>),+)>>,HashMap<TreeId,Arc<Mutex<Self>>>) {
-        $Branch::build(fields, rows, next_tree, is_rm)
+      x
+    });
+    let rs = refs.iter()
+      .filter(|r| ids.values().any(|p| r.bounds.overlap(p)))
+      .map(|r| r.id)
+      .collect::<Vec<TreeId>>();
+    if len == list.len() {
+      (None,rs)
+    } else {
+      (Some((list,refs)),rs)
+    }
+  }
+}
+
+fn get_bounds<P,V,I>(mut indexes: I, rows: &[(P,InsertValue<'_,P,V>)]) -> P
+where P: Point, V: Value, I: Iterator<Item=usize> {
+  let first = &rows[indexes.next().unwrap()].0;
+  // the union of a point with itself turns scalars into intervals
+  indexes.fold(first.union(first), |bounds,i| bounds.and_then(|b| b.union(&rows[i].0)))
+    .expect("tree points have a union")
+}
+
+macro_rules! impl_tree {
+  ($Tree:ident,$Branch:ident,$Node:ident,$MState:ident,
+  ($($T:tt),+),($($i:tt),+),($($n:tt),+)) => {
+    /// Tree in N dimensions. You might need to manually specify the appropriate
+    /// Tree{N} as T for `DB<_,T,_,_>`.
+    pub type $Tree<$($T),+,V> = PointTree<($(Coord<$T>),+),V>;
+    pub type $Branch<$($T),+,V> = PointBranch<($(Coord<$T>),+),V>;
+    pub type $Node<$($T),+,V> = PointNode<($(Coord<$T>),+),V>;
+    pub type $MState<'a,$($T),+,V> = MState<'a,($(Coord<$T>),+),V>;
+
+    impl<$($T),+> TreePoint for ($(Coord<$T>),+) where $($T: Scalar),+ {
+      type Pivots = ($(Option<Vec<$T>>),+);
+      fn tree_from_bytes_v1<V>(src: &[u8]) -> Result<PointTree<Self,V>,Error> where V: Value {
+        $Tree::<$($T),+,V>::from_bytes_v1(src)
       }
-      fn list(&mut self) -> (Vec<(($(Coord<$T>),+),V)>,Vec<TreeRef<($(Coord<$T>),+)>>) {
-        let mut cursors = VecDeque::new();
-        cursors.push_back(self.root.clone());
-        let mut rows = vec![];
-        let mut refs = vec![];
-        while let Some(c) = cursors.pop_front() {
-          match c.as_ref() {
-            $Node::Branch(branch) => {
-              for (_bitfield,b) in branch.intersections.iter() {
-                cursors.push_back(b.clone());
-              }
-              for b in branch.nodes.iter() {
-                cursors.push_back(b.clone());
-              }
-            },
-            $Node::Data(data,rs) => {
-              rows.extend(data.iter().cloned().collect::<Vec<_>>());
-              refs.extend_from_slice(&rs);
-            },
-          }
+      fn sort_cmp_dim(&self, other: &Self, dim: usize, total_order: bool)
+      -> Option<std::cmp::Ordering> {
+        match dim {
+          $($i => sort_cmp(&self.$i, &other.$i, total_order),)+
+          _ => panic!["unexpected level modulo dimension"]
         }
-        (rows,refs)
       }
-      fn list_refs(&mut self) -> Vec<TreeRef<($(Coord<$T>),+)>> {
-        let mut cursors = VecDeque::new();
-        cursors.push_back(self.root.clone());
-        let mut refs = vec![];
-        while let Some(c) = cursors.pop_front() {
-          match c.as_ref() {
-            $Node::Branch(branch) => {
-              for (_bitfield,b) in branch.intersections.iter() {
-                cursors.push_back(b.clone());
-              }
-              for b in branch.nodes.iter() {
-                cursors.push_back(b.clone());
-              }
-            },
-            $Node::Data(_data,rs) => {
-              refs.extend_from_slice(&rs);
-            },
-          }
+      fn pick_pivots<'b,F,I>(point_at: F, rows: usize, n: usize, dim: usize, is_min: bool,
+      total_order: bool, all: I) -> Option<Self::Pivots>
+      where F: Fn(usize) -> &'b Self, I: Iterator<Item=&'b Self> {
+        let mut pivots = ($($n),+);
+        match dim {
+          $($i => {
+            pivots.$i = Some(choose_pivots(|k| &point_at(k).$i, rows, n, is_min, total_order,
+              all.map(|p| &p.$i))?);
+          }),+
+          _ => panic!["unexpected level modulo dimension"]
         }
-        refs
+        Some(pivots)
       }
-      fn query_local(
-        &mut self, bbox: &(($($T),+),($($T),+))
-      ) -> (Vec<(($(Coord<$T>),+),V)>,Vec<TreeRef<($(Coord<$T>),+)>>) {
-        let mut rows = vec![];
-        let mut refs = vec![];
-        let mut cursors = VecDeque::new();
-        cursors.push_back((0,self.root.clone()));
-
-        while let Some((level,c)) = cursors.pop_front() {
-          match c.as_ref() {
-            $Node::Branch(branch) => {
-              match level % $dim {
-                $($i => {
-                  let pivots = branch.pivots.$i.as_ref().unwrap();
-
-                  {
-                    let mut matching: u32 = 0;
-                    if &(bbox.0).$i <= pivots.first().unwrap() {
-                      matching |= (1<<0);
-                    }
-                    let ranges = pivots.iter().zip(pivots.iter().skip(1));
-                    for (i,(start,end)) in ranges.enumerate() {
-                      if intersect_iv(start, end, &(bbox.0).$i, &(bbox.1).$i) {
-                        matching |= (1<<i);
-                        matching |= (1<<(i+1));
-                      }
-                    }
-                    if &(bbox.1).$i >= pivots.last().unwrap() {
-                      matching |= (1<<(pivots.len()-1));
-                    }
-                    for (bitfield,b) in branch.intersections.iter() {
-                      if (matching & bitfield) > 0 {
-                        cursors.push_back((level+1,Arc::clone(b)));
-                      }
-                    }
-                  }
-
-                  {
-                    let xs = &branch.nodes;
-                    let ranges = pivots.iter().zip(pivots.iter().skip(1));
-                    if &(bbox.0).$i <= pivots.first().unwrap() {
-                      cursors.push_back((level+1,Arc::clone(xs.first().unwrap())));
-                    }
-                    for ((start,end),b) in ranges.zip(xs.iter().skip(1)) {
-                      if intersect_iv(start, end, &(bbox.0).$i, &(bbox.1).$i) {
-                        cursors.push_back((level+1,Arc::clone(b)));
-                      }
-                    }
-                    if &(bbox.1).$i >= pivots.last().unwrap() {
-                      cursors.push_back((level+1,Arc::clone(xs.last().unwrap())));
-                    }
-                  }
-                }),+
-                _ => panic!["unexpected level modulo dimension"]
-              }
-            },
-            $Node::Data(data,rs) => {
-              rows.extend(data.iter()
-                .filter(|pv| {
-                  true $(&& intersect_coord(&(pv.0).$i, &(bbox.0).$i, &(bbox.1).$i))+
-                })
-                .cloned()
-                .collect::<Vec<_>>()
-              );
-              refs.extend(rs.iter()
-                .filter(|r| {
-                  true $(&& intersect_coord(&r.bounds.$i, &(bbox.0).$i, &(bbox.1).$i))+
-                })
-                .cloned()
-                .collect::<Vec<TreeRef<($(Coord<$T>),+)>>>()
-              );
-            },
-          }
+      fn pivot_slots(pivots: &Self::Pivots, dim: usize) -> usize {
+        match dim {
+          $($i => pivot_slots(pivots.$i.as_ref().unwrap()),)+
+          _ => panic!["unexpected level modulo dimension"]
         }
-        (rows,refs)
       }
-
-      fn query<S>(
-        &mut self,
-        trees: Arc<TreeFile<S,Self,($(Coord<$T>),+),V>>,
-        bbox: &(($($T),+),($($T),+)),
-        fields: Arc<SetupFields>,
-        root_index: usize,
-        root: &TreeRef<($(Coord<$T>),+)>,
-      ) -> QStream<($(Coord<$T>),+),V> where S: RA {
-        self.query_trace(trees, bbox, fields, root_index, root, None)
-      }
-
-      fn query_trace<S>(
-        &mut self,
-        trees: Arc<TreeFile<S,Self,($(Coord<$T>),+),V>>,
-        bbox: &(($($T),+),($($T),+)),
-        fields: Arc<SetupFields>,
-        root_index: usize,
-        root: &TreeRef<($(Coord<$T>),+)>,
-        o_trace: Option<Arc<Mutex<Box<dyn QTrace<($(Coord<$T>),+)>>>>>,
-      ) -> QStream<($(Coord<$T>),+),V> where S: RA {
-        let nproc = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
-        let (refs_sender,refs_receiver) = channel::unbounded::<TreeRef<($(Coord<$T>),+)>>();
-        let (queue_sender,queue_receiver) = channel::bounded::<Result<(
-          Vec<(($(Coord<$T>),+),V)>,
-          Vec<TreeRef<($(Coord<$T>),+)>>,
-        ),Error>
-        >(nproc);
-        let (trace_sender,trace_receiver) = channel::unbounded::<TreeRef<($(Coord<$T>),+)>>();
-        if let Some(trace_r) = &o_trace {
-          let trace = trace_r.clone();
-          let root_c = root.clone();
-          spawn(async move {
-            trace.lock().await.trace(root_c);
-            while let Ok(tr) = trace_receiver.recv().await {
-              trace.lock().await.trace(tr);
-            }
-          });
+      fn pivot_bitfield(&self, pivots: &Self::Pivots, dim: usize) -> u32 {
+        match dim {
+          $($i => pivot_bitfield(&self.$i, pivots.$i.as_ref().unwrap()),)+
+          _ => panic!["unexpected level modulo dimension"]
         }
-
-        for _ in 0..nproc {
-          let refs_r = refs_receiver.clone();
-          let queue_s = queue_sender.clone();
-          let bbox_c = bbox.clone();
-          let trees_c = trees.clone();
-          let is_tracing = o_trace.is_some();
-          let trace_s = trace_sender.clone();
-          spawn(async move {
-            while let Ok(r) = refs_r.recv().await {
-              if is_tracing { trace_s.send(r.clone()).await.unwrap(); }
-              match trees_c.get(&r.id).await {
-                Err(e) => queue_s.send(Err(e.into())).await.unwrap(),
-                Ok(t) => {
-                  let (rows,refs) = t.lock().await.query_local(&bbox_c);
-                  queue_s.send(Ok((rows,refs))).await.unwrap();
-                }
-              };
-            }
-            trace_s.close();
-          });
+      }
+      fn in_slot(&self, pivots: &Self::Pivots, dim: usize, slot: usize) -> bool {
+        match dim {
+          $($i => in_slot(&self.$i, pivots.$i.as_ref().unwrap(), slot),)+
+          _ => panic!["unexpected level modulo dimension"]
         }
-        struct QState<$($T: Scalar),+, V: Value> {
-          refs: VecDeque<TreeRef<($(Coord<$T>),+)>>,
-          results: VecDeque<(($(Coord<$T>),+),V)>,
-          active: usize,
-          queue_r: channel::Receiver<Result<(
-            Vec<(($(Coord<$T>),+),V)>,
-            Vec<TreeRef<($(Coord<$T>),+)>>,
-          ),Error>>,
-          queue_s: channel::Sender<Result<(
-            Vec<(($(Coord<$T>),+),V)>,
-            Vec<TreeRef<($(Coord<$T>),+)>>,
-          ),Error>>,
-          refs_s: channel::Sender<TreeRef<($(Coord<$T>),+)>>,
-          fields: Arc<SetupFields>,
-          root: TreeRef<($(Coord<$T>),+)>,
+      }
+      fn query_branch<'b,N>(pivots: &Self::Pivots, dim: usize, bbox: &Self::Bounds,
+      intersections: &'b [(u32,N)], nodes: &'b [N]) -> Vec<&'b N> {
+        match dim {
+          $($i => query_branch(pivots.$i.as_ref().unwrap(), &(bbox.0).$i, &(bbox.1).$i,
+            intersections, nodes),)+
+          _ => panic!["unexpected level modulo dimension"]
         }
-        let istate = {
-          let (v_results,v_refs) = self.query_local(bbox);
-          let mut refs = VecDeque::with_capacity(v_refs.len());
-          let mut results = VecDeque::with_capacity(v_results.len());
-          for r in v_results { results.push_back(r); }
-          for r in v_refs { refs.push_back(r); }
-          QState {
-            refs,
-            results,
-            active: 0,
-            queue_r: queue_receiver.clone(),
-            queue_s: queue_sender.clone(),
-            refs_s: refs_sender.clone(),
-            fields: fields.clone(),
-            root: root.clone(),
-          }
-        };
-        Box::new(unfold(istate, async move |mut state| {
-          loop {
-            if let Some(res) = state.results.pop_front() {
-              return Some((Ok(res),state));
-            } else if state.active > 0 {
-              match state.queue_r.recv().await.unwrap() {
-                Ok((v_results,v_refs)) => {
-                  state.active -= 1;
-                  state.results.reserve(v_results.len());
-                  state.refs.reserve(v_refs.len());
-                  for r in v_results { state.results.push_back(r); }
-                  for r in v_refs { state.refs.push_back(r); }
-                },
-                Err(e) => {
-                  state.active -= 1;
-                  return Some((Err(e.into()),state));
-                },
-              }
-            } else if !state.refs.is_empty() {
-              for _ in 0..nproc {
-                if let Some(r) = state.refs.pop_front() {
-                  state.active += 1;
-                  state.refs_s.send(r).await.unwrap();
-                } else {
-                  break;
-                }
-              }
-            } else {
-              break;
-            }
-          }
-          state.refs_s.close();
-          state.queue_s.close();
-          if let Err(e) = state.fields.log(&format![
-            "query end root_index={} root.id={} root.bounds={:?}",
-            root_index, state.root.id, &state.root.bounds
-          ]).await {
-            return Some((Err(e.into()),state));
-          }
-          None
-        }))
       }
-
-      async fn remove<S>(&mut self, xids: Arc<Mutex<HashMap<V::Id,($(Coord<$T>),+)>>>)
-      -> (Option<(Vec<(($(Coord<$T>),+),V)>,Vec<TreeRef<($(Coord<$T>),+)>>)>,Vec<TreeId>) where S: RA {
-        let (mut list, refs) = self.list();
-        let len = list.len();
-        let mut ids = xids.lock().await;
-        list.drain_filter(|(_,v)| {
-          let id = v.get_id();
-          let x = ids.contains_key(&id);
-          if x {
-            ids.remove(&id);
-          }
-          x
-        });
-        let rs = refs.iter()
-          .filter(|r| {
-            for (_,p) in ids.iter() {
-              if true $(&& intersect_coord_coord(&r.bounds.$i, &p.$i))+ {
-                return true;
-              }
-            }
-            false
-          })
-          .map(|r| { r.id })
-          .collect::<Vec<TreeId>>();
-        if len == list.len() {
-          (None,rs)
-        } else {
-          (Some((list,refs)),rs)
+      fn intersect_bounds(&self, bbox: &Self::Bounds) -> bool {
+        true $(&& intersect_coord(&self.$i, &(bbox.0).$i, &(bbox.1).$i))+
+      }
+      fn bounds_dims(_bbox: &Self::Bounds) -> usize {
+        [$($i),+].len()
+      }
+      fn pivot_strings(pivots: &Self::Pivots, dim: usize) -> Vec<String> {
+        match dim {
+          $($i => pivot_strings(pivots.$i.as_ref().unwrap()),)+
+          _ => panic!["unexpected level modulo dimension"]
         }
       }
     }
 
-    fn $get_bounds<$($T),+,V,I>(mut indexes: I,
-    rows: &[(($(Coord<$T>),+),InsertValue<'_,($(Coord<$T>),+),V>)]) -> ($(Coord<$T>),+)
-    where $($T: Scalar),+, V: Value, I: Iterator<Item=usize> {
-      let first = indexes.next().unwrap();
-      let ibounds = (
-        ($(
-          match &(rows[first].0).$i {
-            Coord::Scalar(x) => x.clone(),
-            Coord::Interval(x,_) => x.clone(),
-          }
-        ),+),
-        ($(
-          match &(rows[first].0).$i {
-            Coord::Scalar(x) => x.clone(),
-            Coord::Interval(_,x) => x.clone(),
-          }
-        ),+)
-      );
-      let bounds = indexes.fold(ibounds, |bounds,i| {
-        (
-          ($(coord_min(&(rows[i].0).$i,&(bounds.0).$i)),+),
-          ($(coord_max(&(rows[i].0).$i,&(bounds.1).$i)),+)
-        )
-      });
-      ($(Coord::Interval((bounds.0).$i,(bounds.1).$i)),+)
-    }
-
     impl<$($T),+> Overlap for (($($T),+),($($T),+)) where $($T: Scalar),+ {
       fn overlap(&self, other: &Self) -> bool {
         true $(&& intersect_iv(&(self.0).$i, &(self.1).$i, &(other.0).$i, &(other.1).$i))+
@@ -716,38 +687,76 @@ macro_rules! impl_tree {
   }
 }
 
-#[cfg(feature="2d")] impl_tree![Tree2,Branch2,Node2,MState2,get_bounds2,build_data2,
-  (P0,P1),(0,1),(usize,usize),(None,None),2
-];
-#[cfg(feature="3d")] impl_tree![Tree3,Branch3,Node3,MState3,get_bounds3,build_data3,
-  (P0,P1,P2),(0,1,2),(usize,usize,usize),(None,None,None),3
+#[cfg(feature="2d")] impl_tree![Tree2,Branch2,Node2,MState2,(P0,P1),(0,1),(None,None)];
+#[cfg(feature="3d")] impl_tree![Tree3,Branch3,Node3,MState3,(P0,P1,P2),(0,1,2),(None,None,None)];
+#[cfg(feature="4d")] impl_tree![Tree4,Branch4,Node4,Mstate4,
+  (P0,P1,P2,P3),(0,1,2,3),(None,None,None,None)
 ];
-#[cfg(feature="4d")] impl_tree![Tree4,Branch4,Node4,Mstate4,get_bounds4,build_data4,
-  (P0,P1,P2,P3),(0,1,2,3),(usize,usize,usize,usize),(None,None,None,None),4
+#[cfg(feature="5d")] impl_tree![Tree5,Branch5,Node5,MState5,
+  (P0,P1,P2,P3,P4),(0,1,2,3,4),(None,None,None,None,None)
 ];
-#[cfg(feature="5d")] impl_tree![Tree5,Branch5,Node5,MState5,get_bounds5,build_data5,
-  (P0,P1,P2,P3,P4),(0,1,2,3,4),
-  (usize,usize,usize,usize,usize),(None,None,None,None,None),5
+#[cfg(feature="6d")] impl_tree![Tree6,Branch6,Node6,MState6,
+  (P0,P1,P2,P3,P4,P5),(0,1,2,3,4,5),(None,None,None,None,None,None)
 ];
-#[cfg(feature="6d")] impl_tree![Tree6,Branch6,Node6,MState6,get_bounds6,build_data6,
-  (P0,P1,P2,P3,P4,P5),(0,1,2,3,4,5),
-  (usize,usize,usize,usize,usize,usize),(None,None,None,None,None,None),6
+#[cfg(feature="7d")] impl_tree![Tree7,Branch7,Node7,MState7,
+  (P0,P1,P2,P3,P4,P5,P6),(0,1,2,3,4,5,6),(None,None,None,None,None,None,None)
 ];
-#[cfg(feature="7d")] impl_tree![Tree7,Branch7,Node7,MState7,get_bounds7,build_data7,
-  (P0,P1,P2,P3,P4,P5,P6),(0,1,2,3,4,5,6),
-  (usize,usize,usize,usize,usize,usize,usize),(None,None,None,None,None,None,None),7
-];
-#[cfg(feature="8d")] impl_tree![Tree8,Branch8,Node8,MState8,get_bounds8,build_data8,
-  (P0,P1,P2,P3,P4,P5,P6,P7),(0,1,2,3,4,5,6,7),
-  (usize,usize,usize,usize,usize,usize,usize,usize),(None,None,None,None,None,None,None,None),8
+#[cfg(feature="8d")] impl_tree![Tree8,Branch8,Node8,MState8,
+  (P0,P1,P2,P3,P4,P5,P6,P7),(0,1,2,3,4,5,6,7),(None,None,None,None,None,None,None,None)
 ];
 
 type CreateTrees<T> = HashMap<TreeId,Arc<Mutex<T>>>;
 
+// build a tree with T::build(). a build that is split across threads runs as a blocking task
+// with its own copy of the rows, so that it doesn't hold up the executor thread.
+#[cfg(not(feature="wasm"))]
+pub(crate) async fn build_tree<'a,T,P,V>(
+  fields: Arc<SetupFields>,
+  rows: &[(P,InsertValue<'a,P,V>)],
+  next_tree: &mut TreeId,
+  is_rm: bool,
+) -> (Option<TreeRef<P>>,CreateTrees<T>) where T: Tree<P,V>, P: Point, V: Value {
+  if crate::setup::build_threads(fields.build_threads) <= 1 || rows.len() < PARALLEL_BUILD_MIN {
+    return T::build(fields, rows, next_tree, is_rm);
+  }
+  let mut values = vec![];
+  let points = rows.iter().map(|(p,v)| match v {
+    InsertValue::Value(v) => {
+      values.push((*v).clone());
+      (p.clone(),None)
+    },
+    InsertValue::Ref(r) => (p.clone(),Some(r.clone())),
+  }).collect::<Vec<_>>();
+  let mut next = *next_tree;
+  let (tr,trees,next) = async_std::task::spawn_blocking(move || {
+    let mut values = values.iter();
+    let rows = points.into_iter().map(|(p,r)| match r {
+      Some(r) => (p,InsertValue::Ref(r)),
+      None => (p,InsertValue::Value(values.next().unwrap())),
+    }).collect::<Vec<_>>();
+    let (tr,trees) = T::build(fields, &rows, &mut next, is_rm);
+    (tr,trees,next)
+  }).await;
+  *next_tree = next;
+  (tr,trees)
+}
+
+#[cfg(feature="wasm")]
+pub(crate) async fn build_tree<'a,T,P,V>(
+  fields: Arc<SetupFields>,
+  rows: &[(P,InsertValue<'a,P,V>)],
+  next_tree: &mut TreeId,
+  is_rm: bool,
+) -> (Option<TreeRef<P>>,CreateTrees<T>) where T: Tree<P,V>, P: Point, V: Value {
+  T::build(fields, rows, next_tree, is_rm)
+}
+type QueueItem<P,V> = Result<(Vec<(P,V)>,Vec<TreeRef<P>>),Error>;
+
 #[async_trait::async_trait]
 pub trait Tree<P,V>: Send+Sync+ToBytes+FromBytes+CountBytes+std::fmt::Debug+'static
 where P: Point, V: Value {
   fn empty() -> Self;
+  fn from_rows(rows: Vec<(P,V)>) -> Self where Self: Sized;
   fn build<'a>(
     fields: Arc<SetupFields>,
     rows: &[(P,InsertValue<'a,P,V>)],
@@ -756,6 +765,13 @@ where P: Point, V: Value {
   ) -> (Option<TreeRef<P>>,CreateTrees<Self>) where Self: Sized;
   fn list(&mut self) -> (Vec<(P,V)>,Vec<TreeRef<P>>);
   fn list_refs(&mut self) -> Vec<TreeRef<P>>;
+  // copy of the tree with its ref to tree `id` pointing at tree `to` instead
+  fn replace_ref(&self, id: TreeId, to: TreeId) -> Self where Self: Sized;
+  // read a tree file written in format version 1, to upgrade a database from that version
+  fn from_bytes_v1(_src: &[u8]) -> Result<Self,Error> where Self: Sized {
+    EyrosErrorKind::FormatVersion { version: 1 }.raise()
+  }
+  fn dump(&self) -> DumpNode;
   fn query_local(&mut self, bbox: &P::Bounds) -> (Vec<(P,V)>,Vec<TreeRef<P>>);
   fn query<S>(
     &mut self,
@@ -778,6 +794,127 @@ where P: Point, V: Value {
     -> (Option<(Vec<(P,V)>,Vec<TreeRef<P>>)>,Vec<TreeId>) where S: RA;
 }
 
+// walk a tree from its root, loading referenced trees from `trees` on a pool of tasks
+pub(crate) fn query_tree<S,T,P,V>(
+  tree: &mut T,
+  trees: Arc<TreeFile<S,T,P,V>>,
+  bbox: &P::Bounds,
+  fields: Arc<SetupFields>,
+  root_index: usize,
+  root: &TreeRef<P>,
+  o_trace: Option<Arc<Mutex<Box<dyn QTrace<P>>>>>,
+) -> QStream<P,V> where S: RA, T: Tree<P,V>, P: Point, V: Value {
+  let nproc = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+  let (refs_sender,refs_receiver) = channel::unbounded::<TreeRef<P>>();
+  let (queue_sender,queue_receiver) = channel::bounded::<Result<(
+    Vec<(P,V)>,
+    Vec<TreeRef<P>>,
+  ),Error>
+  >(nproc);
+  let (trace_sender,trace_receiver) = channel::unbounded::<TreeRef<P>>();
+  if let Some(trace_r) = &o_trace {
+    let trace = trace_r.clone();
+    let root_c = root.clone();
+    spawn(async move {
+      trace.lock().await.trace(root_c);
+      while let Ok(tr) = trace_receiver.recv().await {
+        trace.lock().await.trace(tr);
+      }
+    });
+  }
+
+  for _ in 0..nproc {
+    let refs_r = refs_receiver.clone();
+    let queue_s = queue_sender.clone();
+    let bbox_c = bbox.clone();
+    let trees_c = trees.clone();
+    let is_tracing = o_trace.is_some();
+    let trace_s = trace_sender.clone();
+    spawn(async move {
+      while let Ok(r) = refs_r.recv().await {
+        if is_tracing { trace_s.send(r.clone()).await.unwrap(); }
+        match trees_c.get(&r.id).await {
+          Err(e) => queue_s.send(Err(e)).await.unwrap(),
+          Ok(t) => {
+            let (rows,refs) = t.lock().await.query_local(&bbox_c);
+            queue_s.send(Ok((rows,refs))).await.unwrap();
+          }
+        };
+      }
+      trace_s.close();
+    });
+  }
+  struct QState<P: Point, V: Value> {
+    refs: VecDeque<TreeRef<P>>,
+    results: VecDeque<(P,V)>,
+    active: usize,
+    queue_r: channel::Receiver<QueueItem<P,V>>,
+    queue_s: channel::Sender<QueueItem<P,V>>,
+    refs_s: channel::Sender<TreeRef<P>>,
+    fields: Arc<SetupFields>,
+    root: TreeRef<P>,
+  }
+  let istate = {
+    let (v_results,v_refs) = tree.query_local(bbox);
+    let mut refs = VecDeque::with_capacity(v_refs.len());
+    let mut results = VecDeque::with_capacity(v_results.len());
+    for r in v_results { results.push_back(r); }
+    for r in v_refs { refs.push_back(r); }
+    QState {
+      refs,
+      results,
+      active: 0,
+      queue_r: queue_receiver.clone(),
+      queue_s: queue_sender.clone(),
+      refs_s: refs_sender.clone(),
+      fields: fields.clone(),
+      root: root.clone(),
+    }
+  };
+  Box::new(unfold(istate, async move |mut state| {
+    loop {
+      if let Some(res) = state.results.pop_front() {
+        return Some((Ok(res),state));
+      } else if state.active > 0 {
+        match state.queue_r.recv().await.unwrap() {
+          Ok((v_results,v_refs)) => {
+            state.active -= 1;
+            state.results.reserve(v_results.len());
+            state.refs.reserve(v_refs.len());
+            for r in v_results { state.results.push_back(r); }
+            for r in v_refs { state.refs.push_back(r); }
+          },
+          Err(e) => {
+            state.active -= 1;
+            return Some((Err(e),state));
+          },
+        }
+      } else if !state.refs.is_empty() {
+        for _ in 0..nproc {
+          if let Some(r) = state.refs.pop_front() {
+            state.active += 1;
+            state.refs_s.send(r).await.unwrap();
+          } else {
+            break;
+          }
+        }
+      } else {
+        break;
+      }
+    }
+    state.refs_s.close();
+    state.queue_s.close();
+    if let Err(e) = state.fields.log(DebugEvent::QueryEnd {
+      index: root_index,
+      id: state.root.id,
+      bounds: format!["{:?}", state.root.bounds],
+    }).await {
+      return Some((Err(e),state));
+    }
+    None
+  }))
+}
+
 pub struct Merge<'a,S,T,P,V>
 where P: Point, V: Value, T: Tree<P,V>, S: RA {
   pub fields: Arc<SetupFields>,
@@ -796,8 +933,6 @@ impl<'a,S,T,P,V> Merge<'a,S,T,P,V>
 where P: Point, V: Value, T: Tree<P,V>, S: RA {
   pub async fn merge(&mut self)
   -> Result<(Option<TreeRef<P>>,Vec<TreeId>,HashMap<TreeId,Arc<Mutex<T>>>),Error> {
-    self.remove().await?;
-
     let mut lists = vec![];
     let mut rm_trees = vec![];
     let mut rows: Vec<(P,InsertValue<'_,P,V>)> = vec![];
@@ -828,16 +963,23 @@ where P: Point, V: Value, T: Tree<P,V>, S: RA {
       }).collect::<Vec<_>>());
     }
     //assert![rows.len()>0, "rows.len()={}. must be >0", rows.len()];
-    let (tr, create_trees) = T::build(
+    self.fields.log(DebugEvent::Merge { inputs: self.inputs.len(), rows: rows.len() }).await?;
+    let (tr, create_trees) = build_tree::<T,P,V>(
       Arc::clone(&self.fields),
       &rows,
       &mut self.next_tree,
       false
-    );
+    ).await;
     Ok((tr, rm_trees, create_trees))
   }
-  pub async fn remove(&mut self) -> Result<(),Error> {
-    if self.deletes.is_empty() { return Ok(()) }
+  // return value: (deleted count, missing ids, updated trees, removed trees)
+  //
+  // a tree that loses records is rebuilt under the id from TreeFile::replacement_id(). when that
+  // is a new id, the ref in its parent is pointed at the new id, which can move the parent in
+  // turn, up to the root in self.roots.
+  pub async fn remove(&mut self)
+  -> Result<(usize,Vec<V::Id>,Vec<TreeId>,Vec<TreeId>),Error> {
+    if self.deletes.is_empty() { return Ok((0,vec![],vec![],vec![])) }
     let mut work = vec![];
     let ids = {
       let mut map = HashMap::new();
@@ -846,13 +988,15 @@ where P: Point, V: Value, T: Tree<P,V>, S: RA {
       }
       Arc::new(Mutex::new(map))
     };
+    let n_ids = ids.lock().await.len();
     let fields = {
       let mut f = (*self.fields).clone();
       f.max_records = usize::MAX;
       f.max_depth = usize::MAX;
       Arc::new(f)
     };
-    for ro in self.roots.iter() {
+    let next_tree = Arc::new(Mutex::new(*self.next_tree));
+    for (i,ro) in self.roots.iter().enumerate() {
       if ro.is_none() { continue }
       let r = ro.as_ref().unwrap();
       // TODO: remove the delete when found
@@ -860,60 +1004,103 @@ where P: Point, V: Value, T: Tree<P,V>, S: RA {
       let xids = ids.clone();
       let id = r.id;
       let xfields = Arc::clone(&fields);
+      let next_tree = Arc::clone(&next_tree);
       work.push(async move {
-        let mut refs = vec![id];
-        while !refs.is_empty() {
-          let r = refs.pop().unwrap();
+        let mut updated = vec![];
+        let mut removed = vec![];
+        // current id of each tree visited from this root and the index of its parent
+        let mut visited: Vec<(TreeId,Option<usize>)> = vec![];
+        let mut refs = vec![(id,None)];
+        while let Some((r,parent)) = refs.pop() {
           let tm = trees.get(&r).await?;
-          let mut t = tm.lock().await;
-          let (built,nrefs) = t.remove::<S>(
+          let (built,nrefs) = tm.lock().await.remove::<S>(
             Arc::clone(&xids),
           ).await;
-          refs.extend(nrefs);
-          if let Some((list,refs)) = built {
-            let mut rows = Vec::with_capacity(list.len() + refs.len());
-            rows.extend(list.iter().map(|(p,v)| {
-              (p.clone(),InsertValue::Value(v))
-            }).collect::<Vec<_>>());
-            rows.extend(refs.iter().map(|r| {
-              (r.bounds.clone(),InsertValue::Ref(r.clone()))
-            }).collect::<Vec<_>>());
-            let mut next_tree = r;
-            if rows.is_empty() {
-              trees.put(&r, Arc::new(Mutex::new(T::empty()))).await?;
-            } else {
-              let (tr, create_trees) = T::build(
-                Arc::clone(&xfields),
-                &rows,
-                &mut next_tree,
-                true
-              );
-              let tr_id = tr.map(|x| x.id);
-              assert![tr_id == Some(r),
-                "unexpected id constructing replacement tree for remove(). \
-                expected: {:?}, received: {:?}", Some(r), tr_id
-              ];
-              assert![create_trees.len() == 1, "unexpected external sub-trees during remove()"];
-              for (r,t) in create_trees {
-                trees.put(&r, t).await?;
-              }
+          refs.extend(nrefs.into_iter().map(|x| (x,Some(visited.len()))));
+          visited.push((r,parent));
+          let (list,refs) = match built {
+            Some(b) => b,
+            None => continue,
+          };
+          let mut rows = Vec::with_capacity(list.len() + refs.len());
+          rows.extend(list.iter().map(|(p,v)| {
+            (p.clone(),InsertValue::Value(v))
+          }).collect::<Vec<_>>());
+          rows.extend(refs.iter().map(|r| {
+            (r.bounds.clone(),InsertValue::Ref(r.clone()))
+          }).collect::<Vec<_>>());
+          let n = trees.replacement_id(&r, &mut *next_tree.lock().await).await?;
+          if rows.is_empty() {
+            trees.put(&n, Arc::new(Mutex::new(T::empty()))).await?;
+          } else {
+            let mut next = n;
+            let (tr, create_trees) = build_tree::<T,P,V>(
+              Arc::clone(&xfields),
+              &rows,
+              &mut next,
+              true
+            ).await;
+            let tr_id = tr.map(|x| x.id);
+            assert![tr_id == Some(n),
+              "unexpected id constructing replacement tree for remove(). \
+              expected: {:?}, received: {:?}", Some(n), tr_id
+            ];
+            assert![create_trees.len() == 1, "unexpected external sub-trees during remove()"];
+            for (r,t) in create_trees {
+              trees.put(&r, t).await?;
             }
           }
+          if !updated.contains(&n) { updated.push(n) }
+          // point the parents at the trees that moved
+          let mut i = visited.len()-1;
+          let (mut from, mut to) = (r, n);
+          while from != to {
+            visited[i].0 = to;
+            removed.push(from);
+            let p = match visited[i].1 {
+              Some(p) => p,
+              None => break,
+            };
+            let pid = visited[p].0;
+            let t = trees.get(&pid).await?.lock().await.replace_ref(from, to);
+            let n = trees.replacement_id(&pid, &mut *next_tree.lock().await).await?;
+            trees.put(&n, Arc::new(Mutex::new(t))).await?;
+            if !updated.contains(&n) { updated.push(n) }
+            i = p;
+            from = pid;
+            to = n;
+          }
         }
-        let r: Result<(),Error> = Ok(());
+        let r: Result<(usize,TreeId,Vec<TreeId>,Vec<TreeId>),Error>
+          = Ok((i,visited[0].0,updated,removed));
         r
       });
     }
-    for r in join_all(work).await { r?; }
-    if self.error_if_missing {
-      let xids = ids.lock().await;
-      if !xids.is_empty() {
-        return EyrosErrorKind::RemoveIdsMissing {
-          ids: xids.keys().map(|id| format!["{:?}",id]).collect()
-        }.raise();
+    let mut updated = vec![];
+    let mut removed = vec![];
+    for r in join_all(work).await {
+      let (i,root_id,u,rm) = r?;
+      if let Some(root) = self.roots[i].as_mut() {
+        root.id = root_id;
       }
+      updated.extend(u);
+      removed.extend(rm);
+    }
+    *self.next_tree = *next_tree.lock().await;
+    self.inputs = Arc::new(
+      self.roots.iter()
+        .take_while(|r| r.is_some())
+        .map(|r| r.as_ref().unwrap().clone())
+        .collect::<Vec<TreeRef<P>>>()
+    );
+    let xids = ids.lock().await;
+    let missing = xids.keys().cloned().collect::<Vec<V::Id>>();
+    if self.error_if_missing && !missing.is_empty() {
+      return EyrosErrorKind::RemoveIdsMissing {
+        ids: missing.iter().map(|id| format!["{:?}",id]).collect()
+      }.raise();
     }
-    Ok(())
+    Ok((n_ids - missing.len(), missing, updated, removed))
   }
 }
 
@@ -934,67 +1121,183 @@ pub fn get_file_from_id(id: &TreeId) -> String {
 
 fn find_separation<X>(amin: &X, amax: &X, bmin: &X, bmax: &X, is_min: bool) -> X where X: Scalar {
   if is_min && intersect_iv(amin, amax, bmin, bmax) {
-    amin.clone()/2.into() + bmin.clone()/2.into()
+    X::midpoint(amin, bmin)
   } else if !is_min && intersect_iv(amin, amax, bmin, bmax) {
-    amax.clone()/2.into() + bmax.clone()/2.into()
+    X::midpoint(amax, bmax)
   } else {
-    amax.clone()/2.into() + bmin.clone()/2.into()
+    X::midpoint(amax, bmin)
   }
 }
 
-fn intersect_iv<X>(a0: &X, a1: &X, b0: &X, b1: &X) -> bool where X: PartialOrd {
-  a1 >= b0 && a0 <= b1
+// a <= b. values that can't be compared, like NaN, sort after every other value as they do
+// when building with Setup::total_order(), which is the only way for them to get into a tree
+fn le<X>(a: &X, b: &X) -> bool where X: Scalar {
+  cmp_scalar(a,b).is_le()
 }
 
-fn intersect_pivot<X>(c: &Coord<X>, p: &X) -> bool where X: Scalar {
-  match c {
-    Coord::Scalar(x) => *x == *p,
-    Coord::Interval(min,max) => *min <= *p && *p <= *max,
+fn intersect_iv<X>(a0: &X, a1: &X, b0: &X, b1: &X) -> bool where X: Scalar {
+  le(b0,a1) && le(a0,b1)
+}
+
+// compare a lower end to an upper end where None is unbounded
+fn ends_le<X>(min: Option<&X>, max: Option<&X>) -> bool where X: Scalar {
+  match (min,max) {
+    (Some(a),Some(b)) => le(a,b),
+    _ => true,
   }
 }
 
+fn intersect_pivot<X>(c: &Coord<X>, p: &X) -> bool where X: Scalar {
+  ends_le(c.min(), Some(p)) && ends_le(Some(p), c.max())
+}
+
 fn intersect_coord_coord<X>(a: &Coord<X>, b: &Coord<X>) -> bool where X: Scalar {
-  match (a,b) {
-    (Coord::Scalar(x),Coord::Scalar(y)) => *x == *y,
-    (Coord::Scalar(x),Coord::Interval(y0,y1)) => *y0 <= *x && *x <= *y1,
-    (Coord::Interval(x0,x1),Coord::Scalar(y)) => *x0 <= *y && *y <= *x1,
-    (Coord::Interval(x0,x1),Coord::Interval(y0,y1)) => intersect_iv(x0,x1,y0,y1),
-  }
+  ends_le(a.min(), b.max()) && ends_le(b.min(), a.max())
 }
 
 fn intersect_coord<X>(c: &Coord<X>, low: &X, high: &X) -> bool where X: Scalar {
+  ends_le(c.min(), Some(high)) && ends_le(Some(low), c.max())
+}
+
+// coordinate entirely below the pivot
+fn below_pivot<X>(c: &Coord<X>, p: &X) -> bool where X: Scalar {
+  c.max().map(|x| !le(p,x)).unwrap_or(false)
+}
+
+// coordinate entirely above the pivot
+fn above_pivot<X>(c: &Coord<X>, p: &X) -> bool where X: Scalar {
+  c.min().map(|x| !le(x,p)).unwrap_or(false)
+}
+
+// sorts by the lower end, with no lower limit before any value
+fn coord_cmp<X>(x: &Coord<X>, y: &Coord<X>) -> Option<std::cmp::Ordering> where X: Scalar {
+  x.min().partial_cmp(&y.min())
+}
+
+// compare coordinates for sorting, falling back to a total order when enabled
+pub(crate) fn sort_cmp<X>(x: &Coord<X>, y: &Coord<X>, total_order: bool) -> Option<std::cmp::Ordering>
+where X: Scalar {
+  match coord_cmp(x,y) {
+    None if total_order => Some(match (x.min(),y.min()) {
+      (Some(a),Some(b)) => a.cmp_total(b),
+      (a,b) => a.is_some().cmp(&b.is_some()),
+    }),
+    c => c,
+  }
+}
+
+// pivots are placed using only the bounded ends of a coordinate so that open ranges
+// don't pull pivots toward their missing end
+fn pivot_ends<X>(c: &Coord<X>) -> Option<(&X,&X)> where X: Scalar {
   match c {
-    Coord::Scalar(x) => low <= x && x <= high,
-    Coord::Interval(x,y) => intersect_iv(x,y,low,high),
+    Coord::Scalar(x) => Some((x,x)),
+    Coord::Interval(min,max) => Some((min,max)),
+    Coord::From(x) => Some((x,x)),
+    Coord::To(x) => Some((x,x)),
+    Coord::All => None,
   }
 }
 
-fn coord_cmp<X>(x: &Coord<X>, y: &Coord<X>) -> Option<std::cmp::Ordering> where X: Scalar {
-  match (x,y) {
-    (Coord::Scalar(a),Coord::Scalar(b)) => a.partial_cmp(b),
-    (Coord::Scalar(a),Coord::Interval(b,_)) => a.partial_cmp(b),
-    (Coord::Interval(a,_),Coord::Scalar(b)) => a.partial_cmp(b),
-    (Coord::Interval(a,_),Coord::Interval(b,_)) => a.partial_cmp(b),
+fn pivot_between<X>(a: &Coord<X>, b: &Coord<X>, is_min: bool) -> Option<X> where X: Scalar {
+  match (pivot_ends(a),pivot_ends(b)) {
+    (Some(a),Some(b)) => Some(find_separation(a.0,a.1,b.0,b.1,is_min)),
+    (Some(a),None) | (None,Some(a)) => Some(find_separation(a.0,a.1,a.0,a.1,is_min)),
+    (None,None) => None,
+  }
+}
+
+// choose up to n pivots from the coordinates of a sorted range of rows, or None when there
+// is no bounded coordinate to pivot on
+fn choose_pivots<'b,X,F,I>(coord_at: F, rlen: usize, n: usize, is_min: bool, total_order: bool,
+mut all: I) -> Option<Vec<X>>
+where X: Scalar, F: Fn(usize) -> &'b Coord<X>, I: Iterator<Item=&'b Coord<X>> {
+  let mut ps: Vec<X> = match rlen {
+    0 => panic!["not enough data to create a branch"],
+    1 => pivot_between(coord_at(0),coord_at(0),is_min).into_iter().collect(),
+    2 => pivot_between(coord_at(0),coord_at(1),is_min).into_iter().collect(),
+    _ => {
+      (0..n).filter_map(|k| {
+        let m = k * rlen / (n+1);
+        pivot_between(coord_at(m),coord_at(m+1),is_min)
+      }).collect()
+    }
+  };
+  if ps.is_empty() {
+    // every coordinate here is Coord::All, which intersects any pivot
+    ps.push(all.find_map(|c| pivot_ends(c).map(|(x,_)| x.clone()))?);
   }
+  // pivots aren't always sorted. make sure they are:
+  ps.sort_unstable_by(|a,b| {
+    match a.partial_cmp(b) {
+      Some(c) => c,
+      None if total_order => a.cmp_total(b),
+      None => panic!["comparison failed for sorting pivots. a={:?} b={:?}", a, b],
+    }
+  });
+  Some(ps)
+}
+
+// child nodes of a branch: below the first pivot, between each pair of pivots,
+// and above the last pivot when there is more than one
+fn pivot_slots<X>(ps: &[X]) -> usize {
+  if ps.len() > 1 { ps.len() + 1 } else { 1 }
 }
 
-fn coord_min<X>(x: &Coord<X>, r: &X) -> X where X: Scalar {
-  match x {
-    Coord::Scalar(a) => cmp_min(a,r),
-    Coord::Interval(a,_) => cmp_min(a,r),
+fn pivot_bitfield<X>(c: &Coord<X>, ps: &[X]) -> u32 where X: Scalar {
+  let mut bitfield: u32 = 0;
+  for (i,pivot) in ps.iter().enumerate() {
+    if intersect_pivot(c, pivot) {
+      bitfield |= 1 << i;
+    }
   }
+  bitfield
 }
 
-fn coord_max<X>(x: &Coord<X>, r: &X) -> X where X: Scalar {
-  match x {
-    Coord::Scalar(a) => cmp_max(a,r),
-    Coord::Interval(_,a) => cmp_max(a,r),
+fn in_slot<X>(c: &Coord<X>, ps: &[X], slot: usize) -> bool where X: Scalar {
+  if slot == 0 {
+    below_pivot(c, ps.first().unwrap())
+  } else if slot < ps.len() {
+    intersect_coord(c, &ps[slot-1], &ps[slot])
+  } else {
+    above_pivot(c, ps.last().unwrap())
   }
 }
 
-fn cmp_min<X>(a: &X, b: &X) -> X where X: Scalar {
-  (if a < b { a } else { b }).clone()
+fn query_branch<'b,X,N>(ps: &[X], min: &X, max: &X, intersections: &'b [(u32,N)],
+nodes: &'b [N]) -> Vec<&'b N> where X: Scalar {
+  let mut matching: u32 = 0;
+  if le(min, ps.first().unwrap()) {
+    matching |= 1<<0;
+  }
+  let ranges = ps.iter().zip(ps.iter().skip(1));
+  for (i,(start,end)) in ranges.enumerate() {
+    if intersect_iv(start, end, min, max) {
+      matching |= 1<<i;
+      matching |= 1<<(i+1);
+    }
+  }
+  if le(ps.last().unwrap(), max) {
+    matching |= 1<<(ps.len()-1);
+  }
+  let mut bs = intersections.iter()
+    .filter(|(bitfield,_)| (matching & bitfield) > 0)
+    .map(|(_,b)| b)
+    .collect::<Vec<_>>();
+  let ranges = ps.iter().zip(ps.iter().skip(1));
+  if le(min, ps.first().unwrap()) {
+    bs.push(nodes.first().unwrap());
+  }
+  for ((start,end),b) in ranges.zip(nodes.iter().skip(1)) {
+    if intersect_iv(start, end, min, max) {
+      bs.push(b);
+    }
+  }
+  if le(ps.last().unwrap(), max) {
+    bs.push(nodes.last().unwrap());
+  }
+  bs
 }
-fn cmp_max<X>(a: &X, b: &X) -> X where X: Scalar {
-  (if a > b { a } else { b }).clone()
+
+fn pivot_strings<X>(ps: &[X]) -> Vec<String> where X: Scalar {
+  ps.iter().map(|p| format!["{:?}",p]).collect()
 }
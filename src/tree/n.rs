@@ -0,0 +1,64 @@
+use crate::{Scalar,Coord,Overlap};
+use super::{PointTree,PointBranch,PointNode,MState,TreePoint,intersect_iv,intersect_coord,
+  intersect_coord_coord,sort_cmp,choose_pivots,pivot_slots,pivot_bitfield,in_slot,query_branch,
+  pivot_strings};
+
+type PointN<X> = Vec<Coord<X>>;
+
+/// Tree with a number of dimensions that is chosen at runtime. Points are `Vec<Coord<X>>` and
+/// every point in a database must have the same length.
+pub type TreeN<X,V> = PointTree<PointN<X>,V>;
+/// Branch of a `TreeN`. The dimension of the `pivots` is the depth of the branch modulo the
+/// number of dimensions.
+pub type BranchN<X,V> = PointBranch<PointN<X>,V>;
+pub type NodeN<X,V> = PointNode<PointN<X>,V>;
+pub type MStateN<'a,X,V> = MState<'a,PointN<X>,V>;
+
+impl<X> TreePoint for Vec<Coord<X>> where X: Scalar {
+  type Pivots = Vec<X>;
+  fn sort_cmp_dim(&self, other: &Self, dim: usize, total_order: bool)
+  -> Option<std::cmp::Ordering> {
+    sort_cmp(&self[dim], &other[dim], total_order)
+  }
+  fn pick_pivots<'b,F,I>(point_at: F, rows: usize, n: usize, dim: usize, is_min: bool,
+  total_order: bool, all: I) -> Option<Self::Pivots>
+  where F: Fn(usize) -> &'b Self, I: Iterator<Item=&'b Self> {
+    choose_pivots(|k| &point_at(k)[dim], rows, n, is_min, total_order, all.map(|p| &p[dim]))
+  }
+  fn pivot_slots(pivots: &Self::Pivots, _dim: usize) -> usize {
+    pivot_slots(pivots)
+  }
+  fn pivot_bitfield(&self, pivots: &Self::Pivots, dim: usize) -> u32 {
+    pivot_bitfield(&self[dim], pivots)
+  }
+  fn in_slot(&self, pivots: &Self::Pivots, dim: usize, slot: usize) -> bool {
+    in_slot(&self[dim], pivots, slot)
+  }
+  fn query_branch<'b,N>(pivots: &Self::Pivots, dim: usize, bbox: &Self::Bounds,
+  intersections: &'b [(u32,N)], nodes: &'b [N]) -> Vec<&'b N> {
+    query_branch(pivots, &bbox.0[dim], &bbox.1[dim], intersections, nodes)
+  }
+  fn intersect_bounds(&self, bbox: &Self::Bounds) -> bool {
+    self.iter().zip(bbox.0.iter().zip(bbox.1.iter()))
+      .all(|(c,(min,max))| intersect_coord(c, min, max))
+  }
+  fn bounds_dims(bbox: &Self::Bounds) -> usize {
+    bbox.0.len()
+  }
+  fn pivot_strings(pivots: &Self::Pivots, _dim: usize) -> Vec<String> {
+    pivot_strings(pivots)
+  }
+}
+
+impl<X> Overlap for (Vec<X>,Vec<X>) where X: Scalar {
+  fn overlap(&self, other: &Self) -> bool {
+    self.0.iter().zip(self.1.iter()).zip(other.0.iter().zip(other.1.iter()))
+      .all(|((a0,a1),(b0,b1))| intersect_iv(a0, a1, b0, b1))
+  }
+}
+
+impl<X> Overlap for Vec<Coord<X>> where X: Scalar {
+  fn overlap(&self, other: &Self) -> bool {
+    self.iter().zip(other.iter()).all(|(a,b)| intersect_coord_coord(a, b))
+  }
+}
@@ -0,0 +1,120 @@
+use crate::{TuplePoint,Value,Error,RA,SetupFields,query::{QStream,QTrace},tree_file::TreeFile,
+  dump::DumpNode};
+use super::{Tree,TreeRef,TreeId,InsertValue,CreateTrees,query_tree};
+use async_std::sync::{Arc,Mutex};
+use std::collections::HashMap;
+
+type Removed<P,V> = (Option<(Vec<(P,V)>,Vec<TreeRef<P>>)>,Vec<TreeId>);
+
+/// Tree for points that implement `TuplePoint`, such as structs with `#[derive(eyros::Point)]`.
+/// Points are converted to their tuple of coordinates and stored in the matching `Tree{N}`,
+/// so the file format is the same as for a database of tuples.
+#[derive(Debug)]
+pub struct TreeOf<P,V> where P: TuplePoint, V: Value {
+  pub tree: P::Tree<V>,
+}
+
+impl<P,V> TreeOf<P,V> where P: TuplePoint, V: Value {
+  pub fn new(tree: P::Tree<V>) -> Self {
+    Self { tree }
+  }
+}
+
+fn to_tuple_ref<P>(r: &TreeRef<P>) -> TreeRef<P::Tuple> where P: TuplePoint {
+  TreeRef { id: r.id, bounds: r.bounds.to_tuple() }
+}
+
+fn from_tuple_ref<P>(r: TreeRef<P::Tuple>) -> TreeRef<P> where P: TuplePoint {
+  TreeRef { id: r.id, bounds: P::from_tuple(r.bounds) }
+}
+
+fn from_tuple_rows<P,V>(rows: Vec<(P::Tuple,V)>) -> Vec<(P,V)> where P: TuplePoint {
+  rows.into_iter().map(|(p,v)| (P::from_tuple(p),v)).collect()
+}
+
+fn from_tuple_refs<P>(refs: Vec<TreeRef<P::Tuple>>) -> Vec<TreeRef<P>> where P: TuplePoint {
+  refs.into_iter().map(from_tuple_ref).collect()
+}
+
+#[async_trait::async_trait]
+impl<P,V> Tree<P,V> for TreeOf<P,V> where P: TuplePoint, V: Value {
+  fn empty() -> Self {
+    Self::new(P::Tree::<V>::empty())
+  }
+  fn from_rows(rows: Vec<(P,V)>) -> Self {
+    Self::new(P::Tree::<V>::from_rows(rows.iter().map(|(p,v)| (p.to_tuple(),v.clone())).collect()))
+  }
+  fn build<'a>(
+    fields: Arc<SetupFields>,
+    rows: &[(P,InsertValue<'a,P,V>)],
+    next_tree: &mut TreeId,
+    is_rm: bool,
+  ) -> (Option<TreeRef<P>>,CreateTrees<Self>) {
+    let tuple_rows = rows.iter().map(|(p,x)| {
+      (p.to_tuple(), match x {
+        InsertValue::Value(v) => InsertValue::Value(*v),
+        InsertValue::Ref(r) => InsertValue::Ref(to_tuple_ref(r)),
+      })
+    }).collect::<Vec<_>>();
+    let (root,trees) = P::Tree::<V>::build(fields, &tuple_rows, next_tree, is_rm);
+    let trees = trees.into_iter().map(|(id,t)| {
+      let tree = match Arc::try_unwrap(t) {
+        Ok(t) => t.into_inner(),
+        Err(_) => panic!["tree {} was shared during build", id],
+      };
+      (id,Arc::new(Mutex::new(Self::new(tree))))
+    }).collect::<HashMap<_,_>>();
+    (root.map(from_tuple_ref),trees)
+  }
+  fn from_bytes_v1(src: &[u8]) -> Result<Self,Error> {
+    Ok(Self::new(P::Tree::<V>::from_bytes_v1(src)?))
+  }
+  fn list(&mut self) -> (Vec<(P,V)>,Vec<TreeRef<P>>) {
+    let (rows,refs) = self.tree.list();
+    (from_tuple_rows(rows),from_tuple_refs(refs))
+  }
+  fn list_refs(&mut self) -> Vec<TreeRef<P>> {
+    from_tuple_refs(self.tree.list_refs())
+  }
+  fn replace_ref(&self, id: TreeId, to: TreeId) -> Self {
+    Self::new(self.tree.replace_ref(id, to))
+  }
+  fn dump(&self) -> DumpNode {
+    self.tree.dump()
+  }
+  fn query_local(&mut self, bbox: &P::Bounds) -> (Vec<(P,V)>,Vec<TreeRef<P>>) {
+    let (rows,refs) = self.tree.query_local(bbox);
+    (from_tuple_rows(rows),from_tuple_refs(refs))
+  }
+  fn query<S>(
+    &mut self,
+    trees: Arc<TreeFile<S,Self,P,V>>,
+    bbox: &P::Bounds,
+    fields: Arc<SetupFields>,
+    root_index: usize,
+    root: &TreeRef<P>,
+  ) -> QStream<P,V> where S: RA {
+    self.query_trace(trees, bbox, fields, root_index, root, None)
+  }
+  fn query_trace<S>(
+    &mut self,
+    trees: Arc<TreeFile<S,Self,P,V>>,
+    bbox: &P::Bounds,
+    fields: Arc<SetupFields>,
+    root_index: usize,
+    root: &TreeRef<P>,
+    o_trace: Option<Arc<Mutex<Box<dyn QTrace<P>>>>>,
+  ) -> QStream<P,V> where S: RA {
+    query_tree(self, trees, bbox, fields, root_index, root, o_trace)
+  }
+  async fn remove<S>(&mut self, ids: Arc<Mutex<HashMap<V::Id,P>>>) -> Removed<P,V> where S: RA {
+    let tuple_ids = {
+      let ids = ids.lock().await;
+      Arc::new(Mutex::new(ids.iter().map(|(id,p)| (id.clone(),p.to_tuple())).collect()))
+    };
+    let (removed,rs) = self.tree.remove::<S>(Arc::clone(&tuple_ids)).await;
+    let remaining = tuple_ids.lock().await;
+    ids.lock().await.retain(|id,_| remaining.contains_key(id));
+    (removed.map(|(rows,refs)| (from_tuple_rows(rows),from_tuple_refs(refs))),rs)
+  }
+}
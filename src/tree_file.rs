@@ -1,20 +1,79 @@
 use lru::{LruCache as LRU};
-use crate::{Tree,TreeId,tree,Error,Point,Value,Storage,RA,SetupFields,EyrosErrorKind};
+use crate::{Tree,TreeId,tree,Error,Point,Value,Storage,RA,SetupFields,EyrosErrorKind,DebugEvent,
+  stats::Counters};
 use std::collections::{HashMap,HashSet};
 use async_std::{sync::{Arc,Mutex,RwLock}};
 #[cfg(not(feature="wasm"))] use async_std::task::spawn;
 #[cfg(feature="wasm")] use async_std::task::{spawn_local as spawn};
 use futures::future::join_all;
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+// lru of trees limited by a count of trees and, when max_bytes is above 0,
+// by the total of their CountBytes sizes
+struct TreeCache<T> {
+  lru: LRU<TreeId,(Arc<Mutex<T>>,usize)>,
+  max_bytes: usize,
+  bytes: usize,
+}
+
+impl<T> TreeCache<T> {
+  fn new(size: usize, max_bytes: usize) -> Self {
+    Self { lru: LRU::new(size), max_bytes, bytes: 0 }
+  }
+  fn get(&mut self, id: &TreeId) -> Option<&Arc<Mutex<T>>> {
+    self.lru.get(id).map(|(t,_)| t)
+  }
+  fn put(&mut self, id: TreeId, t: Arc<Mutex<T>>, size: usize) {
+    self.pop(&id);
+    // a tree larger than the whole budget would only evict everything else
+    if self.lru.cap() == 0 || (self.max_bytes > 0 && size > self.max_bytes) { return }
+    while self.lru.len() >= self.lru.cap()
+    || (self.max_bytes > 0 && self.bytes + size > self.max_bytes) {
+      match self.lru.pop_lru() {
+        Some((_,(_,n))) => self.bytes -= n,
+        None => break,
+      }
+    }
+    self.lru.put(id, (t,size));
+    self.bytes += size;
+  }
+  fn pop(&mut self, id: &TreeId) {
+    if let Some((_,n)) = self.lru.pop(id) {
+      self.bytes -= n;
+    }
+  }
+}
+
+// trees put since the last sync along with their CountBytes sizes
+type Pending<T> = HashMap<TreeId,(Arc<Mutex<T>>,usize)>;
 
 pub struct TreeFile<S,T,P,V> where T: Tree<P,V>, P: Point, V: Value, S: RA {
   fields: Arc<SetupFields>,
-  cache: Arc<Mutex<LRU<TreeId,Arc<Mutex<T>>>>>,
+  cache: Arc<Mutex<TreeCache<T>>>,
   storage: Arc<Mutex<Box<dyn Storage<S>>>>,
-  updated: Arc<RwLock<HashMap<TreeId,Arc<Mutex<T>>>>>,
+  updated: Arc<RwLock<Pending<T>>>,
   removed: Arc<RwLock<HashSet<TreeId>>>,
+  pins: Arc<AtomicUsize>,
+  pub(crate) counters: Arc<Counters>,
   _marker: std::marker::PhantomData<(P,V)>,
 }
 
+/// Keeps `TreeFile::remove_files()` from deleting tree files while it is held. Removed trees
+/// wait for the first sync after every pin is dropped.
+pub struct Pin(Arc<AtomicUsize>);
+
+impl Drop for Pin {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+/// Pending tree changes captured by `TreeFile::checkpoint()`.
+pub struct Checkpoint<T> {
+  updated: Pending<T>,
+  removed: HashSet<TreeId>,
+}
+
 impl<S,T,P,V> Clone for TreeFile<S,T,P,V> where T: Tree<P,V>, P: Point, V: Value, S: RA {
   fn clone(&self) -> Self {
     Self {
@@ -23,6 +82,8 @@ impl<S,T,P,V> Clone for TreeFile<S,T,P,V> where T: Tree<P,V>, P: Point, V: Value
       storage: self.storage.clone(),
       updated: self.updated.clone(),
       removed: self.removed.clone(),
+      pins: self.pins.clone(),
+      counters: self.counters.clone(),
       _marker: std::marker::PhantomData,
     }
   }
@@ -30,67 +91,89 @@ impl<S,T,P,V> Clone for TreeFile<S,T,P,V> where T: Tree<P,V>, P: Point, V: Value
 
 impl<S,T,P,V> TreeFile<S,T,P,V> where T: Tree<P,V>, P: Point, V: Value, S: RA {
   pub fn new(fields: Arc<SetupFields>, storage: Arc<Mutex<Box<dyn Storage<S>>>>) -> Self {
-    let cache = Arc::new(Mutex::new(LRU::new(fields.tree_cache_size)));
+    let cache = TreeCache::new(fields.tree_cache_size, fields.tree_cache_bytes);
+    let cache = Arc::new(Mutex::new(cache));
     Self {
       fields,
       cache,
       storage,
       updated: Arc::new(RwLock::new(HashMap::new())),
       removed: Arc::new(RwLock::new(HashSet::new())),
+      pins: Arc::new(AtomicUsize::new(0)),
+      counters: Arc::new(Counters::default()),
       _marker: std::marker::PhantomData,
     }
   }
   pub async fn get(&self, id: &TreeId) -> Result<Arc<Mutex<T>>,Error> {
-    if let Some(t) = self.updated.read().await.get(id) {
-      self.fields.log(&format![
-        "get tree id={} file={}: updated", id, tree::get_file_from_id(id)
-      ]).await?;
+    if let Some((t,_)) = self.updated.read().await.get(id) {
+      self.fields.log(DebugEvent::GetUpdated { id: *id }).await?;
+      Counters::add(&self.counters.cache_hits, 1);
       return Ok(Arc::clone(t));
     }
     if self.removed.read().await.contains(id) {
-      self.fields.log(&format![
-        "get tree id={} file={}: removed (error)", id, tree::get_file_from_id(id)
-      ]).await?;
+      self.fields.log(DebugEvent::GetRemoved { id: *id }).await?;
       return EyrosErrorKind::TreeRemoved { id: *id }.raise();
     }
+    self.get_stored(id).await
+  }
+  // tree `id` from the cache or its file, even if it was removed since the last sync. the file
+  // of a removed tree stays in storage while a Pin is held.
+  pub async fn get_stored(&self, id: &TreeId) -> Result<Arc<Mutex<T>>,Error> {
     {
       let mut cache = self.cache.lock().await;
       if let Some(t) = cache.get(id) {
-        self.fields.log(&format![
-          "get tree id={} file={}: cached", id, tree::get_file_from_id(id)
-        ]).await?;
+        self.fields.log(DebugEvent::CacheHit { id: *id }).await?;
+        Counters::add(&self.counters.cache_hits, 1);
         return Ok(Arc::clone(t));
       }
     }
     {
       let file = tree::get_file_from_id(id);
-      self.fields.log(&format![
-        "get tree id={} file={}: not cached", id, &file
-      ]).await?;
+      self.fields.log(DebugEvent::CacheMiss { id: *id }).await?;
+      Counters::add(&self.counters.cache_misses, 1);
       let mut s = self.storage.lock().await.open(&file).await?;
       let len = s.len().await?;
       if len == 0 {
         return EyrosErrorKind::TreeEmpty { id: *id, file }.raise();
       }
       let bytes = s.read(0, len).await?;
-      self.fields.log(&format!["read {} bytes from tree id={}", len, id]).await?;
-      let t = Arc::new(Mutex::new(T::from_bytes(&bytes)?.1));
-      self.cache.lock().await.put(*id, Arc::clone(&t));
+      self.fields.log(DebugEvent::Read { id: *id, bytes: len }).await?;
+      Counters::add(&self.counters.trees_read, 1);
+      Counters::add(&self.counters.bytes_read, len);
+      let tree = T::from_bytes(&bytes)?.1;
+      let size = tree.count_bytes();
+      let t = Arc::new(Mutex::new(tree));
+      self.cache.lock().await.put(*id, Arc::clone(&t), size);
       Ok(t)
     }
   }
+  // size of a tree file in storage, or the encoded size of a tree that hasn't been synced yet
+  pub async fn stored_len(&self, id: &TreeId) -> Result<u64,Error> {
+    if let Some((_,size)) = self.updated.read().await.get(id) {
+      return Ok(*size as u64);
+    }
+    let s = self.storage.lock().await.open(&tree::get_file_from_id(id)).await?;
+    s.len().await
+  }
   pub async fn put(&self, id: &TreeId, t: Arc<Mutex<T>>) -> Result<(),Error> {
-    self.fields.log(&format!["put tree id={}", id]).await?;
+    self.fields.log(DebugEvent::Put { id: *id }).await?;
+    let size = t.lock().await.count_bytes();
     let mut cache = self.cache.lock().await;
     let mut updated = self.updated.write().await;
     let mut removed = self.removed.write().await;
-    cache.put(*id, Arc::clone(&t));
-    updated.insert(*id, Arc::clone(&t));
+    // pending trees are replaced in place, and a tree in storage is only put again after it
+    // was removed, so any other id is a new tree
+    if !updated.contains_key(id) && !removed.contains(id) {
+      Counters::add(&self.counters.trees_created, 1);
+    }
+    cache.put(*id, Arc::clone(&t), size);
+    updated.insert(*id, (Arc::clone(&t),size));
     removed.remove(id);
     Ok(())
   }
   pub async fn remove(&self, id: &TreeId) -> Result<(),Error> {
-    self.fields.log(&format!["remove tree id={}", id]).await?;
+    self.fields.log(DebugEvent::Remove { id: *id }).await?;
+    Counters::add(&self.counters.trees_removed, 1);
     let mut cache = self.cache.lock().await;
     let mut updated = self.updated.write().await;
     let mut removed = self.removed.write().await;
@@ -99,29 +182,96 @@ impl<S,T,P,V> TreeFile<S,T,P,V> where T: Tree<P,V>, P: Point, V: Value, S: RA {
     removed.insert(*id);
     Ok(())
   }
-  pub async fn sync(&self) -> Result<(),Error> {
-    self.fields.log("sync begin").await?;
+  // id to put a changed version of tree `id` under. trees put since the last sync aren't referred
+  // to by the meta in storage, so they are replaced in place. any other tree moves to a new id
+  // and its file is removed after the next sync writes a meta that no longer refers to it.
+  pub async fn replacement_id(&self, id: &TreeId, next_tree: &mut TreeId) -> Result<TreeId,Error> {
+    if self.updated.read().await.contains_key(id) {
+      return Ok(*id);
+    }
+    self.remove(id).await?;
+    *next_tree += 1;
+    Ok(*next_tree - 1)
+  }
+  pub async fn checkpoint(&self) -> Checkpoint<T> {
+    Checkpoint {
+      updated: self.updated.read().await.clone(),
+      removed: self.removed.read().await.clone(),
+    }
+  }
+  pub async fn restore(&self, checkpoint: Checkpoint<T>) -> Result<(),Error> {
+    self.fields.log(DebugEvent::RestoreCheckpoint).await?;
+    let mut cache = self.cache.lock().await;
     let mut updated = self.updated.write().await;
     let mut removed = self.removed.write().await;
+    // trees put since the checkpoint may reuse ids, so evict every id touched since then
+    for id in updated.keys().chain(removed.iter()) {
+      cache.pop(id);
+    }
+    for (id,(t,size)) in checkpoint.updated.iter() {
+      cache.put(*id, Arc::clone(t), *size);
+    }
+    *updated = checkpoint.updated;
+    *removed = checkpoint.removed;
+    Ok(())
+  }
+  // trees put since the last sync, which are the only trees that can change under an id
+  pub async fn pending_trees(&self) -> HashMap<TreeId,Arc<Mutex<T>>> {
+    self.updated.read().await.iter().map(|(id,(t,_))| (*id,Arc::clone(t))).collect()
+  }
+  pub fn pin(&self) -> Pin {
+    self.pins.fetch_add(1, Ordering::SeqCst);
+    Pin(Arc::clone(&self.pins))
+  }
+  // total CountBytes size of the trees put since the last sync
+  pub async fn pending_bytes(&self) -> u64 {
+    self.updated.read().await.values().map(|(_,size)| *size as u64).sum()
+  }
+  pub async fn sync(&self) -> Result<(),Error> {
+    self.write_updated().await?;
+    self.remove_files().await
+  }
+  // write every tree put since the last sync. the trees stay readable from memory until
+  // they are durable, and removed trees stay in place for remove_files()
+  pub async fn write_updated(&self) -> Result<(),Error> {
+    self.fields.log(DebugEvent::SyncBegin).await?;
+    let mut updated = self.updated.write().await;
     let mut work = vec![];
-    for (id,t) in updated.iter() {
+    for (id,(t,_)) in updated.iter() {
       let file = tree::get_file_from_id(id);
       let tree = Arc::clone(t);
       let storage = Arc::clone(&self.storage);
-      self.fields.log(&format!["sync tree (updated) id={} file={}", id, &file]).await?;
+      let counters = Arc::clone(&self.counters);
+      self.fields.log(DebugEvent::SyncTree { id: *id }).await?;
       work.push(spawn(async move {
         let bytes = tree.lock().await.to_bytes()?;
         let mut s = storage.lock().await.open(&file).await?;
         s.write(0, &bytes).await?;
         s.sync_all().await?;
+        Counters::add(&counters.bytes_written, bytes.len() as u64);
         let res: Result<(),Error> = Ok(());
         res
       }));
     }
+    for r in join_all(work).await { r?; }
+    updated.clear();
+    Ok(())
+  }
+  // delete the files of trees removed since the last sync. this runs after the meta file that
+  // no longer refers to them has been written, so a crash never leaves the meta pointing at
+  // missing trees
+  pub async fn remove_files(&self) -> Result<(),Error> {
+    let mut removed = self.removed.write().await;
+    if self.pins.load(Ordering::SeqCst) > 0 {
+      // a copy may still read these, so leave them for the next sync
+      self.fields.log(DebugEvent::SyncComplete).await?;
+      return Ok(());
+    }
+    let mut work = vec![];
     for id in removed.iter() {
       let file = tree::get_file_from_id(id);
       let storage = self.storage.clone();
-      self.fields.log(&format!["sync tree (remove) id={} file={}", id, &file]).await?;
+      self.fields.log(DebugEvent::SyncRemove { id: *id }).await?;
       work.push(spawn(async move {
         // ignore errors
         match storage.lock().await.remove(&file).await {
@@ -133,9 +283,8 @@ impl<S,T,P,V> TreeFile<S,T,P,V> where T: Tree<P,V>, P: Point, V: Value, S: RA {
       }));
     }
     for r in join_all(work).await { r?; }
-    updated.clear();
     removed.clear();
-    self.fields.log("sync complete").await?;
+    self.fields.log(DebugEvent::SyncComplete).await?;
     Ok(())
   }
 }
@@ -0,0 +1,68 @@
+use crate::{Meta,Tree,TreeId,Point,Value,Error,EyrosErrorKind,RA,Storage,MetaFile,tree};
+use std::collections::HashMap;
+
+// depth-first walk so that a tree is written after its children, once their new ids are known
+enum Step {
+  Enter(TreeId),
+  Leave(TreeId),
+}
+
+// upgrade a database from format version 1, whose meta was read into `meta`. every tree
+// reachable from the roots is read in the old format and written in the current one under a new
+// id, then the meta is written and the old tree files are removed.
+//
+// version 1 tree files are never overwritten, and the meta file of version 1 is only replaced by
+// a later sync, so an upgrade that is interrupted before the new meta is written starts over on
+// the next open.
+pub(crate) async fn upgrade_v1<S,T,P,V>(storage: &mut Box<dyn Storage<S>>, meta: &mut Meta<P>,
+meta_file: &mut MetaFile<S>) -> Result<u64,Error>
+where S: RA, T: Tree<P,V>, P: Point, V: Value {
+  let mut next_tree = meta.next_tree;
+  let mut bytes_written = 0;
+  let mut ids: HashMap<TreeId,TreeId> = HashMap::new();
+  let mut entered: HashMap<TreeId,T> = HashMap::new();
+  let mut steps = meta.roots.iter().filter_map(|r| r.as_ref().map(|r| Step::Enter(r.id)))
+    .collect::<Vec<_>>();
+  while let Some(step) = steps.pop() {
+    match step {
+      Step::Enter(id) => {
+        if entered.contains_key(&id) || ids.contains_key(&id) { continue }
+        let file = tree::get_file_from_id(&id);
+        let mut s = storage.open(&file).await?;
+        let len = s.len().await?;
+        if len == 0 {
+          return EyrosErrorKind::TreeEmpty { id, file }.raise();
+        }
+        let mut t = T::from_bytes_v1(&s.read(0, len).await?)?;
+        steps.push(Step::Leave(id));
+        steps.extend(t.list_refs().iter().map(|r| Step::Enter(r.id)));
+        entered.insert(id, t);
+      },
+      Step::Leave(id) => {
+        let mut t = entered.remove(&id).unwrap();
+        for r in t.list_refs().iter() {
+          t = t.replace_ref(r.id, ids[&r.id]);
+        }
+        let bytes = t.to_bytes()?;
+        let to = next_tree;
+        next_tree += 1;
+        let mut s = storage.open(&tree::get_file_from_id(&to)).await?;
+        s.write(0, &bytes).await?;
+        // files left over from an interrupted upgrade may be longer
+        s.truncate(bytes.len() as u64).await?;
+        s.sync_all().await?;
+        ids.insert(id, to);
+        bytes_written += bytes.len() as u64;
+      },
+    }
+  }
+  for r in meta.roots.iter_mut().flatten() {
+    r.id = ids[&r.id];
+  }
+  meta.next_tree = next_tree;
+  bytes_written += meta_file.write(meta).await?;
+  for id in ids.keys() {
+    storage.remove(&tree::get_file_from_id(id)).await?;
+  }
+  Ok(bytes_written)
+}
@@ -25,8 +25,34 @@ def_value![i8];
 def_value![i16];
 def_value![i32];
 def_value![i64];
+def_value![u128];
+def_value![i128];
+def_value![bool];
 
 impl<T> Value for Vec<T> where T: Value+Clone+Eq {
   type Id = Vec<T>;
   fn get_id(&self) -> Self { self.clone() }
 }
+
+impl<T,const N: usize> Value for [T;N] where T: Value+Eq+Default+Copy {
+  type Id = [T;N];
+  fn get_id(&self) -> Self { *self }
+}
+
+// tuples are their own id, like Vec<T>
+macro_rules! def_tuple_value {
+  ($($T:ident),+) => {
+    impl<$($T),+> Value for ($($T),+) where $($T: Value+Eq),+ {
+      type Id = ($($T),+);
+      fn get_id(&self) -> Self { self.clone() }
+    }
+  }
+}
+
+def_tuple_value![A,B];
+def_tuple_value![A,B,C];
+def_tuple_value![A,B,C,D];
+def_tuple_value![A,B,C,D,E];
+def_tuple_value![A,B,C,D,E,F];
+def_tuple_value![A,B,C,D,E,F,G];
+def_tuple_value![A,B,C,D,E,F,G,H];
@@ -0,0 +1,126 @@
+use crate::{DB,Tree,Point,Value,Error,RA,TreeId,point_to_bytes,check_point};
+use std::collections::{HashSet,VecDeque};
+
+/// Result of `DB::verify()`. The database is consistent when `problems` is empty.
+#[derive(Debug,Clone)]
+pub struct Verify<P> where P: Point {
+  /// Number of trees that were read and checked.
+  pub trees: usize,
+  /// Number of records in the trees that were read.
+  pub records: usize,
+  pub problems: Vec<Problem<P>>,
+}
+
+impl<P> Verify<P> where P: Point {
+  /// Whether no problems were found.
+  pub fn is_ok(&self) -> bool {
+    self.problems.is_empty()
+  }
+}
+
+/// Inconsistency found by `DB::verify()`.
+#[derive(Debug,Clone)]
+pub enum Problem<P> where P: Point {
+  /// A tree could not be read or decoded.
+  Unreadable { id: TreeId, error: String },
+  /// A record or a ref in tree `id` is not covered by the bounds that point to the tree.
+  OutOfBounds { id: TreeId, point: P, bounds: P },
+  /// A record in tree `id` fails `Point::check()`, or `Point::check_total_order()` with
+  /// `Setup::total_order()`, or has the wrong number of dimensions.
+  InvalidPoint { id: TreeId, point: P, error: String },
+  /// A tree is referred to more than once.
+  DuplicateRef { id: TreeId },
+  /// A tree id was never handed out by the database meta.
+  UnallocatedId { id: TreeId, next_tree: TreeId },
+}
+
+impl<P> std::fmt::Display for Problem<P> where P: Point {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Problem::Unreadable { id, error } => {
+        write![f, "tree {} could not be read: {}", id, error]
+      },
+      Problem::OutOfBounds { id, point, bounds } => {
+        write![f, "{:?} in tree {} is outside of its bounds {:?}", point, id, bounds]
+      },
+      Problem::InvalidPoint { id, point, error } => {
+        write![f, "invalid point {:?} in tree {}: {}", point, id, error]
+      },
+      Problem::DuplicateRef { id } => {
+        write![f, "tree {} is referred to more than once", id]
+      },
+      Problem::UnallocatedId { id, next_tree } => {
+        write![f, "tree {} is not below the next tree id {}", id, next_tree]
+      },
+    }
+  }
+}
+
+// whether `bounds` covers `point`. points have no PartialEq, so compare encoded unions instead.
+// union(bounds,bounds) turns scalars into intervals so that both sides have the same form.
+// point types without a union can only be checked for overlap.
+fn covers<P>(bounds: &P, point: &P) -> Result<bool,Error> where P: Point {
+  match (bounds.union(point),bounds.union(bounds)) {
+    (Some(a),Some(b)) => Ok(point_to_bytes(&a)? == point_to_bytes(&b)?),
+    _ => Ok(bounds.overlap(point)),
+  }
+}
+
+pub async fn verify<S,T,P,V>(db: &DB<S,T,P,V>) -> Result<Verify<P>,Error>
+where S: RA, P: Point, V: Value, T: Tree<P,V> {
+  let (roots,next_tree,dimensions) = {
+    let meta = db.meta.read().await;
+    (meta.roots.clone(), meta.next_tree, meta.dimensions)
+  };
+  let mut verify = Verify { trees: 0, records: 0, problems: vec![] };
+  let mut seen = HashSet::new();
+  let mut cursors = roots.iter().filter_map(|r| r.clone()).collect::<VecDeque<_>>();
+  while let Some(r) = cursors.pop_front() {
+    if !seen.insert(r.id) {
+      verify.problems.push(Problem::DuplicateRef { id: r.id });
+      continue;
+    }
+    if r.id >= next_tree {
+      verify.problems.push(Problem::UnallocatedId { id: r.id, next_tree });
+    }
+    let (rows,refs) = match db.trees.get(&r.id).await {
+      Ok(t) => t.lock().await.list(),
+      Err(e) => {
+        verify.problems.push(Problem::Unreadable { id: r.id, error: e.to_string() });
+        continue;
+      },
+    };
+    verify.trees += 1;
+    verify.records += rows.len();
+    for (point,_) in rows.iter() {
+      let checked = check_point(&db.fields, point).map_err(|e| e.to_string()).and_then(|_| {
+        let n = point.dimensions();
+        if dimensions > 0 && n != dimensions {
+          Err(format!["expected {} dimensions, found {}", dimensions, n])
+        } else {
+          Ok(())
+        }
+      });
+      if let Err(error) = checked {
+        verify.problems.push(Problem::InvalidPoint { id: r.id, point: point.clone(), error });
+      } else if !covers(&r.bounds, point)? {
+        verify.problems.push(Problem::OutOfBounds {
+          id: r.id,
+          point: point.clone(),
+          bounds: r.bounds.clone(),
+        });
+      }
+    }
+    for x in refs {
+      if !covers(&r.bounds, &x.bounds)? {
+        verify.problems.push(Problem::OutOfBounds {
+          id: r.id,
+          point: x.bounds.clone(),
+          bounds: r.bounds.clone(),
+        });
+      }
+      cursors.push_back(x);
+    }
+  }
+  Ok(verify)
+}
@@ -1,4 +1,5 @@
-use crate::{DB,Setup,Row,Coord,Point,Value,TreeRef,tree,BatchOptions,Error as E};
+use crate::{DB,Setup,SetupFields,Row,Coord,Value,TreeRef,TreeId,tree,BatchOptions,
+  BatchReport,Stats,Problem,Point,Error as E};
 mod storage;
 pub use storage::{JsStorage,JsRandomAccess};
 mod stream;
@@ -70,13 +71,110 @@ impl FromBytes for V {
   }
 }
 
+// interval ends that are null, undefined, or infinite have no limit
+fn interval_end(x: &JsValue) -> Option<f64> {
+  x.as_f64().filter(|x| x.is_finite() || x.is_nan())
+}
+
+// convert js numbers into coordinates, failing on values that the coordinate type would
+// otherwise silently round, truncate, or saturate
+trait FromJs: Sized {
+  fn from_js(x: f64) -> Result<Self,Error>;
+}
+impl FromJs for f32 {
+  fn from_js(x: f64) -> Result<Self,Error> { Ok(x as f32) }
+}
+impl FromJs for f64 {
+  fn from_js(x: f64) -> Result<Self,Error> { Ok(x) }
+}
+impl FromJs for u32 {
+  fn from_js(x: f64) -> Result<Self,Error> {
+    if x.fract() == 0.0 && x >= 0.0 && x <= (u32::MAX as f64) {
+      Ok(x as u32)
+    } else {
+      Err(Error::new(&format!["coordinate {} can't be stored as a u32", x]))
+    }
+  }
+}
+
+fn report_to_js(report: BatchReport<V>) -> Result<JsValue,Error> {
+  let errf = |e| Error::new(&format!["{:?}",e]);
+  let ids = |xs: &[TreeId]| xs.iter().map(|x| JsValue::from_f64(*x as f64)).collect::<Array>();
+  let r = Object::new();
+  set(&r, &"inserted".into(), &JsValue::from_f64(report.inserted as f64)).map_err(errf)?;
+  set(&r, &"deleted".into(), &JsValue::from_f64(report.deleted as f64)).map_err(errf)?;
+  let missing = report.missing.iter()
+    .map(|id| JsValue::from(Uint8Array::from(id.as_slice())))
+    .collect::<Array>();
+  set(&r, &"missing".into(), &missing).map_err(errf)?;
+  set(&r, &"treesCreated".into(), &ids(&report.trees_created)).map_err(errf)?;
+  set(&r, &"treesUpdated".into(), &ids(&report.trees_updated)).map_err(errf)?;
+  set(&r, &"treesRemoved".into(), &ids(&report.trees_removed)).map_err(errf)?;
+  Ok(r.into())
+}
+
+fn fields_to_js(fields: &SetupFields) -> Result<JsValue,Error> {
+  let errf = |e| Error::new(&format!["{:?}",e]);
+  let r = Object::new();
+  for (key,x) in [
+    ("branchFactor", fields.branch_factor),
+    ("maxDepth", fields.max_depth),
+    ("maxRecords", fields.max_records),
+    ("extRecords", fields.ext_records),
+    ("inline", fields.inline),
+    ("inlineMaxBytes", fields.inline_max_bytes),
+    ("treeCacheSize", fields.tree_cache_size),
+    ("treeCacheBytes", fields.tree_cache_bytes),
+    ("maxPendingBytes", fields.max_pending_bytes),
+    ("rebuildDepth", fields.rebuild_depth),
+  ] {
+    set(&r, &key.into(), &JsValue::from_f64(x as f64)).map_err(errf)?;
+  }
+  set(&r, &"totalOrder".into(), &JsValue::from_bool(fields.total_order)).map_err(errf)?;
+  Ok(r.into())
+}
+
+fn stats_to_js(stats: &Stats) -> Result<JsValue,Error> {
+  let errf = |e| Error::new(&format!["{:?}",e]);
+  let r = Object::new();
+  for (key,x) in [
+    ("cacheHits", stats.cache_hits),
+    ("cacheMisses", stats.cache_misses),
+    ("treesRead", stats.trees_read),
+    ("bytesRead", stats.bytes_read),
+    ("bytesWritten", stats.bytes_written),
+    ("treesCreated", stats.trees_created),
+    ("treesRemoved", stats.trees_removed),
+    ("queries", stats.queries),
+    ("rowsReturned", stats.rows_returned),
+  ] {
+    set(&r, &key.into(), &JsValue::from_f64(x as f64)).map_err(errf)?;
+  }
+  Ok(r.into())
+}
+
+fn problem_to_js<P>(problem: &Problem<P>) -> Result<JsValue,Error> where P: Point {
+  let errf = |e| Error::new(&format!["{:?}",e]);
+  let (kind,id) = match problem {
+    Problem::Unreadable { id, .. } => ("unreadable", id),
+    Problem::OutOfBounds { id, .. } => ("outOfBounds", id),
+    Problem::InvalidPoint { id, .. } => ("invalidPoint", id),
+    Problem::DuplicateRef { id } => ("duplicateRef", id),
+    Problem::UnallocatedId { id, .. } => ("unallocatedId", id),
+  };
+  let r = Object::new();
+  set(&r, &"type".into(), &kind.into()).map_err(errf)?;
+  set(&r, &"id".into(), &JsValue::from_f64(*id as f64)).map_err(errf)?;
+  set(&r, &"message".into(), &problem.to_string().into()).map_err(errf)?;
+  Ok(r.into())
+}
+
 macro_rules! def_mix {
   ($C:ident, $Stream:ident, $Tree:ident, $open:ident, $n:literal, ($($T:ty),+), ($($I:tt),+)) => {
-    use crate::$Tree;
     pub use stream::$Stream;
     #[wasm_bindgen]
     pub struct $C {
-      db: Arc<Mutex<DB<S,$Tree<$($T),+,V>,($(Coord<$T>),+),V>>>
+      db: Arc<Mutex<DB<S,crate::$Tree<$($T),+,V>,($(Coord<$T>),+),V>>>
     }
     #[wasm_bindgen]
     impl $C {
@@ -84,7 +182,8 @@ macro_rules! def_mix {
         let errf = |e| Error::new(&format!["{:?}",e]);
         let db_ref = Arc::clone(&self.db);
         future_to_promise(async move {
-          let mut r_opts = BatchOptions::new();
+          let mut db = db_ref.lock().await;
+          let mut r_opts = BatchOptions::new().rebuild_depth(db.fields.rebuild_depth);
           if opts.is_object() {
             if let Some(depth) = get(&opts,&"rebuildDepth".into()).map_err(errf)?.as_f64() {
               r_opts = r_opts.rebuild_depth(depth as usize);
@@ -93,11 +192,10 @@ macro_rules! def_mix {
               r_opts = r_opts.error_if_missing(x);
             }
           }
-          let mut db = db_ref.lock().await;
           let batch = Self::batch_rows(rows)?;
-          db.batch_with_options(&batch, &r_opts).await
+          let report = db.batch_with_options(&batch, &r_opts).await
             .map_err(|e| Error::new(&format!["{:?}",e]))?;
-          Ok(JsValue::NULL)
+          report_to_js(report).map_err(|e| e.into())
         })
       }
       fn batch_rows(rows: JsValue) -> Result<Vec<Row<($(Coord<$T>),+),V>>,Error> {
@@ -125,12 +223,12 @@ macro_rules! def_mix {
                   match Array::is_array(&p) {
                     true => {
                       let a: Array = p.into();
-                      Coord::Interval(
-                        a.get(0).as_f64().unwrap() as $T,
-                        a.get(1).as_f64().unwrap() as $T
+                      Coord::from_ends(
+                        interval_end(&a.get(0)).map(<$T>::from_js).transpose()?,
+                        interval_end(&a.get(1)).map(<$T>::from_js).transpose()?
                       )
                     },
-                    false => Coord::Scalar(p.as_f64().unwrap() as $T)
+                    false => Coord::Scalar(<$T>::from_js(p.as_f64().unwrap())?)
                   }
                 }
               ),+), V { data })
@@ -150,12 +248,12 @@ macro_rules! def_mix {
                   match Array::is_array(&p) {
                     true => {
                       let a: Array = p.into();
-                      Coord::Interval(
-                        a.get(0).as_f64().unwrap() as $T,
-                        a.get(1).as_f64().unwrap() as $T
+                      Coord::from_ends(
+                        interval_end(&a.get(0)).map(<$T>::from_js).transpose()?,
+                        interval_end(&a.get(1)).map(<$T>::from_js).transpose()?
                       )
                     },
-                    false => Coord::Scalar(p.as_f64().unwrap() as $T)
+                    false => Coord::Scalar(<$T>::from_js(p.as_f64().unwrap())?)
                   }
                 }
               ),+), buf)
@@ -176,15 +274,14 @@ macro_rules! def_mix {
                 let tr_obj = Object::new();
                 set(&tr_obj, &"id".into(), &JsValue::from_f64(tr.id as f64)).unwrap();
                 set(&tr_obj, &"file".into(), &tree::get_file_from_id(&tr.id).into()).unwrap();
-                let bbox = tr.bounds.to_bounds().unwrap();
                 let bbox_js = Array::new_with_length($n*2);
                 $(
-                  bbox_js.set($I, JsValue::from_f64((bbox.0).$I as f64));
-                  bbox_js.set($I, JsValue::from_f64((bbox.0).$I as f64));
+                  bbox_js.set($I, JsValue::from_f64(tr.bounds.$I.min()
+                    .map(|x| *x as f64).unwrap_or(f64::NEG_INFINITY)));
                 )+
                 $(
-                  bbox_js.set($I+$n, JsValue::from_f64((bbox.1).$I as f64));
-                  bbox_js.set($I+$n, JsValue::from_f64((bbox.1).$I as f64));
+                  bbox_js.set($I+$n, JsValue::from_f64(tr.bounds.$I.max()
+                    .map(|x| *x as f64).unwrap_or(f64::INFINITY)));
                 )+
                 set(&tr_obj, &"bbox".into(), &bbox_js).unwrap();
                 f.call1(&JsValue::NULL, &tr_obj).unwrap();
@@ -201,10 +298,10 @@ macro_rules! def_mix {
           let bbox_a: Array = bbox_js.into();
           let bbox = (
             ($(
-              bbox_a.get($I).as_f64().unwrap() as $T
+              <$T>::from_js(bbox_a.get($I).as_f64().unwrap())?
             ),+),
             ($(
-              bbox_a.get($I+$n).as_f64().unwrap() as $T
+              <$T>::from_js(bbox_a.get($I+$n).as_f64().unwrap())?
             ),+)
           );
           let mut db = db_ref.lock().await;
@@ -227,6 +324,123 @@ macro_rules! def_mix {
           Ok(JsValue::NULL)
         })
       }
+      pub fn optimize(&self, rebuild_depth: JsValue) -> Promise {
+        let db_ref = Arc::clone(&self.db);
+        future_to_promise(async move {
+          let mut db = db_ref.lock().await;
+          let depth = rebuild_depth.as_f64().map(|x| x as usize)
+            .unwrap_or(db.fields.rebuild_depth);
+          db.optimize(depth).await.map_err(|e| Error::new(&format!["{:?}",e]))?;
+          Ok(JsValue::NULL)
+        })
+      }
+      pub fn fields(&self) -> Promise {
+        let db_ref = Arc::clone(&self.db);
+        future_to_promise(async move {
+          let db = db_ref.lock().await;
+          fields_to_js(&db.fields).map_err(|e| e.into())
+        })
+      }
+      pub fn info(&self) -> Promise {
+        let errf = |e| Error::new(&format!["{:?}",e]);
+        let db_ref = Arc::clone(&self.db);
+        future_to_promise(async move {
+          let db = db_ref.lock().await;
+          let info = db.info().await.map_err(|e| Error::new(&format!["{:?}",e]))?;
+          let r = Object::new();
+          let roots = Array::new();
+          for root in info.roots.iter() {
+            let x = Object::new();
+            set(&x, &"index".into(), &JsValue::from_f64(root.index as f64)).map_err(errf)?;
+            set(&x, &"id".into(), &JsValue::from_f64(root.id as f64)).map_err(errf)?;
+            set(&x, &"records".into(), &JsValue::from_f64(root.records as f64)).map_err(errf)?;
+            set(&x, &"trees".into(), &JsValue::from_f64(root.trees as f64)).map_err(errf)?;
+            set(&x, &"depth".into(), &JsValue::from_f64(root.depth as f64)).map_err(errf)?;
+            set(&x, &"bbox".into(), &Self::bbox_js(&root.bounds)).map_err(errf)?;
+            roots.push(&x);
+          }
+          set(&r, &"roots".into(), &roots).map_err(errf)?;
+          for (key,x) in [
+            ("trees", info.trees as f64),
+            ("records", info.records as f64),
+            ("inlineRecords", info.inline_records as f64),
+            ("externalRecords", info.external_records as f64),
+            ("bytes", info.bytes as f64),
+            ("inlineRatio", info.inline_ratio()),
+          ] {
+            set(&r, &key.into(), &JsValue::from_f64(x)).map_err(errf)?;
+          }
+          let depths = info.depths.iter().map(|x| JsValue::from_f64(*x as f64)).collect::<Array>();
+          set(&r, &"depths".into(), &depths).map_err(errf)?;
+          let bbox = info.bounds.as_ref().map(Self::bbox_js).unwrap_or(JsValue::NULL);
+          set(&r, &"bbox".into(), &bbox).map_err(errf)?;
+          Ok(r.into())
+        })
+      }
+      pub fn verify(&self) -> Promise {
+        let errf = |e| Error::new(&format!["{:?}",e]);
+        let db_ref = Arc::clone(&self.db);
+        future_to_promise(async move {
+          let db = db_ref.lock().await;
+          let v = db.verify().await.map_err(|e| Error::new(&format!["{:?}",e]))?;
+          let r = Object::new();
+          set(&r, &"ok".into(), &JsValue::from_bool(v.is_ok())).map_err(errf)?;
+          set(&r, &"trees".into(), &JsValue::from_f64(v.trees as f64)).map_err(errf)?;
+          set(&r, &"records".into(), &JsValue::from_f64(v.records as f64)).map_err(errf)?;
+          let problems = Array::new();
+          for p in v.problems.iter() {
+            problems.push(&problem_to_js(p)?);
+          }
+          set(&r, &"problems".into(), &problems).map_err(errf)?;
+          Ok(r.into())
+        })
+      }
+      pub fn stats(&self) -> Promise {
+        let db_ref = Arc::clone(&self.db);
+        future_to_promise(async move {
+          let db = db_ref.lock().await;
+          stats_to_js(&db.stats()).map_err(|e| e.into())
+        })
+      }
+      pub fn export(&self) -> Promise {
+        let db_ref = Arc::clone(&self.db);
+        future_to_promise(async move {
+          let db = db_ref.lock().await;
+          let mut buf = vec![];
+          db.export(&mut buf).await.map_err(|e| Error::new(&format!["{:?}",e]))?;
+          Ok(Uint8Array::from(buf.as_slice()).into())
+        })
+      }
+      pub fn import(&self, data: Uint8Array) -> Promise {
+        let errf = |e| Error::new(&format!["{:?}",e]);
+        let db_ref = Arc::clone(&self.db);
+        future_to_promise(async move {
+          let mut db = db_ref.lock().await;
+          let report = db.import(data.to_vec().as_slice()).await
+            .map_err(|e| Error::new(&format!["{:?}",e]))?;
+          let r = Object::new();
+          set(&r, &"rows".into(), &JsValue::from_f64(report.rows as f64)).map_err(errf)?;
+          set(&r, &"dimensions".into(), &JsValue::from_f64(report.header.dimensions as f64))
+            .map_err(errf)?;
+          let scalars = report.header.scalars.iter().map(|k| JsValue::from(k.name()))
+            .collect::<Array>();
+          set(&r, &"scalars".into(), &scalars).map_err(errf)?;
+          Ok(r.into())
+        })
+      }
+      // bounds as [min0,min1,...,max0,max1,...] with infinite ends for open intervals
+      fn bbox_js(bounds: &($(Coord<$T>),+)) -> JsValue {
+        let bbox = Array::new_with_length($n*2);
+        $(
+          bbox.set($I, JsValue::from_f64(bounds.$I.min()
+            .map(|x| *x as f64).unwrap_or(f64::NEG_INFINITY)));
+        )+
+        $(
+          bbox.set($I+$n, JsValue::from_f64(bounds.$I.max()
+            .map(|x| *x as f64).unwrap_or(f64::INFINITY)));
+        )+
+        bbox.into()
+      }
     }
     #[wasm_bindgen]
     pub async fn $open(opts: JsValue) -> Result<$C,Error> {
@@ -247,11 +461,12 @@ macro_rules! def_mix {
             Receiver<(String,Sender<Result<JsRandomAccess,E>>)>,
           ) = unbounded();
           spawn_local(async move {
-            // todo: do something with these errors
             while let Ok((name,s)) = receiver.recv().await {
-              let context = JsError::wrap(storage_fn.call1(&JsValue::NULL, &name.into()))
-                .unwrap();
-              s.send(JsRandomAccess::from_context(context).await).await.unwrap();
+              let r = match JsError::wrap(storage_fn.call1(&JsValue::NULL, &name.into())) {
+                Ok(context) => JsRandomAccess::from_context(context).await,
+                Err(e) => Err(e),
+              };
+              if let Err(_) = s.send(r).await {} // ignore send errors
             }
           });
           sender
@@ -262,12 +477,12 @@ macro_rules! def_mix {
             Receiver<(String,Sender<Result<(),E>>)>,
           ) = unbounded();
           spawn_local(async move {
-            // todo: do something with these errors
             while let Ok((name,s)) = receiver.recv().await {
-              s.send(match remove_fn.call1(&JsValue::NULL, &name.into()) {
-                Err(e) => JsError::wrap(Err(e.into())),
+              let r = match remove_fn.call1(&JsValue::NULL, &name.into()) {
+                Err(e) => JsError::wrap(Err(e)),
                 _ => Ok(()),
-              }).await.unwrap();
+              };
+              if let Err(_) = s.send(r).await {} // ignore send errors
             }
           });
           sender
@@ -285,18 +500,38 @@ macro_rules! def_mix {
         Some(x) => { setup = setup.max_records(x as usize); },
         _ => {},
       };
+      match get(&opts,&"extRecords".into()).map_err(errf)?.as_f64() {
+        Some(x) => { setup = setup.ext_records(x as usize); },
+        _ => {},
+      };
       match get(&opts,&"inline".into()).map_err(errf)?.as_f64() {
         Some(x) => { setup = setup.inline(x as usize); },
         _ => {},
       };
+      match get(&opts,&"inlineMaxBytes".into()).map_err(errf)?.as_f64() {
+        Some(x) => { setup = setup.inline_max_bytes(x as usize); },
+        _ => {},
+      };
       match get(&opts,&"treeCacheSize".into()).map_err(errf)?.as_f64() {
         Some(x) => { setup = setup.tree_cache_size(x as usize); },
         _ => {},
       };
+      match get(&opts,&"treeCacheBytes".into()).map_err(errf)?.as_f64() {
+        Some(x) => { setup = setup.tree_cache_bytes(x as usize); },
+        _ => {},
+      };
+      match get(&opts,&"maxPendingBytes".into()).map_err(errf)?.as_f64() {
+        Some(x) => { setup = setup.max_pending_bytes(x as usize); },
+        _ => {},
+      };
       match get(&opts,&"rebuildDepth".into()).map_err(errf)?.as_f64() {
         Some(x) => { setup = setup.rebuild_depth(x as usize); },
         _ => {},
       };
+      match get(&opts,&"totalOrder".into()).map_err(errf)?.as_bool() {
+        Some(x) => { setup = setup.total_order(x); },
+        _ => {},
+      };
       match get(&opts,&"debug".into()).map_err(errf)?.dyn_into::<Function>() {
         Ok(f) => {
           let (sender,receiver): (Sender<String>, Receiver<String>) = unbounded();
@@ -311,7 +546,7 @@ macro_rules! def_mix {
         Err(_) => {},
       };
       type P = ($(Coord<$T>),+);
-      type T = $Tree<$($T),+,V>;
+      type T = crate::$Tree<$($T),+,V>;
       let db: DB<S,T,P,V> = setup.build().await
         .map_err(|e| Error::new(&format!["{:?}",e]))?;
       Ok($C { db: Arc::new(Mutex::new(db)) })
@@ -319,37 +554,71 @@ macro_rules! def_mix {
   }
 }
 
+// each instantiation is exported as open_{types} and selected from js with opts.types.
+// the stream types in stream.rs must match.
+
 #[cfg(feature="2d")]
 def_mix![JsDB2,JsStream2,Tree2,
   open_f32_f32,2,(f32,f32),(0,1)];
+#[cfg(feature="2d")]
+def_mix![JsDB2F64,JsStream2F64,Tree2,
+  open_f64_f64,2,(f64,f64),(0,1)];
 
 #[cfg(feature="3d")]
 def_mix![JsDB3,JsStream3,Tree3,
   open_f32_f32_f32,3,(f32,f32,f32),(0,1,2)];
+#[cfg(feature="3d")]
+def_mix![JsDB3F64,JsStream3F64,Tree3,
+  open_f64_f64_f64,3,(f64,f64,f64),(0,1,2)];
+#[cfg(feature="3d")]
+def_mix![JsDB3F64U32,JsStream3F64U32,Tree3,
+  open_f64_f64_u32,3,(f64,f64,u32),(0,1,2)];
 
 #[cfg(feature="4d")]
 def_mix![JsDB4,JsStream4,Tree4,
   open_f32_f32_f32_f32,4,(f32,f32,f32,f32),(0,1,2,3)];
+#[cfg(feature="4d")]
+def_mix![JsDB4F64,JsStream4F64,Tree4,
+  open_f64_f64_f64_f64,4,(f64,f64,f64,f64),(0,1,2,3)];
+#[cfg(feature="4d")]
+def_mix![JsDB4F64U32,JsStream4F64U32,Tree4,
+  open_f64_f64_f64_u32,4,(f64,f64,f64,u32),(0,1,2,3)];
 
 #[cfg(feature="5d")]
 def_mix![JsDB5,JsStream5,Tree5,
   open_f32_f32_f32_f32_f32,
   5,(f32,f32,f32,f32,f32),(0,1,2,3,4)];
+#[cfg(feature="5d")]
+def_mix![JsDB5F64,JsStream5F64,Tree5,
+  open_f64_f64_f64_f64_f64,
+  5,(f64,f64,f64,f64,f64),(0,1,2,3,4)];
 
 #[cfg(feature="6d")]
 def_mix![JsDB6,JsStream6,Tree6,
   open_f32_f32_f32_f32_f32_f32,
   6,(f32,f32,f32,f32,f32,f32),(0,1,2,3,4,5)];
+#[cfg(feature="6d")]
+def_mix![JsDB6F64,JsStream6F64,Tree6,
+  open_f64_f64_f64_f64_f64_f64,
+  6,(f64,f64,f64,f64,f64,f64),(0,1,2,3,4,5)];
 
 #[cfg(feature="7d")]
 def_mix![JsDB7,JsStream7,Tree7,
   open_f32_f32_f32_f32_f32_f32_f32,
   7,(f32,f32,f32,f32,f32,f32,f32),(0,1,2,3,4,5,6)];
+#[cfg(feature="7d")]
+def_mix![JsDB7F64,JsStream7F64,Tree7,
+  open_f64_f64_f64_f64_f64_f64_f64,
+  7,(f64,f64,f64,f64,f64,f64,f64),(0,1,2,3,4,5,6)];
 
 #[cfg(feature="8d")]
 def_mix![JsDB8,JsStream8,Tree8,
   open_f32_f32_f32_f32_f32_f32_f32_f32,
   8,(f32,f32,f32,f32,f32,f32,f32,f32),(0,1,2,3,4,5,6,7)];
+#[cfg(feature="8d")]
+def_mix![JsDB8F64,JsStream8F64,Tree8,
+  open_f64_f64_f64_f64_f64_f64_f64_f64,
+  8,(f64,f64,f64,f64,f64,f64,f64,f64),(0,1,2,3,4,5,6,7)];
 
 #[wasm_bindgen]
 extern "C" {
@@ -1,6 +1,6 @@
 use crate::{Storage,Error,wasm::error::JsError};
 use random_access_storage::RandomAccess;
-use wasm_bindgen::{prelude::JsValue,closure::Closure};
+use wasm_bindgen::{prelude::JsValue,closure::Closure,JsCast};
 use wasm_bindgen_futures::spawn_local;
 use js_sys::{Function,Uint8Array,Reflect::get};
 use async_std::channel::{unbounded,Sender,Receiver};
@@ -50,12 +50,12 @@ impl JsRandomAccess {
     Self { rpc }
   }
   pub async fn from_context(context: JsValue) -> Result<Self,Error> {
-    let write_fn: Function = JsError::wrap(get(&context,&"write".into()))?.into();
-    let read_fn: Function = JsError::wrap(get(&context,&"read".into()))?.into();
-    let len_fn: Function = JsError::wrap(get(&context,&"len".into()))?.into();
-    let truncate_fn: Function = JsError::wrap(get(&context,&"truncate".into()))?.into();
-    let del_fn: Function = JsError::wrap(get(&context,&"del".into()))?.into();
-    let sync_fn: Function = JsError::wrap(get(&context,&"sync".into()))?.into();
+    let write_fn = get_fn(&context, "write")?;
+    let read_fn = get_fn(&context, "read")?;
+    let len_fn = get_fn(&context, "len")?;
+    let truncate_fn = get_fn(&context, "truncate")?;
+    let del_fn = get_fn(&context, "del")?;
+    let sync_fn = get_fn(&context, "sync")?;
     let (sender, receiver): (
       Sender<(JRequest,Sender<Result<JResponse,Error>>)>,
       Receiver<(JRequest,Sender<Result<JResponse,Error>>)>,
@@ -64,6 +64,7 @@ impl JsRandomAccess {
       while let Ok((msg,s)) = receiver.recv().await {
         match msg {
           JRequest::Write { offset, data } => {
+            let s_err = s.clone();
             let cb = Closure::once_into_js(Box::new(move |err: JsValue| {
               spawn_local(async move {
                 let r = s.send(JsError::wrap(
@@ -73,14 +74,17 @@ impl JsRandomAccess {
                 if let Err(_) = r.await {} // ignore send errors
               });
             }) as Box<dyn FnOnce(JsValue)>);
-            write_fn.call3(
+            if let Err(e) = write_fn.call3(
               &JsValue::NULL,
               &JsValue::from_f64(offset as f64),
               unsafe { &Uint8Array::view(&data) },
               &cb
-            ).unwrap();
+            ) {
+              reply_err(&s_err, e).await;
+            }
           },
           JRequest::Read { offset, length } => {
+            let s_err = s.clone();
             let cb = Closure::once_into_js(Box::new(move |err: JsValue, value: JsValue| {
               spawn_local(async move {
                 let r = s.send(JsError::wrap(
@@ -93,14 +97,17 @@ impl JsRandomAccess {
                 if let Err(_) = r.await {} // ignore send errors
               });
             }) as Box<dyn FnOnce(JsValue,JsValue)>);
-            read_fn.call3(
+            if let Err(e) = read_fn.call3(
               &JsValue::NULL,
               &JsValue::from_f64(offset as f64),
               &JsValue::from_f64(length as f64),
               &cb
-            ).unwrap();
+            ) {
+              reply_err(&s_err, e).await;
+            }
           },
           JRequest::Del { offset, length } => {
+            let s_err = s.clone();
             let cb = Closure::once_into_js(Box::new(move |err: JsValue| {
               spawn_local(async move {
                 let r = s.send(JsError::wrap(
@@ -110,14 +117,17 @@ impl JsRandomAccess {
                 if let Err(_) = r.await {} // ignore send errors
               });
             }) as Box<dyn FnOnce(JsValue)>);
-            del_fn.call3(
+            if let Err(e) = del_fn.call3(
               &JsValue::NULL,
               &JsValue::from_f64(offset as f64),
               &JsValue::from_f64(length as f64),
               &cb
-            ).unwrap();
+            ) {
+              reply_err(&s_err, e).await;
+            }
           },
           JRequest::Len {} => {
+            let s_err = s.clone();
             let cb = Closure::once_into_js(Box::new(move |err: JsValue, value: JsValue| {
               spawn_local(async move {
                 let r = s.send(JsError::wrap(
@@ -127,9 +137,12 @@ impl JsRandomAccess {
                 if let Err(_) = r.await {} // ignore send errors
               });
             }) as Box<dyn FnOnce(JsValue,JsValue)>);
-            len_fn.call1(&JsValue::NULL, &cb).unwrap();
+            if let Err(e) = len_fn.call1(&JsValue::NULL, &cb) {
+              reply_err(&s_err, e).await;
+            }
           },
           JRequest::Truncate { length } => {
+            let s_err = s.clone();
             let cb = Closure::once_into_js(Box::new(move |err: JsValue| {
               spawn_local(async move {
                 let r = s.send(JsError::wrap(
@@ -139,13 +152,16 @@ impl JsRandomAccess {
                 if let Err(_) = r.await {} // ignore send errors
               });
             }) as Box<dyn FnOnce(JsValue)>);
-            truncate_fn.call2(
+            if let Err(e) = truncate_fn.call2(
               &JsValue::NULL,
               &JsValue::from_f64(length as f64),
               &cb
-            ).unwrap();
+            ) {
+              reply_err(&s_err, e).await;
+            }
           },
           JRequest::IsEmpty {} => {
+            let s_err = s.clone();
             let cb = Closure::once_into_js(Box::new(move |err: JsValue, value: JsValue| {
               spawn_local(async move {
                 let r = s.send(JsError::wrap(
@@ -158,9 +174,12 @@ impl JsRandomAccess {
                 if let Err(_) = r.await {} // ignore send errors
               });
             }) as Box<dyn FnOnce(JsValue,JsValue)>);
-            len_fn.call1(&JsValue::NULL, &cb).unwrap();
+            if let Err(e) = len_fn.call1(&JsValue::NULL, &cb) {
+              reply_err(&s_err, e).await;
+            }
           },
           JRequest::SyncAll {} => {
+            let s_err = s.clone();
             let cb = Closure::once_into_js(Box::new(move |err: JsValue| {
               spawn_local(async move {
                 let r = s.send(JsError::wrap(
@@ -170,7 +189,9 @@ impl JsRandomAccess {
                 if let Err(_) = r.await {} // ignore send errors
               });
             }) as Box<dyn FnOnce(JsValue)>);
-            sync_fn.call1(&JsValue::NULL, &cb).unwrap();
+            if let Err(e) = sync_fn.call1(&JsValue::NULL, &cb) {
+              reply_err(&s_err, e).await;
+            }
           },
         }
       }
@@ -179,6 +200,18 @@ impl JsRandomAccess {
   }
 }
 
+fn get_fn(context: &JsValue, name: &str) -> Result<Function,Error> {
+  match JsError::wrap(get(context,&name.into()))?.dyn_into::<Function>() {
+    Ok(f) => Ok(f),
+    Err(_) => JsError::wrap(Err(format!["storage.{} is not a function", name].into())),
+  }
+}
+
+// send an exception thrown while calling into the js storage back to the waiting request
+async fn reply_err(s: &Sender<Result<JResponse,Error>>, err: JsValue) {
+  if let Err(_) = s.send(JsError::wrap(Err(err))).await {} // ignore send errors
+}
+
 #[async_trait::async_trait]
 impl RandomAccess for JsRandomAccess {
   type Error = Box<dyn std::error::Error+Sync+Send>;
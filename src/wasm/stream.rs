@@ -2,7 +2,16 @@ use crate::{Coord,Error,wasm::V};
 use async_std::{prelude::*,stream::Stream,sync::{Arc,Mutex}};
 use wasm_bindgen::prelude::{wasm_bindgen,JsValue};
 use wasm_bindgen_futures::future_to_promise;
-use js_sys::{Error as JsError,Array,Uint8Array,Promise};
+use js_sys::{Error as JsError,Array,Object,Float64Array,Uint8Array,Uint32Array,Promise,
+  Reflect::set};
+
+// ends of a coordinate as f64, using infinity for ends without a limit
+macro_rules! min_f64 {
+  ($c:expr) => { $c.min().map(|x| *x as f64).unwrap_or(f64::NEG_INFINITY) }
+}
+macro_rules! max_f64 {
+  ($c:expr) => { $c.max().map(|x| *x as f64).unwrap_or(f64::INFINITY) }
+}
 
 macro_rules! def_stream {
   ($C:ident, ($($T:tt),+), $n:literal, ($($i:tt),+)) => {
@@ -33,10 +42,10 @@ macro_rules! def_stream {
                 $(
                   p.set($i, match point.$i {
                     Coord::Scalar(x) => JsValue::from_f64(x as f64),
-                    Coord::Interval(x0,x1) => {
+                    c => {
                       let iv = Array::new_with_length(2);
-                      iv.set(0, JsValue::from_f64(x0 as f64));
-                      iv.set(1, JsValue::from_f64(x1 as f64));
+                      iv.set(0, JsValue::from_f64(min_f64!(c)));
+                      iv.set(1, JsValue::from_f64(max_f64!(c)));
                       iv.into()
                     }
                   });
@@ -52,14 +61,81 @@ macro_rules! def_stream {
           }
         })
       }
+      /// Read up to `n` rows at once into flat typed arrays:
+      /// `coords` has a `min,max` pair for every dimension of every row,
+      /// `mask` has a byte for every dimension of every row set to 1 for intervals,
+      /// intervals without an end use `-Infinity` or `Infinity`,
+      /// and the value for row `i` is `values.subarray(offsets[i],offsets[i+1])`.
+      /// Resolves to `null` once the stream is finished.
+      /// Rejects when `n` is 0, which could never read a row.
+      #[wasm_bindgen(js_name = nextBatch)]
+      pub fn next_batch(&self, n: u32) -> Promise {
+        let stream_ref = Arc::clone(&self.stream);
+        future_to_promise(async move {
+          if n == 0 {
+            return Err(JsError::new("nextBatch(n) requires n > 0").into());
+          }
+          let mut stream = stream_ref.lock().await;
+          let mut coords: Vec<f64> = vec![];
+          let mut mask: Vec<u8> = vec![];
+          let mut values: Vec<u8> = vec![];
+          let mut offsets: Vec<u32> = vec![0];
+          while offsets.len() <= n as usize {
+            match stream.next().await {
+              None => break,
+              Some(Err(e)) => {
+                return Err(JsError::new(&format!["{:?}",e]).into());
+              },
+              Some(Ok((point,value))) => {
+                $(
+                  match point.$i {
+                    Coord::Scalar(x) => {
+                      coords.push(x as f64);
+                      coords.push(x as f64);
+                      mask.push(0);
+                    },
+                    c => {
+                      coords.push(min_f64!(c));
+                      coords.push(max_f64!(c));
+                      mask.push(1);
+                    },
+                  }
+                )+
+                values.extend_from_slice(&value.data);
+                offsets.push(values.len() as u32);
+              }
+            }
+          }
+          if offsets.len() == 1 {
+            return Ok(JsValue::NULL);
+          }
+          let r = Object::new();
+          set(&r, &"coords".into(), &Float64Array::from(coords.as_slice()).into())?;
+          set(&r, &"mask".into(), &Uint8Array::from(mask.as_slice()).into())?;
+          set(&r, &"values".into(), &Uint8Array::from(values.as_slice()).into())?;
+          set(&r, &"offsets".into(), &Uint32Array::from(offsets.as_slice()).into())?;
+          set(&r, &"length".into(), &JsValue::from_f64((offsets.len()-1) as f64))?;
+          Ok(r.into())
+        })
+      }
     }
   }
 }
 
 #[cfg(feature="2d")] def_stream![JsStream2,(f32,f32),2,(0,1)];
+#[cfg(feature="2d")] def_stream![JsStream2F64,(f64,f64),2,(0,1)];
 #[cfg(feature="3d")] def_stream![JsStream3,(f32,f32,f32),3,(0,1,2)];
+#[cfg(feature="3d")] def_stream![JsStream3F64,(f64,f64,f64),3,(0,1,2)];
+#[cfg(feature="3d")] def_stream![JsStream3F64U32,(f64,f64,u32),3,(0,1,2)];
 #[cfg(feature="4d")] def_stream![JsStream4,(f32,f32,f32,f32),4,(0,1,2,3)];
+#[cfg(feature="4d")] def_stream![JsStream4F64,(f64,f64,f64,f64),4,(0,1,2,3)];
+#[cfg(feature="4d")] def_stream![JsStream4F64U32,(f64,f64,f64,u32),4,(0,1,2,3)];
 #[cfg(feature="5d")] def_stream![JsStream5,(f32,f32,f32,f32,f32),5,(0,1,2,3,4)];
+#[cfg(feature="5d")] def_stream![JsStream5F64,(f64,f64,f64,f64,f64),5,(0,1,2,3,4)];
 #[cfg(feature="6d")] def_stream![JsStream6,(f32,f32,f32,f32,f32,f32),6,(0,1,2,3,4,5)];
+#[cfg(feature="6d")] def_stream![JsStream6F64,(f64,f64,f64,f64,f64,f64),6,(0,1,2,3,4,5)];
 #[cfg(feature="7d")] def_stream![JsStream7,(f32,f32,f32,f32,f32,f32,f32),7,(0,1,2,3,4,5,6)];
+#[cfg(feature="7d")] def_stream![JsStream7F64,(f64,f64,f64,f64,f64,f64,f64),7,(0,1,2,3,4,5,6)];
 #[cfg(feature="8d")] def_stream![JsStream8,(f32,f32,f32,f32,f32,f32,f32,f32),8,(0,1,2,3,4,5,6,7)];
+#[cfg(feature="8d")]
+def_stream![JsStream8F64,(f64,f64,f64,f64,f64,f64,f64,f64),8,(0,1,2,3,4,5,6,7)];
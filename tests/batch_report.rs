@@ -0,0 +1,49 @@
+use eyros::{Coord,Row,BatchOptions,Error,EyrosError,EyrosErrorKind};
+use tempfile::Builder as Tmpfile;
+
+use std::collections::HashSet;
+
+mod common;
+use common::{P,V,points,inserts,deletes};
+
+#[async_std::test]
+async fn batch_report() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let points = points(2000);
+  let mut db = eyros::open_from_path2(dir.path()).await?;
+
+  let report = db.batch(&inserts(&points, 0..1000)).await?;
+  assert_eq![report.inserted, 1000];
+  assert_eq![report.deleted, 0];
+  assert![report.missing.is_empty()];
+  assert![!report.trees_created.is_empty(), "first batch creates trees"];
+  assert![report.trees_removed.is_empty(), "first batch has no trees to merge"];
+  let first_trees = report.trees_created.iter().copied().collect::<HashSet<_>>();
+  db.sync().await?;
+
+  let mut batch = deletes(&points, 0..100);
+  batch.push(Row::Delete((Coord::Scalar(0.5),Coord::Scalar(0.5)),5000));
+  batch.push(Row::Delete((Coord::Scalar(-0.5),Coord::Scalar(0.5)),5001));
+  batch.extend(inserts(&points, 1000..1500));
+  let opts = BatchOptions::new().error_if_missing(false);
+  let report = db.batch_with_options(&batch, &opts).await?;
+  assert_eq![report.inserted, 500];
+  assert_eq![report.deleted, 100];
+  assert_eq![report.missing.iter().copied().collect::<HashSet<V>>(), [5000,5001].into(),
+    "missing ids are reported with their id type"];
+  assert![!report.trees_updated.is_empty(), "deletes rewrite existing trees"];
+  assert![report.trees_removed.iter().any(|id| first_trees.contains(id)),
+    "merged trees are removed"];
+  assert![report.trees_created.iter().all(|id| !first_trees.contains(id)),
+    "merged trees get new ids"];
+  db.sync().await?;
+
+  let missing: Vec<Row<P,V>> = vec![Row::Delete((Coord::Scalar(0.5),Coord::Scalar(0.5)),6000)];
+  let err = db.batch(&missing).await.expect_err("error_if_missing is set by default");
+  assert_eq![format!["{}", err], "ids not found during remove(): 6000"];
+  match err.downcast_ref::<EyrosError>().map(|e| e.kind()) {
+    Some(EyrosErrorKind::RemoveIdsMissing { ids }) => assert_eq![ids, &["6000"]],
+    _ => panic!["unexpected error: {:?}", err],
+  }
+  Ok(())
+}
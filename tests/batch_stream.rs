@@ -0,0 +1,83 @@
+use eyros::{DB,Tree2,Setup,Row,Error,Debugger,DebugEvent};
+use tempfile::Builder as Tmpfile;
+
+use std::collections::HashSet;
+use std::sync::{Arc,Mutex};
+
+mod common;
+use common::{P,V,points,inserts,deletes,ids};
+
+#[async_std::test]
+async fn batch_stream() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let rows = inserts(&points(5500), 0..5500);
+  let bbox = ((-1.0,-1.0),(1.0,1.0));
+  {
+    let mut db: DB<_,Tree2<f32,f32,V>,P,V> = Setup::from_path(dir.path())
+      .stream_batch_size(1000)
+      .build()
+      .await?;
+    db.batch_stream(async_std::stream::from_iter(rows.clone())).await?;
+    assert_eq![ids(db.query(&bbox).await?).await?, (0..5500).collect(),
+      "every streamed row is written"];
+  }
+  {
+    let mut db: DB<_,Tree2<f32,f32,V>,P,V> = Setup::from_path(dir.path()).build().await?;
+    assert_eq![ids(db.query(&bbox).await?).await?, (0..5500).collect(),
+      "streamed rows are synced"];
+  }
+  Ok(())
+}
+
+#[async_std::test]
+async fn batch_stream_mixed() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let events = Arc::new(Mutex::new(vec![]));
+  let points = points(4000);
+  let bbox = ((-1.0,-1.0),(1.0,1.0));
+  let mut db: DB<_,Tree2<f32,f32,V>,P,V> = Setup::from_path(dir.path())
+    .stream_batch_size(500)
+    .stream_batch_bytes(2_000)
+    .debug(Events { events: Arc::clone(&events) })
+    .build()
+    .await?;
+  db.batch(&inserts(&points, 0..1000)).await?;
+  events.lock().unwrap().clear();
+  // inserts, then deletes of rows from the batch and the stream, then more inserts
+  let rows: Vec<Row<P,V>> = inserts(&points, 1000..3000).into_iter()
+    .chain(deletes(&points, 500..1500))
+    .chain(inserts(&points, 3000..4000))
+    .collect();
+  let report = db.batch_stream(async_std::stream::from_iter(rows)).await?;
+  assert_eq![report.inserted, 3000];
+  assert_eq![report.deleted, 1000];
+  let expected = (0..500).chain(1500..4000).collect::<HashSet<V>>();
+  assert_eq![ids(db.query(&bbox).await?).await?, expected, "rows apply in stream order"];
+  {
+    let mut db: DB<_,Tree2<f32,f32,V>,P,V> = Setup::from_path(dir.path()).build().await?;
+    assert_eq![ids(db.query(&bbox).await?).await?, expected, "streamed rows are synced"];
+  }
+  // wait for events, which are delivered on a separate task
+  async_std::task::sleep(std::time::Duration::from_millis(100)).await;
+  let events = events.lock().unwrap().clone();
+  assert![events.iter().any(|e| matches![e, DebugEvent::BulkWriteRun { .. }]),
+    "inserts are written to sorted runs"];
+  let merged = events.iter().filter_map(|e| match e {
+    DebugEvent::Merge { rows, .. } => Some(*rows),
+    _ => None,
+  }).sum::<usize>();
+  assert![merged < 3000, "inserts aren't merged into the existing forest"];
+  let runs = std::fs::read_dir(dir.path().join("bulk")).map(|d| d.count()).unwrap_or(0);
+  assert_eq![runs, 0, "run files are removed"];
+  Ok(())
+}
+
+struct Events {
+  events: Arc<Mutex<Vec<DebugEvent>>>,
+}
+
+impl Debugger for Events {
+  fn event(&mut self, event: &DebugEvent) {
+    self.events.lock().unwrap().push(event.clone());
+  }
+}
@@ -0,0 +1,35 @@
+use eyros::{DB,Tree2,Setup,Error};
+use tempfile::Builder as Tmpfile;
+
+mod common;
+use common::{P,V,points,inserts,ids,expected};
+
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn build_threads() -> Result<(),Error> {
+  let points = points(120_000);
+  let bboxes = [
+    ((-1.0,-1.0),(1.0,1.0)),
+    ((-0.5,0.1),(-0.3,0.4)),
+    ((0.2,-0.9),(0.7,-0.1)),
+  ];
+  let mut trees = vec![];
+  for threads in [1,4] {
+    let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+      .build_threads(threads)
+      .build()
+      .await?;
+    db.batch(&inserts(&points, 0..100_000)).await?;
+    db.batch(&inserts(&points, 100_000..120_000)).await?;
+    db.sync().await?;
+    for bbox in bboxes.iter() {
+      assert_eq![ids(db.query(bbox).await?).await?, expected(&points, bbox),
+        "query with build_threads={} bbox={:?}", threads, bbox];
+    }
+    trees.push(db.info().await?.trees);
+  }
+  assert_eq![trees[0], trees[1], "threads build the same trees"];
+  Ok(())
+}
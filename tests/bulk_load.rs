@@ -0,0 +1,68 @@
+use eyros::{DB,Tree2,Coord,Point,Setup,Error};
+use tempfile::Builder as Tmpfile;
+
+mod common;
+use common::{P,V,points,inserts,ids,expected};
+
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn bulk_load() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let points = points(7000);
+  let bboxes = [
+    ((-1.0,-1.0),(1.0,1.0)),
+    ((-0.5,0.1),(-0.3,0.4)),
+    ((0.2,-0.9),(0.7,-0.1)),
+  ];
+  let rows = |n: usize| {
+    async_std::stream::from_iter(points[..n].iter().cloned().zip(0..n as V).collect::<Vec<_>>())
+  };
+  {
+    let setup = Setup::from_path(dir.path()).stream_batch_size(1000);
+    let mut db: DB<_,T,P,V> = DB::bulk_load(setup, rows(6500)).await?;
+    for bbox in bboxes.iter() {
+      assert_eq![ids(db.query(bbox).await?).await?, expected(&points[..6500], bbox),
+        "bulk loaded bbox={:?}", bbox];
+    }
+  }
+  let runs = std::fs::read_dir(dir.path().join("bulk")).map(|d| d.count()).unwrap_or(0);
+  assert_eq![runs, 0, "run files are removed"];
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+    db.batch(&inserts(&points, 6500..7000)).await?;
+    db.sync().await?;
+    for bbox in bboxes.iter() {
+      assert_eq![ids(db.query(bbox).await?).await?, expected(&points, bbox),
+        "batch after bulk load"];
+    }
+  }
+  {
+    let setup = Setup::from_path(dir.path());
+    let res: Result<DB<_,T,P,V>,Error> = DB::bulk_load(setup, rows(10)).await;
+    assert![res.is_err(), "bulk load into an existing database fails"];
+  }
+  Ok(())
+}
+
+#[async_std::test]
+async fn bulk_load_total_order() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  // NaN coordinates are sorted by their total order into the runs
+  let nan: P = (Coord::Scalar(f32::NAN),Coord::Scalar(0.0));
+  assert_eq![nan.cmp_dim(&(Coord::Scalar(0.0),Coord::Scalar(0.0)), 0),
+    Some(std::cmp::Ordering::Greater)];
+  let rows = points(3000).into_iter().enumerate().map(|(i,(x,y))| match i % 3 {
+    0 => ((Coord::Scalar(f32::NAN),y), i as V),
+    _ => ((x,y), i as V),
+  }).collect::<Vec<_>>();
+  let setup = Setup::from_path(dir.path()).stream_batch_size(500).total_order(true);
+  let mut db: DB<_,T,P,V> = DB::bulk_load(setup, async_std::stream::from_iter(rows)).await?;
+  let bbox = ((-1.0,-1.0),(1.0,1.0));
+  assert_eq![ids(db.query(&bbox).await?).await?, (0..3000).filter(|i| i % 3 != 0).collect(),
+    "finite queries skip NaN rows"];
+  let info = db.info().await?;
+  assert_eq![info.inline_records + info.external_records, 3000];
+  assert![db.verify().await?.is_ok()];
+  Ok(())
+}
@@ -0,0 +1,119 @@
+use std::process::{Command,Stdio};
+use std::io::Write;
+use tempfile::Builder as Tmpfile;
+
+type Error = Box<dyn std::error::Error+Send+Sync>;
+
+fn eyros(args: &[&str], input: Option<&str>) -> Result<(bool,String),Error> {
+  let mut child = Command::new(env!["CARGO_BIN_EXE_eyros"])
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+  if let Some(input) = input {
+    child.stdin.take().unwrap().write_all(input.as_bytes())?;
+  }
+  let output = child.wait_with_output()?;
+  Ok((output.status.success(),String::from_utf8(output.stdout)?))
+}
+
+fn sorted(s: &str) -> Vec<String> {
+  let mut lines = s.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+  lines.sort();
+  lines
+}
+
+#[test]
+fn cli() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let db = dir.path().join("db");
+  let db = db.to_str().unwrap();
+  let csv = (0..1000).map(|i| {
+    let x = (i as f64)/10.0 - 50.0;
+    if i % 4 == 0 {
+      format!["{}..{},{},{},{}\n", x, x+1.0, -x, i % 7, i]
+    } else {
+      format!["{},{},{},{}\n", x, -x, i % 7, i]
+    }
+  }).collect::<String>();
+  let (ok,_) = eyros(&["-d","3","-t","f64",db,"import"], Some(&csv))?;
+  assert![ok];
+  let (ok,out) = eyros(&["-d","3","-t","f64",db,"export"], None)?;
+  assert![ok];
+  assert_eq![sorted(&out), sorted(&csv)];
+
+  let (ok,out) = eyros(&["--dims","3","--type","f64",db,"query","-10,-10,0,10,10,3"], None)?;
+  assert![ok];
+  let expected = csv.lines().filter(|line| {
+    let cols = line.split(',').collect::<Vec<_>>();
+    let (xmin,xmax) = match cols[0].split_once("..") {
+      Some((a,b)) => (a.parse::<f64>().unwrap(),b.parse::<f64>().unwrap()),
+      None => (cols[0].parse().unwrap(),cols[0].parse().unwrap()),
+    };
+    let y: f64 = cols[1].parse().unwrap();
+    let z: f64 = cols[2].parse().unwrap();
+    xmin <= 10.0 && xmax >= -10.0 && (-10.0..=10.0).contains(&y) && (0.0..=3.0).contains(&z)
+  }).map(|line| format!["{}\n", line]).collect::<String>();
+  assert_eq![sorted(&out), sorted(&expected)];
+
+  let ndjson = "{\"point\":[[null,5],0.5,1],\"value\":5000}\n\
+    {\"point\":[1.5, [2, null], -3], \"value\": 5001}\n";
+  let (ok,_) = eyros(&["-d","3","-t","f64","-f","ndjson",db,"import","-"], Some(ndjson))?;
+  assert![ok];
+  let (ok,out) = eyros(&["-d","3","-t","f64","-f","ndjson",db,"query","-1,0,-5,2,5,5"], None)?;
+  assert![ok];
+  assert![out.contains("{\"point\":[[null,5],0.5,1],\"value\":5000}\n")];
+  assert![out.contains("{\"point\":[1.5,[2,null],-3],\"value\":5001}\n")];
+
+  let (ok,out) = eyros(&["-d","3","-t","f64",db,"verify"], None)?;
+  assert![ok];
+  assert![out.ends_with("records: 0 problems\n")];
+  let (ok,out) = eyros(&["-d","3","-t","f64",db,"info"], None)?;
+  assert![ok];
+  assert![out.contains("records: 1002\n")];
+
+  let (ok,_) = eyros(&["-d","3","-t","f64",db,"import"], Some("1,2,3\n"))?;
+  assert![!ok, "rows with too few coordinates are rejected"];
+  let (ok,_) = eyros(&[db,"unknown"], None)?;
+  assert![!ok];
+  Ok(())
+}
+
+#[test]
+fn cli_checks_database() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let missing = dir.path().join("missing");
+  let missing = missing.to_str().unwrap();
+  for args in [vec![missing,"info"], vec![missing,"export"], vec![missing,"verify"]] {
+    let (ok,_) = eyros(&args, None)?;
+    assert![!ok, "{} fails without a database", args[1]];
+  }
+  assert![!dir.path().join("missing").exists(), "only import creates a database"];
+
+  let db = dir.path().join("db");
+  let db = db.to_str().unwrap();
+  let (ok,_) = eyros(&["-d","3","-t","f64",db,"import"], Some("1,2,3,4\n5,6,7,8\n"))?;
+  assert![ok];
+  for args in [vec![db,"info"], vec!["-d","3",db,"info"], vec!["-t","f64",db,"info"],
+  vec!["-d","3","-t","f32",db,"export"], vec!["-d","3","-t","f64","-n",db,"export"]] {
+    let (ok,_) = eyros(&args, None)?;
+    assert![!ok, "{:?} doesn't match the stored point type", args];
+  }
+  let (ok,out) = eyros(&["-d","3","-t","f64",db,"export"], None)?;
+  assert![ok];
+  assert_eq![sorted(&out), vec!["1,2,3,4","5,6,7,8"]];
+
+  let nd = dir.path().join("nd");
+  let nd = nd.to_str().unwrap();
+  let (ok,_) = eyros(&["-n",nd,"import"], Some("1,2,3\n"))?;
+  assert![ok];
+  let (ok,_) = eyros(&["-n","-d","3",nd,"query","0,0,0,5,5,5"], None)?;
+  assert![!ok, "a TreeN is checked against --dims"];
+  let (ok,_) = eyros(&[nd,"info"], None)?;
+  assert![!ok, "a TreeN isn't opened as a Tree2"];
+  let (ok,out) = eyros(&["-n",nd,"export"], None)?;
+  assert![ok];
+  assert_eq![out, "1,2,3\n"];
+  Ok(())
+}
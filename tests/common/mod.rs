@@ -0,0 +1,61 @@
+// helpers shared by the tests of batches, transactions and bulk loading.
+// each test binary uses a different subset of these.
+#![allow(dead_code)]
+use eyros::{Coord,Scalar,Row,Error};
+use random::{Source,default as rand};
+use async_std::{prelude::*,stream::Stream};
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+pub type P = (Coord<f32>,Coord<f32>);
+pub type V = u32;
+
+// n points in -1..1, each a short interval in x and a scalar in y
+pub fn points(n: usize) -> Vec<P> {
+  let mut r = rand().seed([13,12]);
+  (0..n).map(|_| {
+    let xmin: f32 = r.read::<f32>()*2.0-1.0;
+    let xmax: f32 = xmin + r.read::<f32>().powf(64.0)*(1.0-xmin);
+    let y: f32 = r.read::<f32>()*2.0-1.0;
+    (Coord::Interval(xmin,xmax),Coord::Scalar(y))
+  }).collect()
+}
+
+// inserts of the points in range, with the index of each point as its value
+pub fn inserts(points: &[P], range: Range<usize>) -> Vec<Row<P,V>> {
+  range.map(|i| Row::Insert(points[i].clone(), i as V)).collect()
+}
+
+pub fn deletes(points: &[P], range: Range<usize>) -> Vec<Row<P,V>> {
+  range.map(|i| Row::Delete(points[i].clone(), i as V)).collect()
+}
+
+// values from a query stream, which must not repeat
+pub async fn ids<S,Q>(mut stream: S) -> Result<HashSet<V>,Error>
+where S: Stream<Item=Result<(Q,V),Error>>+Unpin {
+  let mut results = HashSet::new();
+  while let Some(result) = stream.next().await {
+    let v = result?.1;
+    assert![results.insert(v), "duplicate result {}", v];
+  }
+  Ok(results)
+}
+
+// values of the points that intersect bbox
+pub fn expected(points: &[P], bbox: &((f32,f32),(f32,f32))) -> HashSet<V> {
+  points.iter().enumerate()
+    .filter(|(_,p)| {
+      contains(&(bbox.0).0, &(bbox.1).0, &p.0) && contains(&(bbox.0).1, &(bbox.1).1, &p.1)
+    })
+    .map(|(i,_)| i as V)
+    .collect()
+}
+
+pub fn contains<T>(min: &T, max: &T, c: &Coord<T>) -> bool where T: Scalar {
+  match c {
+    Coord::Interval(x0,x1) => min <= x1 && x0 <= max,
+    Coord::Scalar(x) => min <= x && x <= max,
+    _ => panic!["unexpected coordinate type"],
+  }
+}
@@ -0,0 +1,154 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,FileStore,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+async fn rows(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<Vec<(P,V)>,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut results = vec![];
+  while let Some(result) = stream.next().await {
+    results.push(result?);
+  }
+  results.sort_unstable_by_key(|(_,v)| *v);
+  Ok(results)
+}
+
+fn inserts(n: usize, offset: u32) -> Vec<Row<P,V>> {
+  let mut r = rand().seed([13,12+offset as u64]);
+  (0..n as u32).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), offset+i)
+  }).collect()
+}
+
+#[async_std::test]
+async fn copy_to() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(&dir.path().join("a"))
+    .max_records(500)
+    .build()
+    .await?;
+  for batch in inserts(8_000, 0).chunks(2_000) {
+    db.batch(batch).await?;
+    db.sync().await?;
+  }
+  // unsynced changes are part of the copy
+  let mut deletes = vec![];
+  for (p,v) in rows(&mut db).await?.iter().step_by(7) {
+    deletes.push(Row::Delete(p.clone(),*v));
+  }
+  db.batch(&deletes).await?;
+  db.batch(&inserts(1_000, 8_000)).await?;
+
+  let report = db.copy_to(Box::new(FileStore::new(&dir.path().join("b")))).await?;
+  assert![report.trees > 1, "expected more than one tree, copied {}", report.trees];
+  assert![report.bytes > 0];
+
+  let mut copy: DB<_,T,P,V> = Setup::from_path(&dir.path().join("b")).build().await?;
+  let expected = rows(&mut db).await?;
+  assert_eq![expected.len(), 9_000 - deletes.len()];
+  assert_eq![rows(&mut copy).await?, expected];
+  assert![copy.verify().await?.is_ok()];
+
+  // a second copy over the first replaces its meta
+  db.batch(&inserts(500, 9_000)).await?;
+  db.copy_to(Box::new(FileStore::new(&dir.path().join("b")))).await?;
+  let mut copy: DB<_,T,P,V> = Setup::from_path(&dir.path().join("b")).build().await?;
+  assert_eq![rows(&mut copy).await?, rows(&mut db).await?];
+  Ok(())
+}
+
+#[async_std::test]
+async fn copy_during_batches() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(&dir.path().join("a"))
+    .max_records(500)
+    .build()
+    .await?;
+  db.batch(&inserts(5_000, 0)).await?;
+  db.sync().await?;
+  let mut writer = db.clone();
+  let ingest = async_std::task::spawn(async move {
+    for i in 1..10 {
+      writer.batch(&inserts(1_000, i*5_000)).await?;
+      writer.sync().await?;
+    }
+    let r: Result<(),Error> = Ok(());
+    r
+  });
+  db.copy_to(Box::new(FileStore::new(&dir.path().join("b")))).await?;
+  ingest.await?;
+
+  // the copy holds one of the versions written along the way
+  let mut copy: DB<_,T,P,V> = Setup::from_path(&dir.path().join("b")).build().await?;
+  let copied = rows(&mut copy).await?;
+  assert![copied.len() >= 5_000 && (copied.len() - 5_000) % 1_000 == 0,
+    "unexpected number of copied rows: {}", copied.len()];
+  let all = rows(&mut db).await?;
+  assert_eq![all.len(), 14_000];
+  for row in copied.iter() {
+    assert![all.contains(row)];
+  }
+  assert![copy.verify().await?.is_ok()];
+  Ok(())
+}
+
+#[async_std::test]
+async fn copy_over_other_database() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut a: DB<_,T,P,V> = Setup::from_path(&dir.path().join("a"))
+    .max_records(500)
+    .build()
+    .await?;
+  a.batch(&inserts(5_000, 0)).await?;
+  a.sync().await?;
+  let mut c: DB<_,T,P,V> = Setup::from_path(&dir.path().join("c"))
+    .max_records(500)
+    .build()
+    .await?;
+  c.batch(&inserts(6_000, 10_000)).await?;
+  c.sync().await?;
+
+  a.copy_to(Box::new(FileStore::new(&dir.path().join("b")))).await?;
+  let before = files(&dir.path().join("b"))?;
+  // the trees of c use the same ids as the trees of a with different contents
+  let first = c.copy_to(Box::new(FileStore::new(&dir.path().join("b")))).await?;
+  assert![first.trees > 1];
+  let after = files(&dir.path().join("b"))?;
+  for (file,bytes) in before.iter() {
+    if file == "meta" || file == "meta.1" { continue }
+    assert_eq![after.get(file), Some(bytes), "tree file {} of the earlier copy changed", file];
+  }
+  let mut copy: DB<_,T,P,V> = Setup::from_path(&dir.path().join("b")).build().await?;
+  assert_eq![rows(&mut copy).await?, rows(&mut c).await?];
+  assert![copy.verify().await?.is_ok()];
+
+  // copying the same version again only writes the trees that had to move to new ids
+  let report = c.copy_to(Box::new(FileStore::new(&dir.path().join("b")))).await?;
+  assert![report.trees < first.trees, "rewrote {} of {} trees", report.trees, first.trees];
+  let mut copy: DB<_,T,P,V> = Setup::from_path(&dir.path().join("b")).build().await?;
+  assert_eq![rows(&mut copy).await?, rows(&mut c).await?];
+  Ok(())
+}
+
+fn files(dir: &std::path::Path) -> Result<std::collections::HashMap<String,Vec<u8>>,Error> {
+  let mut files = std::collections::HashMap::new();
+  let mut dirs = vec![dir.to_path_buf()];
+  while let Some(d) = dirs.pop() {
+    for entry in std::fs::read_dir(&d)? {
+      let path = entry?.path();
+      if path.is_dir() {
+        dirs.push(path);
+      } else {
+        let name = path.strip_prefix(dir)?.to_string_lossy().to_string();
+        files.insert(name, std::fs::read(&path)?);
+      }
+    }
+  }
+  Ok(files)
+}
@@ -0,0 +1,47 @@
+use eyros::{Point,Overlap,Error};
+
+// a point type from outside of eyros that only implements the required items
+#[derive(Debug,Clone,PartialEq)]
+struct Rect {
+  min: (f32,f32),
+  max: (f32,f32),
+}
+
+impl Overlap for Rect {
+  fn overlap(&self, other: &Self) -> bool {
+    self.min.0 <= other.max.0 && other.min.0 <= self.max.0
+      && self.min.1 <= other.max.1 && other.min.1 <= self.max.1
+  }
+}
+
+impl Point for Rect {
+  type Bounds = ((f32,f32),(f32,f32));
+  fn to_bounds(&self) -> Result<Self::Bounds,Error> {
+    Ok((self.min,self.max))
+  }
+  fn from_bounds(bounds: &Self::Bounds) -> Self {
+    Rect { min: bounds.0, max: bounds.1 }
+  }
+  fn check(&self) -> Result<(),Error> {
+    Ok(())
+  }
+}
+
+#[test]
+fn custom_point_defaults() -> Result<(),Error> {
+  let a = Rect { min: (1.0,2.0), max: (3.0,4.0) };
+  let b = Rect { min: (2.5,-1.0), max: (5.0,0.5) };
+  assert_eq![Rect::DIMENSIONS, None];
+  assert_eq![a.dimensions(), 0];
+  assert_eq![a.cmp_dim(&b, 0), None];
+  assert_eq![a.union(&b), None];
+  assert![!a.overlap(&b)];
+  // points are written as their bounds by default
+  let mut buf = vec![0u8;a.count_point_bytes()];
+  assert_eq![buf.len(), 16];
+  assert_eq![a.write_point_bytes(&mut buf)?, 16];
+  let (size,c) = Rect::point_from_bytes(&buf)?;
+  assert_eq![size, 16];
+  assert_eq![c, a];
+  Ok(())
+}
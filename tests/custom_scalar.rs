@@ -0,0 +1,104 @@
+use eyros::{Coord,Scalar,Row,Error};
+use desert::{ToBytes,FromBytes,CountBytes};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+use std::collections::HashSet;
+
+#[derive(Debug,Clone,Copy,PartialEq,PartialOrd)]
+struct Timestamp(i64);
+
+impl Scalar for Timestamp {
+  fn midpoint(a: &Self, b: &Self) -> Self {
+    Timestamp(<i64 as Scalar>::midpoint(&a.0, &b.0))
+  }
+}
+
+impl ToBytes for Timestamp {
+  fn to_bytes(&self) -> Result<Vec<u8>,Error> {
+    self.0.to_bytes()
+  }
+  fn write_bytes(&self, buf: &mut [u8]) -> Result<usize,Error> {
+    self.0.write_bytes(buf)
+  }
+}
+
+impl CountBytes for Timestamp {
+  fn count_bytes(&self) -> usize {
+    self.0.count_bytes()
+  }
+  fn count_from_bytes(buf: &[u8]) -> Result<usize,Error> {
+    i64::count_from_bytes(buf)
+  }
+}
+
+impl FromBytes for Timestamp {
+  fn from_bytes(src: &[u8]) -> Result<(usize,Self),Error> {
+    let (size,x) = i64::from_bytes(src)?;
+    Ok((size,Timestamp(x)))
+  }
+}
+
+type P = (Coord<Timestamp>,Coord<i128>,Coord<i8>);
+type V = u32;
+
+#[async_std::test]
+async fn custom_scalar() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let now = 1_600_000_000_000i64;
+  let rows: Vec<Row<P,V>> = (0..10_000).map(|i| {
+    let t = now + (r.read::<u64>() % 1_000_000_000) as i64;
+    let t = if i % 5 == 0 {
+      Coord::Interval(Timestamp(t), Timestamp(t + (r.read::<u64>() % 1_000_000) as i64))
+    } else if i % 7 == 0 {
+      Coord::From(Timestamp(t))
+    } else {
+      Coord::Scalar(Timestamp(t))
+    };
+    let big = (r.read::<u64>() as i128) << 60;
+    Row::Insert((t, Coord::Scalar(big), Coord::Scalar(r.read::<u8>() as i8)), i as u32)
+  }).collect();
+  let mut db = eyros::open_from_path3(dir.path()).await?;
+  db.batch(&rows[0..5_000]).await?;
+  db.batch(&rows[5_000..]).await?;
+  let bboxes = [
+    ((Timestamp(now),i128::MIN,i8::MIN),(Timestamp(now + 1_000_000_000),i128::MAX,i8::MAX)),
+    ((Timestamp(now + 200_000_000),0,-10),(Timestamp(now + 300_000_000),1<<120,20)),
+    ((Timestamp(now + 990_000_000),-(1<<100),0),(Timestamp(now + 2_000_000_000),1<<100,i8::MAX)),
+  ];
+  for bbox in bboxes.iter() {
+    let mut results = HashSet::new();
+    let mut stream = db.query(bbox).await?;
+    while let Some(result) = stream.next().await {
+      assert![results.insert(result?.1), "duplicate result"];
+    }
+    let expected: HashSet<V> = rows.iter().filter_map(|row| match row {
+      Row::Insert(p,v) => {
+        let hit = contains(&(bbox.0).0, &(bbox.1).0, &p.0)
+          && contains(&(bbox.0).1, &(bbox.1).1, &p.1)
+          && contains(&(bbox.0).2, &(bbox.1).2, &p.2);
+        if hit { Some(*v) } else { None }
+      },
+      _ => None,
+    }).collect();
+    assert_eq![results, expected, "bbox={:?}", bbox];
+  }
+  Ok(())
+}
+
+#[test]
+fn midpoint() {
+  assert_eq![<u8 as Scalar>::midpoint(&255, &255), 255];
+  assert_eq![<u8 as Scalar>::midpoint(&3, &3), 3];
+  assert_eq![<i8 as Scalar>::midpoint(&-3, &-3), -3];
+  assert_eq![<i64 as Scalar>::midpoint(&i64::MIN, &i64::MAX), -1];
+  assert_eq![<u128 as Scalar>::midpoint(&u128::MAX, &(u128::MAX-2)), u128::MAX-1];
+  assert_eq![<i128 as Scalar>::midpoint(&-7, &1), -3];
+  assert_eq![<f64 as Scalar>::midpoint(&f64::MAX, &f64::MAX), f64::MAX];
+}
+
+fn contains<T> (min: &T, max: &T, c: &Coord<T>) -> bool where T: Scalar {
+  c.min().map(|x| x <= max).unwrap_or(true) && c.max().map(|x| min <= x).unwrap_or(true)
+}
@@ -0,0 +1,74 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,Debugger,DebugEvent,Error};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+use std::sync::{Arc,Mutex};
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+struct Events {
+  events: Arc<Mutex<Vec<DebugEvent>>>,
+}
+
+impl Debugger for Events {
+  fn event(&mut self, event: &DebugEvent) {
+    self.events.lock().unwrap().push(event.clone());
+  }
+}
+
+#[async_std::test]
+async fn debug_events() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let events = Arc::new(Mutex::new(vec![]));
+  let messages = Arc::new(Mutex::new(vec![]));
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+      .debug(Events { events: Arc::clone(&events) })
+      .build()
+      .await?;
+    let rows: Vec<Row<P,V>> = (0..100).map(|i| {
+      let x = (i as f32)/100.0;
+      Row::Insert((Coord::Scalar(x),Coord::Scalar(x)), i)
+    }).collect();
+    db.batch(&rows).await?;
+    db.sync().await?;
+    let mut stream = db.query(&((0.0,0.0),(1.0,1.0))).await?;
+    while let Some(result) = stream.next().await { result?; }
+  }
+  {
+    let msgs = Arc::clone(&messages);
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+      .debug(move |msg: &str| msgs.lock().unwrap().push(msg.to_string()))
+      .build()
+      .await?;
+    let mut stream = db.query(&((0.0,0.0),(1.0,1.0))).await?;
+    while let Some(result) = stream.next().await { result?; }
+  }
+  // events are delivered on a separate task
+  for _ in 0..100 {
+    let done = events.lock().unwrap().iter().any(|e| matches![e, DebugEvent::QueryEnd { .. }])
+      && messages.lock().unwrap().iter().any(|m| m.starts_with("query end"));
+    if done { break }
+    async_std::task::sleep(std::time::Duration::from_millis(10)).await;
+  }
+  let events = events.lock().unwrap().clone();
+  assert_eq![events.first(), Some(&DebugEvent::Open)];
+  assert![events.contains(&DebugEvent::OpenNew)];
+  assert![events.iter().any(|e| matches![e, DebugEvent::Merge { inputs: 0, rows: 100 }])];
+  assert![events.iter().any(|e| matches![e, DebugEvent::Put { .. }])];
+  assert![events.contains(&DebugEvent::SyncBegin)];
+  assert![events.iter().any(|e| matches![e, DebugEvent::SyncTree { .. }])];
+  assert![events.contains(&DebugEvent::SyncComplete)];
+  assert![events.iter().any(|e| matches![e, DebugEvent::QueryStart { .. }])];
+  assert![events.iter().any(|e| matches![e, DebugEvent::QueryRoot { index: 0, .. }])];
+  assert![events.iter().any(|e| matches![e, DebugEvent::CacheHit { .. }])];
+  assert![events.iter().any(|e| matches![e, DebugEvent::QueryEnd { index: 0, .. }])];
+
+  let messages = messages.lock().unwrap().clone();
+  assert_eq![messages.first().map(|m| m.as_str()), Some("opening db")];
+  assert![messages.iter().any(|m| m.starts_with("existing db found"))];
+  assert![messages.iter().any(|m| m.starts_with("get tree id=0 file=") && m.ends_with(": not cached"))];
+  assert![messages.iter().any(|m| m.starts_with("read ") && m.ends_with(" bytes from tree id=0"))];
+  Ok(())
+}
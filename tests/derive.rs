@@ -0,0 +1,111 @@
+use eyros::{Coord,Row,Setup,DB,TreeOf,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+use std::collections::HashMap;
+
+#[derive(Debug,Clone,PartialEq,eyros::Point)]
+struct Event {
+  time: Coord<i64>,
+  lon: Coord<f32>,
+  lat: Coord<f32>,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq,Hash,eyros::Value)]
+struct Feature {
+  #[id] id: u64,
+  kind: u8,
+  tags: Vec<u16>,
+}
+
+#[derive(Debug,Clone,PartialEq,eyros::Point)]
+struct XY(Coord<f64>,Coord<f64>);
+
+#[async_std::test]
+async fn derive_point_value() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<Event,Feature>> = (0..5_000).map(|i| {
+    let t = (r.read::<u32>() % 10_000) as i64;
+    let lon = r.read::<f32>()*360.0-180.0;
+    let lat = r.read::<f32>()*180.0-90.0;
+    Row::Insert(Event {
+      time: if i % 4 == 0 { Coord::Interval(t,t+50) } else { Coord::Scalar(t) },
+      lon: Coord::Scalar(lon),
+      lat: Coord::Interval(lat,lat+r.read::<f32>()),
+    }, Feature { id: i, kind: (i % 7) as u8, tags: vec![i as u16; (i % 3) as usize] })
+  }).collect();
+  let deletes: Vec<Row<Event,Feature>> = rows.iter().step_by(10).map(|row| match row {
+    Row::Insert(p,v) => Row::Delete(p.clone(),v.id),
+    _ => panic!["unexpected row type"],
+  }).collect();
+  let inserted: HashMap<u64,(Event,Feature)> = rows.iter().enumerate()
+    .filter(|(i,_)| i % 10 != 0)
+    .map(|(_,row)| match row {
+      Row::Insert(p,v) => (v.id,(p.clone(),v.clone())),
+      _ => panic!["unexpected row type"],
+    })
+    .collect();
+  let bbox = ((2_000,-90.0,-45.0),(6_000,90.0,45.0));
+  let expected: HashMap<u64,(Event,Feature)> = inserted.iter()
+    .filter(|(_,(p,_))| {
+      overlaps(&p.time, &(bbox.0).0, &(bbox.1).0)
+        && overlaps(&p.lon, &(bbox.0).1, &(bbox.1).1)
+        && overlaps(&p.lat, &(bbox.0).2, &(bbox.1).2)
+    })
+    .map(|(id,pv)| (*id,pv.clone()))
+    .collect();
+  {
+    let mut db = eyros::open_from_path_of(dir.path()).await?;
+    db.batch(&rows[0..2_500]).await?;
+    db.batch(&rows[2_500..]).await?;
+    db.batch(&deletes).await?;
+    db.sync().await?;
+    assert_eq![query(&mut db, &bbox).await?, expected];
+  }
+  {
+    let mut db = eyros::open_from_path_of(dir.path()).await?;
+    assert_eq![query(&mut db, &bbox).await?, expected, "after reopen"];
+  }
+  Ok(())
+}
+
+#[async_std::test]
+async fn derive_tuple_struct() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,TreeOf<XY,u32>,XY,u32> = Setup::from_path(dir.path()).build().await?;
+  db.batch(&[
+    Row::Insert(XY(Coord::Scalar(1.0),Coord::Scalar(2.0)), 100),
+    Row::Insert(XY(Coord::Interval(-5.0,0.5),Coord::From(3.0)), 101),
+    Row::Insert(XY(Coord::Scalar(8.0),Coord::Scalar(9.0)), 102),
+  ]).await?;
+  let mut stream = db.query(&((0.0,0.0),(2.0,5.0))).await?;
+  let mut results = vec![];
+  while let Some(result) = stream.next().await {
+    results.push(result?);
+  }
+  results.sort_by_key(|(_,v)| *v);
+  assert_eq![results, vec![
+    (XY(Coord::Scalar(1.0),Coord::Scalar(2.0)), 100),
+    (XY(Coord::Interval(-5.0,0.5),Coord::From(3.0)), 101),
+  ]];
+  Ok(())
+}
+
+type Bounds = ((i64,f32,f32),(i64,f32,f32));
+
+async fn query(db: &mut DB<impl eyros::RA,TreeOf<Event,Feature>,Event,Feature>, bbox: &Bounds)
+-> Result<HashMap<u64,(Event,Feature)>,Error> {
+  let mut results = HashMap::new();
+  let mut stream = db.query(bbox).await?;
+  while let Some(result) = stream.next().await {
+    let (p,v) = result?;
+    assert![results.insert(v.id,(p,v)).is_none(), "duplicate result"];
+  }
+  Ok(results)
+}
+
+fn overlaps<T>(c: &Coord<T>, min: &T, max: &T) -> bool where T: eyros::Scalar {
+  c.min().map(|x| x <= max).unwrap_or(true) && c.max().map(|x| min <= x).unwrap_or(true)
+}
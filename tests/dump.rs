@@ -0,0 +1,132 @@
+use eyros::{Setup,DB,Tree2,TreeN,Row,Coord,DumpNode,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+fn count_rows(node: &DumpNode) -> usize {
+  match node {
+    DumpNode::Branch { intersections, nodes, .. } => {
+      intersections.iter().map(|(_,n)| count_rows(n)).sum::<usize>()
+        + nodes.iter().map(count_rows).sum::<usize>()
+    },
+    DumpNode::Data { rows, .. } => *rows,
+  }
+}
+
+fn check_branches(node: &DumpNode, dims: usize, depth: usize) {
+  if let DumpNode::Branch { level, dimension, pivots, intersections, nodes } = node {
+    assert_eq![*level, depth];
+    assert_eq![*dimension, depth % dims];
+    assert_eq![nodes.len(), pivots.len() + 1];
+    for (bitfield,n) in intersections.iter() {
+      assert![*bitfield > 0];
+      check_branches(n, dims, depth + 1);
+    }
+    for n in nodes.iter() {
+      check_branches(n, dims, depth + 1);
+    }
+  }
+}
+
+#[async_std::test]
+async fn dump() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .max_records(500)
+    .build()
+    .await?;
+  let empty = db.dump().await?;
+  assert_eq![empty.trees.len(), 0];
+  assert_eq![empty.to_json(), r#"{"roots":[],"trees":[]}"#];
+
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..20_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i)
+  }).collect();
+  for batch in rows.chunks(5_000) {
+    db.batch(batch).await?;
+  }
+  db.sync().await?;
+
+  let info = db.info().await?;
+  let dump = db.dump().await?;
+  assert_eq![dump.roots.len(), info.roots.len()];
+  assert_eq![dump.trees.len(), info.trees];
+  assert_eq![dump.trees.iter().map(|t| count_rows(&t.root)).sum::<usize>(), 20_000];
+  for t in dump.trees.iter() {
+    check_branches(&t.root, 2, 0);
+  }
+  let refs = dump.trees.iter().flat_map(|t| {
+    let mut refs = vec![];
+    let mut cursors = vec![&t.root];
+    while let Some(c) = cursors.pop() {
+      match c {
+        DumpNode::Branch { intersections, nodes, .. } => {
+          cursors.extend(intersections.iter().map(|(_,n)| n));
+          cursors.extend(nodes.iter());
+        },
+        DumpNode::Data { refs: rs, .. } => refs.extend(rs.iter().map(|r| r.id)),
+      }
+    }
+    refs
+  }).collect::<Vec<_>>();
+  assert![!refs.is_empty(), "max_records pushes rows into external trees"];
+  for id in refs.iter() {
+    assert![dump.trees.iter().any(|t| t.id == *id)];
+  }
+
+  let (index,id) = dump.roots[0];
+  let single = db.dump_tree(id).await?;
+  assert_eq![single.roots, vec![(index,id)]];
+  assert_eq![single.trees.len(), 1];
+  assert_eq![single.trees[0], *dump.trees.iter().find(|t| t.id == id).unwrap()];
+
+  let json = dump.to_json();
+  assert![json.starts_with(r#"{"roots":[{"index":"#)];
+  assert_eq![json.matches(r#""type":"data""#).count(),
+    dump.trees.iter().map(|t| count_data(&t.root)).sum::<usize>()];
+  let dot = dump.to_dot();
+  assert![dot.starts_with("digraph eyros {")];
+  assert![dot.trim_end().ends_with('}')];
+  assert_eq![dot.matches("subgraph cluster_t").count(), dump.trees.len()];
+  assert_eq![dot.matches("style=dashed").count(), refs.len()];
+  Ok(())
+}
+
+fn count_data(node: &DumpNode) -> usize {
+  match node {
+    DumpNode::Branch { intersections, nodes, .. } => {
+      intersections.iter().map(|(_,n)| count_data(n)).sum::<usize>()
+        + nodes.iter().map(count_data).sum::<usize>()
+    },
+    DumpNode::Data { .. } => 1,
+  }
+}
+
+#[async_std::test]
+async fn dump_n() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,TreeN<f32,V>,Vec<Coord<f32>>,V> = Setup::from_path(dir.path())
+    .max_records(500)
+    .build()
+    .await?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<Vec<Coord<f32>>,V>> = (0..20_000).map(|i| {
+    let p = (0..3).map(|_| Coord::Scalar(r.read::<f32>())).collect();
+    Row::Insert(p, i)
+  }).collect();
+  db.batch(&rows).await?;
+  db.sync().await?;
+  let dump = db.dump().await?;
+  assert_eq![dump.trees.iter().map(|t| count_rows(&t.root)).sum::<usize>(), 20_000];
+  for t in dump.trees.iter() {
+    check_branches(&t.root, 3, 0);
+  }
+  assert![dump.trees.iter().any(|t| matches![t.root, DumpNode::Branch { .. }])];
+  Ok(())
+}
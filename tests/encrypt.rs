@@ -0,0 +1,152 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,FileStore,EncryptedStorage,Storage,Error};
+use random::{Source,default as rand};
+use random_access_storage::RandomAccess;
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+const KEY: [u8;32] = [7;32];
+
+async fn rows(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<Vec<(P,V)>,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut results = vec![];
+  while let Some(result) = stream.next().await {
+    results.push(result?);
+  }
+  results.sort_unstable_by_key(|(_,v)| *v);
+  Ok(results)
+}
+
+#[async_std::test]
+async fn encrypted_db() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .encrypt(&KEY)
+    .max_records(500)
+    .build()
+    .await?;
+  let mut r = rand().seed([13,12]);
+  let inserts: Vec<Row<P,V>> = (0..5_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i)
+  }).collect();
+  for batch in inserts.chunks(1_000) {
+    db.batch(batch).await?;
+    db.sync().await?;
+  }
+  let expected = rows(&mut db).await?;
+  assert_eq![expected.len(), 5_000];
+
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).encrypt(&KEY).build().await?;
+  assert_eq![rows(&mut db).await?, expected];
+  assert![db.verify().await?.is_ok()];
+
+  // the meta file can't be read without the key
+  let plain: Result<DB<_,T,P,V>,Error> = Setup::from_path(dir.path()).build().await;
+  assert![plain.is_err(), "opened an encrypted database without the key"];
+  let wrong: Result<DB<_,T,P,V>,Error> = Setup::from_path(dir.path())
+    .encrypt(&[8;32]).build().await;
+  assert![wrong.is_err(), "opened an encrypted database with the wrong key"];
+
+  // copy into a plain database and back into an encrypted one
+  db.copy_to(Box::new(FileStore::new(&dir.path().join("plain")))).await?;
+  let mut plain: DB<_,T,P,V> = Setup::from_path(&dir.path().join("plain")).build().await?;
+  assert_eq![rows(&mut plain).await?, expected];
+  let store = EncryptedStorage::new(Box::new(FileStore::new(&dir.path().join("enc"))), &KEY);
+  plain.copy_to(Box::new(store)).await?;
+  let mut enc: DB<_,T,P,V> = Setup::from_path(&dir.path().join("enc"))
+    .encrypt(&KEY).build().await?;
+  assert_eq![rows(&mut enc).await?, expected];
+  Ok(())
+}
+
+#[async_std::test]
+async fn random_access() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut storage = EncryptedStorage::new(Box::new(FileStore::new(dir.path())), &KEY);
+  let mut file = storage.open("file").await?;
+  let mut expected: Vec<u8> = vec![];
+  let mut r = rand().seed([13,12]);
+  for _ in 0..200 {
+    let len = expected.len() as u64;
+    match r.read::<u32>() % 4 {
+      0 => {
+        let length = (r.read::<u32>() % 10_000) as u64;
+        file.truncate(length).await?;
+        expected.resize(length as usize, 0);
+      },
+      _ => {
+        let offset = (r.read::<u32>() as u64) % (len + 5_000);
+        let data = (0..r.read::<u32>() % 9_000).map(|_| r.read::<u8>()).collect::<Vec<u8>>();
+        file.write(offset, &data).await?;
+        let end = offset as usize + data.len();
+        if expected.len() < end {
+          expected.resize(end, 0);
+        }
+        expected[offset as usize..end].copy_from_slice(&data);
+      },
+    }
+    assert_eq![file.len().await?, expected.len() as u64];
+    if !expected.is_empty() {
+      let offset = (r.read::<u32>() as u64) % expected.len() as u64;
+      let length = (r.read::<u32>() as u64) % (expected.len() as u64 - offset + 1);
+      assert_eq![
+        file.read(offset, length).await?,
+        expected[offset as usize..(offset+length) as usize].to_vec()
+      ];
+    }
+  }
+  assert_eq![file.read(0, expected.len() as u64).await?, expected];
+  assert![file.read(0, expected.len() as u64 + 1).await.is_err()];
+
+  // blocks fail to decrypt under a different name
+  std::fs::copy(dir.path().join("file"), dir.path().join("other"))?;
+  let mut other = storage.open("other").await?;
+  if !expected.is_empty() {
+    assert![other.read(0, 1).await.is_err()];
+  }
+  Ok(())
+}
+
+#[async_std::test]
+async fn truncated_files_fail() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut storage = EncryptedStorage::new(Box::new(FileStore::new(dir.path())), &KEY);
+  let mut file = storage.open("file").await?;
+  let data = (0..3*4096).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+  file.write(0, &data[..4096]).await?;
+  file.write(4096, &data[4096..]).await?;
+  assert_eq![file.read(0, data.len() as u64).await?, data];
+  let sealed = std::fs::read(dir.path().join("file"))?;
+  let block = sealed.len() / 3;
+
+  // dropping whole blocks from the end leaves a last block that wasn't sealed as last
+  for n in 1..3 {
+    // the file keeps its name, which is part of what each block authenticates
+    let cut_dir = dir.path().join(format!["cut{}", n]);
+    std::fs::create_dir(&cut_dir)?;
+    std::fs::write(cut_dir.join("file"), &sealed[..n*block])?;
+    let mut cut = EncryptedStorage::new(Box::new(FileStore::new(&cut_dir)), &KEY)
+      .open("file").await?;
+    assert_eq![cut.len().await?, (n*4096) as u64];
+    assert![cut.read(0, (n*4096) as u64).await.is_err(), "file cut to {} blocks was read", n];
+    assert![cut.read(((n-1)*4096) as u64, 1).await.is_err()];
+    // blocks before the end still read
+    if n > 1 {
+      assert_eq![cut.read(0, 4096).await?, data[..4096].to_vec()];
+    }
+  }
+
+  // truncating through the adapter seals the new last block
+  file.truncate(2*4096).await?;
+  assert_eq![file.read(0, 2*4096).await?, data[..2*4096].to_vec()];
+  file.truncate(4096+100).await?;
+  assert_eq![file.read(0, 4096+100).await?, data[..4096+100].to_vec()];
+  file.write(4096+100, &data[4096+100..]).await?;
+  assert_eq![file.read(0, data.len() as u64).await?, data];
+  Ok(())
+}
@@ -0,0 +1,137 @@
+use eyros::{Setup,DB,Tree2,Tree3,TreeN,Row,Coord,ExportHeader,ScalarKind,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+type P3 = (Coord<f32>,Coord<f32>,Coord<f32>);
+
+async fn rows(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<Vec<(P,V)>,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut results = vec![];
+  while let Some(result) = stream.next().await {
+    results.push(result?);
+  }
+  results.sort_unstable_by_key(|(_,v)| *v);
+  Ok(results)
+}
+
+#[async_std::test]
+async fn export_import() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(&dir.path().join("a"))
+    .max_records(500)
+    .branch_factor(5)
+    .build()
+    .await?;
+  let mut r = rand().seed([13,12]);
+  let inserts: Vec<Row<P,V>> = (0..10_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    let p = match i % 3 {
+      0 => (Coord::Interval(x,(x+0.05).min(1.0)),Coord::Scalar(y)),
+      1 => (Coord::Scalar(x),Coord::To(y)),
+      _ => (Coord::Scalar(x),Coord::Scalar(y)),
+    };
+    Row::Insert(p, i)
+  }).collect();
+  for batch in inserts.chunks(2_500) {
+    db.batch(batch).await?;
+  }
+  db.sync().await?;
+
+  let mut buf = vec![];
+  assert_eq![db.export(&mut buf).await?, 10_000];
+
+  let header = ExportHeader::read(&mut buf.as_slice()).await?;
+  assert_eq![header.version, 2];
+  assert_eq![header.dimensions, 2];
+  assert_eq![header.scalars, vec![ScalarKind::F32,ScalarKind::F32]];
+  assert![header.fields.contains(&("branch_factor".to_string(),5))];
+  assert![header.fields.contains(&("max_records".to_string(),500))];
+
+  let mut copy: DB<_,T,P,V> = header.setup(Setup::from_path(&dir.path().join("b")))
+    .build()
+    .await?;
+  assert_eq![copy.fields.branch_factor, 5];
+  assert_eq![copy.fields.max_records, 500];
+  let report = copy.import(buf.as_slice()).await?;
+  assert_eq![report.rows, 10_000];
+  assert_eq![report.header, header];
+  assert_eq![rows(&mut copy).await?, rows(&mut db).await?];
+  assert![copy.verify().await?.is_ok()];
+
+  // points are exported by coordinate, so a TreeN with the same scalars can import them
+  let mut n: DB<_,TreeN<f32,V>,Vec<Coord<f32>>,V> = Setup::from_path(&dir.path().join("n"))
+    .build()
+    .await?;
+  assert_eq![n.import(buf.as_slice()).await?.rows, 10_000];
+  let mut stream = n.query(&(vec![-1.0,-1.0],vec![1.0,1.0])).await?;
+  let mut results = vec![];
+  while let Some(result) = stream.next().await {
+    let (p,v) = result?;
+    results.push(((p[0].clone(),p[1].clone()),v));
+  }
+  results.sort_unstable_by_key(|(_,v)| *v);
+  assert_eq![results, rows(&mut db).await?];
+  Ok(())
+}
+
+#[async_std::test]
+async fn export_layout() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(&dir.path().join("a")).build().await?;
+  db.batch(&[Row::Insert((Coord::Interval(1.0,2.0),Coord::Scalar(-0.5)), 7)]).await?;
+  let mut buf = vec![];
+  db.export(&mut buf).await?;
+  let mut row = vec![1u8];
+  row.extend_from_slice(&14u32.to_le_bytes());
+  row.push(1);
+  row.extend_from_slice(&1.0f32.to_le_bytes());
+  row.extend_from_slice(&2.0f32.to_le_bytes());
+  row.push(0);
+  row.extend_from_slice(&(-0.5f32).to_le_bytes());
+  row.extend_from_slice(&4u32.to_le_bytes());
+  row.extend_from_slice(&eyros::desert::ToBytes::to_bytes(&7u32)?);
+  row.push(0);
+  row.extend_from_slice(&1u64.to_le_bytes());
+  assert![buf.ends_with(&row), "unexpected row layout"];
+  Ok(())
+}
+
+#[async_std::test]
+async fn import_errors() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(&dir.path().join("a")).build().await?;
+  let inserts: Vec<Row<P,V>> = (0..100).map(|i| {
+    let x = (i as f32)/100.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(x)), i)
+  }).collect();
+  db.batch(&inserts).await?;
+  let mut buf = vec![];
+  db.export(&mut buf).await?;
+
+  let mut other: DB<_,T,P,V> = Setup::from_path(&dir.path().join("b"))
+    .stream_batch_size(10)
+    .build()
+    .await?;
+  assert![other.import(&b"not an export"[..]).await.is_err()];
+  assert![other.import(&buf[..buf.len()-20]).await.is_err(), "truncated streams fail"];
+  let mut bad_count = buf.clone();
+  let n = bad_count.len();
+  bad_count[n-8] = 99;
+  assert![other.import(bad_count.as_slice()).await.is_err()];
+  // a failed import rolls back the batches it already wrote
+  let mut bad_tag = buf.clone();
+  bad_tag[n-9] = 7;
+  assert![other.import(bad_tag.as_slice()).await.is_err()];
+  assert_eq![rows(&mut other).await?, vec![]];
+
+  let mut db3: DB<_,Tree3<f32,f32,f32,V>,P3,V> = Setup::from_path(&dir.path().join("c"))
+    .build()
+    .await?;
+  assert![db3.import(buf.as_slice()).await.is_err(), "point types must match"];
+  Ok(())
+}
@@ -0,0 +1,134 @@
+use eyros::{Coord,Row,DB,Setup,Tree2,TreeN,EyrosError,EyrosErrorKind,Error};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn format_version() -> Result<(),Error> {
+  {
+    let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+    let mut db: DB<_,T,P,V> = eyros::open_from_path2(dir.path()).await?;
+    db.batch(&[
+      Row::Insert((Coord::Scalar(1.0),Coord::From(2.0)), 1),
+      Row::Insert((Coord::To(-3.0),Coord::All), 2),
+    ]).await?;
+    db.sync().await?;
+    let mut db: DB<_,T,P,V> = eyros::open_from_path2(dir.path()).await?;
+    let bbox = ((-10.0,-10.0),(10.0,10.0));
+    let mut stream = db.query(&bbox).await?;
+    let mut n = 0;
+    while let Some(result) = stream.next().await {
+      result?;
+      n += 1;
+    }
+    assert_eq![n, 2, "a database reopens with the current format version"];
+    drop(stream);
+    drop(db);
+    // the meta records the point type, so other point types are refused
+    let r: Result<DB<_,Tree2<f64,f64,V>,(Coord<f64>,Coord<f64>),V>,Error>
+      = Setup::from_path(dir.path()).build().await;
+    let err = r.err().expect("opened a database with other scalars");
+    assert![matches![kind(&err), Some(EyrosErrorKind::PointType { .. })],
+      "unexpected error: {}", err];
+    assert![format!["{}", err].contains("scalars f32,f32 but was opened with scalars f64,f64"),
+      "unexpected error: {}", err];
+    let r: Result<DB<_,TreeN<f32,V>,Vec<Coord<f32>>,V>,Error>
+      = Setup::from_path(dir.path()).build().await;
+    let err = r.err().expect("opened a database with runtime dimensions");
+    assert![matches![kind(&err), Some(EyrosErrorKind::PointType { .. })],
+      "unexpected error: {}", err];
+  }
+  {
+    // a version 1 database with a root tree that refers to a second tree
+    let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+    write_v1(dir.path())?;
+    let r: Result<DB<_,TreeN<f32,V>,Vec<Coord<f32>>,V>,Error>
+      = Setup::from_path(dir.path()).build().await;
+    let err = r.err().expect("opened a version 1 database with runtime dimensions");
+    assert![matches![kind(&err), Some(EyrosErrorKind::FormatVersion { version: 1 })],
+      "unexpected error: {}", err];
+    let mut db: DB<_,T,P,V> = eyros::open_from_path2(dir.path()).await?;
+    assert_eq![query(&mut db).await?, vec![
+      ((Coord::Scalar(1.0),Coord::Scalar(2.0)),1),
+      ((Coord::Scalar(-3.0),Coord::Interval(-1.0,1.0)),2),
+      ((Coord::Interval(0.5,0.75),Coord::Scalar(0.25)),3),
+    ], "version 1 records after the upgrade"];
+    for id in 0..2 {
+      assert![!dir.path().join(tree_file(id)).exists(), "version 1 tree {} removed", id];
+    }
+    db.batch(&[Row::Insert((Coord::Scalar(5.0),Coord::From(6.0)), 4)]).await?;
+    db.sync().await?;
+    drop(db);
+    let mut db: DB<_,T,P,V> = eyros::open_from_path2(dir.path()).await?;
+    assert_eq![query(&mut db).await?.len(), 4, "upgraded database reopens"];
+  }
+  {
+    let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+    std::fs::write(dir.path().join("meta"), b"eyros\x03\x00\x00")?;
+    let err = open_err(dir.path()).await;
+    assert![matches![kind(&err), Some(EyrosErrorKind::FormatVersion { version: 3 })],
+      "unexpected error: {}", err];
+  }
+  Ok(())
+}
+
+async fn open_err(path: &std::path::Path) -> Error {
+  let r: Result<DB<_,T,P,V>,Error> = eyros::open_from_path2(path).await;
+  match r {
+    Ok(_) => panic!["opened a database with an unsupported format version"],
+    Err(e) => e,
+  }
+}
+
+// version 1 stored integers and floats big endian, points in trees as a bitfield of which
+// dimensions are intervals followed by the coordinates, and the bounds of refs as an interval in
+// each dimension. the meta has no header and stores root bounds as ((xmin,ymin),(xmax,ymax)).
+fn write_v1(path: &std::path::Path) -> Result<(),Error> {
+  fn floats(buf: &mut Vec<u8>, xs: &[f32]) {
+    for x in xs { buf.extend_from_slice(&x.to_be_bytes()) }
+  }
+  let mut t0 = ((2u32<<1)|1|(1<<17)).to_be_bytes().to_vec();
+  t0.push(0b00);
+  floats(&mut t0, &[1.0,2.0]);
+  t0.extend_from_slice(&1u32.to_be_bytes());
+  t0.push(0b10);
+  floats(&mut t0, &[-3.0,-1.0,1.0]);
+  t0.extend_from_slice(&2u32.to_be_bytes());
+  t0.push(1);
+  floats(&mut t0, &[0.5,0.75,0.25,0.25]);
+  let mut t1 = ((1u32<<1)|1).to_be_bytes().to_vec();
+  t1.push(0b01);
+  floats(&mut t1, &[0.5,0.75,0.25]);
+  t1.extend_from_slice(&3u32.to_be_bytes());
+  for (id,bytes) in [(0,t0),(1,t1)].iter() {
+    let file = path.join(tree_file(*id));
+    std::fs::create_dir_all(file.parent().unwrap())?;
+    std::fs::write(file, bytes)?;
+  }
+  let mut meta = vec![2,1,0b1,0];
+  floats(&mut meta, &[-3.0,-1.0,1.0,2.0]);
+  std::fs::write(path.join("meta"), &meta)?;
+  Ok(())
+}
+
+fn tree_file(id: u64) -> String {
+  format!["t/00/00/00/00/00/00/00/{:02x}", id]
+}
+
+async fn query(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<Vec<(P,V)>,Error> {
+  let bbox = ((-10.0,-10.0),(10.0,10.0));
+  let mut stream = db.query(&bbox).await?;
+  let mut rows = vec![];
+  while let Some(result) = stream.next().await {
+    rows.push(result?);
+  }
+  rows.sort_by_key(|row| row.1);
+  Ok(rows)
+}
+
+fn kind(err: &Error) -> Option<&EyrosErrorKind> {
+  err.downcast_ref::<EyrosError>().map(|e| e.kind())
+}
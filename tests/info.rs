@@ -0,0 +1,60 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn info() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .max_records(500)
+    .build()
+    .await?;
+  let empty = db.info().await?;
+  assert_eq![empty.roots.len(), 0];
+  assert_eq![empty.records, 0];
+  assert_eq![empty.inline_ratio(), 0.0];
+  assert![empty.bounds.is_none()];
+
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..30_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    let p = if i % 3 == 0 {
+      (Coord::Interval(x,x+0.01),Coord::Scalar(y))
+    } else {
+      (Coord::Scalar(x),Coord::Scalar(y))
+    };
+    Row::Insert(p, i)
+  }).collect();
+  for batch in rows.chunks(10_000) {
+    db.batch(batch).await?;
+  }
+  db.sync().await?;
+  let info = db.info().await?;
+  let live = db.meta.read().await.roots.iter().filter(|r| r.is_some()).count();
+  assert_eq![info.roots.len(), live];
+  assert_eq![info.records, 30_000];
+  assert_eq![info.roots.iter().map(|r| r.records).sum::<usize>(), 30_000];
+  assert_eq![info.inline_records + info.external_records, 30_000];
+  assert![info.external_records > 0, "max_records pushes rows into external trees"];
+  assert_eq![info.depths.iter().sum::<usize>(), info.trees];
+  assert_eq![info.depths[0], live];
+  assert_eq![info.depths.len(), info.roots.iter().map(|r| r.depth).max().unwrap() + 1];
+  assert![info.bytes > 0];
+
+  let (xmin,xmax,ymin,ymax) = rows.iter().fold((f32::MAX,f32::MIN,f32::MAX,f32::MIN), |b,row| {
+    match row {
+      Row::Insert(p,_) => (
+        b.0.min(*p.0.min().unwrap()), b.1.max(*p.0.max().unwrap()),
+        b.2.min(*p.1.min().unwrap()), b.3.max(*p.1.max().unwrap()),
+      ),
+      _ => b,
+    }
+  });
+  assert_eq![info.bounds, Some((Coord::Interval(xmin,xmax),Coord::Interval(ymin,ymax)))];
+  Ok(())
+}
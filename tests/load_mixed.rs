@@ -89,6 +89,7 @@ fn contains<T> (min: &T, max: &T, c: &Coord<T>) -> bool where T: Scalar {
   match c {
     Coord::Interval(x0,x1) => min <= x1 && x0 <= max,
     Coord::Scalar(x) => min <= x && x <= max,
+    _ => panic!["unexpected coordinate type"],
   }
 }
 
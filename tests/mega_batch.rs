@@ -146,6 +146,7 @@ fn cmp<T> (a: &T, b: &T) -> Ordering where T: PartialOrd {
 fn contains<T> (min: &T, max: &T, c: &Coord<T>) -> bool where T: Scalar {
   match c {
     Coord::Interval(x0,x1) => min <= x1 && x0 <= max,
-    Coord::Scalar(x) => min <= x && x <= max
+    Coord::Scalar(x) => min <= x && x <= max,
+    _ => panic!["unexpected coordinate type"],
   }
 }
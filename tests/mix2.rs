@@ -61,9 +61,11 @@ fn contains (point: &P, bbox: &<P as Point>::Bounds) -> bool {
   (match point.0 {
     Coord::Scalar(x) => contains_pt((bbox.0).0, (bbox.1).0, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).0, (bbox.1).0, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   }) && (match point.1 {
     Coord::Scalar(x) => contains_pt((bbox.0).1, (bbox.1).1, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).1, (bbox.1).1, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   })
 }
 
@@ -86,6 +88,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   if xcmp != Ordering::Equal { return xcmp }
   match (&(a.0).1,&(b.0).1) {
@@ -99,5 +102,6 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   }
 }
@@ -75,12 +75,15 @@ fn contains (point: &P, bbox: &<P as Point>::Bounds) -> bool {
   (match point.0 {
     Coord::Scalar(x) => contains_pt((bbox.0).0, (bbox.1).0, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).0, (bbox.1).0, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   }) && (match point.1 {
     Coord::Scalar(x) => contains_pt((bbox.0).1, (bbox.1).1, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).1, (bbox.1).1, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   }) && (match point.2 {
     Coord::Scalar(x) => contains_pt((bbox.0).2, (bbox.1).2, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).2, (bbox.1).2, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   })
 }
 
@@ -103,6 +106,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   if xcmp != Ordering::Equal { return xcmp }
   let ycmp = match (&(a.0).1,&(b.0).1) {
@@ -116,6 +120,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   if ycmp != Ordering::Equal { return ycmp }
   let zcmp = match (&(a.0).2,&(b.0).2) {
@@ -129,6 +134,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   zcmp
 }
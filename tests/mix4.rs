@@ -88,15 +88,19 @@ fn contains (point: &P, bbox: &<P as Point>::Bounds) -> bool {
   (match point.0 {
     Coord::Scalar(x) => contains_pt((bbox.0).0, (bbox.1).0, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).0, (bbox.1).0, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   }) && (match point.1 {
     Coord::Scalar(x) => contains_pt((bbox.0).1, (bbox.1).1, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).1, (bbox.1).1, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   }) && (match point.2 {
     Coord::Scalar(x) => contains_pt((bbox.0).2, (bbox.1).2, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).2, (bbox.1).2, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   }) && (match point.3 {
     Coord::Scalar(x) => contains_pt((bbox.0).3, (bbox.1).3, x),
     Coord::Interval(x0,x1) => contains_iv((bbox.0).3, (bbox.1).3, x0, x1),
+    _ => panic!["unexpected coordinate type"],
   })
 }
 
@@ -119,6 +123,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   if xcmp != Ordering::Equal { return xcmp }
   let ycmp = match (&(a.0).1,&(b.0).1) {
@@ -132,6 +137,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   if ycmp != Ordering::Equal { return ycmp }
   let zcmp = match (&(a.0).2,&(b.0).2) {
@@ -145,6 +151,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   if zcmp != Ordering::Equal { return zcmp }
   let wcmp = match (&(a.0).3,&(b.0).3) {
@@ -158,6 +165,7 @@ fn cmp (a: &(P,V), b: &(P,V)) -> Ordering {
     },
     (Coord::Scalar(_),Coord::Interval(_,_)) => Ordering::Less,
     (Coord::Interval(_,_),Coord::Scalar(_)) => Ordering::Greater,
+    _ => panic!["unexpected coordinate type"],
   };
   wcmp
 }
@@ -0,0 +1,122 @@
+use eyros::{Coord,Row,Setup,DB,Tree2,DumpNode,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn non_finite() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db = eyros::open_from_path2(dir.path()).await?;
+  let bad: Vec<P> = vec![
+    (Coord::Scalar(f32::NAN),Coord::Scalar(0.0)),
+    (Coord::Scalar(0.0),Coord::Interval(f32::NAN,1.0)),
+    (Coord::Interval(0.0,f32::INFINITY),Coord::Scalar(0.0)),
+    (Coord::Scalar(0.0),Coord::From(f32::NEG_INFINITY)),
+    (Coord::To(f32::NAN),Coord::All),
+  ];
+  for p in bad.iter() {
+    let mut batch = rows(100, [13,12]);
+    batch.insert(50, Row::Insert(p.clone(), 9000));
+    let err = db.batch(&batch).await.expect_err("non-finite coordinates are rejected");
+    assert![format!["{}", err].contains("non-finite"), "unexpected error: {}", err];
+    let err = db.batch(&[Row::Delete(p.clone(), 9000)]).await
+      .expect_err("non-finite coordinates are rejected for deletes");
+    assert![format!["{}", err].contains("non-finite"), "unexpected error: {}", err];
+  }
+  assert_eq![count(&mut db).await?, 0, "rejected batches don't insert any rows"];
+  db.batch(&rows(100, [13,12])).await?;
+  assert_eq![count(&mut db).await?, 100];
+  Ok(())
+}
+
+#[async_std::test]
+async fn total_order() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .total_order(true)
+    .build()
+    .await?;
+  assert![db.fields.total_order];
+  db.batch(&rows(5_000, [13,12])).await?;
+  db.batch(&rows(5_000, [14,12])).await?;
+  assert_eq![count(&mut db).await?, 10_000];
+  let mut batch = vec![];
+  for (i,row) in rows(5_000, [15,12]).into_iter().enumerate() {
+    match (i % 5, row) {
+      (0, Row::Insert((_,y),v)) => batch.push(Row::Insert((Coord::Scalar(f32::NAN),y),v)),
+      (1, Row::Insert((x,_),v)) => batch.push(Row::Insert((x,Coord::Interval(0.0,f32::NAN)),v)),
+      (2, Row::Insert((x,_),v)) => batch.push(Row::Insert((x,Coord::From(f32::INFINITY)),v)),
+      (_, row) => batch.push(row),
+    }
+  }
+  db.batch(&batch).await?;
+  db.sync().await?;
+  assert_eq![count(&mut db).await?, 13_000, "finite queries skip NaN and infinite rows"];
+  let info = db.info().await?;
+  assert_eq![info.inline_records + info.external_records, 15_000];
+  assert![db.verify().await?.is_ok()];
+  let err = db.batch(&[Row::Insert((Coord::Interval(f32::NAN,0.0),Coord::Scalar(0.0)),1)]).await
+    .expect_err("NaN sorts after every other value, so the interval sides are reversed");
+  assert![format!["{}", err].contains("min <= max"), "unexpected error: {}", err];
+  Ok(())
+}
+
+#[async_std::test]
+async fn total_order_infinite() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .total_order(true)
+    .build()
+    .await?;
+  // adjacent rows of -inf and +inf have no midpoint, so pivots between them must not be NaN
+  let batch = rows(10_000, [13,12]).into_iter().enumerate().map(|(i,row)| {
+    let x = match i % 3 {
+      0 => Coord::Scalar(f32::NEG_INFINITY),
+      1 => Coord::Interval(f32::NEG_INFINITY,f32::INFINITY),
+      _ => Coord::Scalar(f32::INFINITY),
+    };
+    match row {
+      Row::Insert((_,y),v) => Row::Insert((x,y),v),
+      row => row,
+    }
+  }).collect::<Vec<_>>();
+  db.batch(&batch).await?;
+  db.sync().await?;
+  assert_eq![count(&mut db).await?, 3_333, "infinite intervals match finite queries"];
+  assert![db.verify().await?.is_ok()];
+  let mut nodes = db.dump().await?.trees.into_iter().map(|t| t.root).collect::<Vec<_>>();
+  let mut branches = 0;
+  while let Some(node) = nodes.pop() {
+    if let DumpNode::Branch { pivots, intersections, nodes: children, .. } = node {
+      assert![pivots.iter().all(|p| !p.contains("NaN")), "NaN pivot in {:?}", pivots];
+      branches += 1;
+      nodes.extend(intersections.into_iter().map(|(_,n)| n));
+      nodes.extend(children);
+    }
+  }
+  assert![branches > 0];
+  Ok(())
+}
+
+fn rows(n: usize, seed: [u64;2]) -> Vec<Row<P,V>> {
+  let mut r = rand().seed(seed);
+  (0..n).map(|i| {
+    let x: f32 = r.read::<f32>()*2.0-1.0;
+    let y: f32 = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i as u32)
+  }).collect()
+}
+
+async fn count(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<usize,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut n = 0;
+  while let Some(result) = stream.next().await {
+    result?;
+    n += 1;
+  }
+  Ok(n)
+}
@@ -0,0 +1,114 @@
+use eyros::{Coord,Scalar,Row,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+use std::collections::HashMap;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+
+#[async_std::test]
+async fn open_interval() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..5_000).map(|i| {
+    Row::Insert((coord(&mut r),coord(&mut r)), i as u32)
+  }).collect();
+  let bboxes = [
+    ((-1.0,-1.0),(1.0,1.0)),
+    ((-0.5,-0.8),(0.3,-0.5)),
+    ((0.9,0.9),(0.95,0.99)),
+    ((-5.0,2.0),(-4.0,3.0)),
+  ];
+  let deletes: Vec<Row<P,V>> = rows[0..200].iter().map(|row| match row {
+    Row::Insert(p,v) => Row::Delete(p.clone(),*v),
+    _ => panic!["unexpected row type"],
+  }).collect();
+  let inserted: HashMap<V,P> = rows[200..].iter().map(|row| match row {
+    Row::Insert(p,v) => (*v,p.clone()),
+    _ => panic!["unexpected row type"],
+  }).collect();
+  {
+    let mut db = eyros::open_from_path2(dir.path()).await?;
+    for batch in rows.chunks(1_000) {
+      db.batch(batch).await?;
+    }
+    db.batch(&deletes).await?;
+    db.sync().await?;
+    for bbox in bboxes.iter() {
+      assert_eq![query(&mut db, bbox).await?, expected(&inserted, bbox), "bbox={:?}", bbox];
+    }
+  }
+  {
+    let mut db = eyros::open_from_path2(dir.path()).await?;
+    for bbox in bboxes.iter() {
+      assert_eq![query(&mut db, bbox).await?, expected(&inserted, bbox),
+        "after reopen bbox={:?}", bbox];
+    }
+  }
+  Ok(())
+}
+
+#[async_std::test]
+async fn open_interval_n() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([14,12]);
+  let rows: Vec<Row<Vec<Coord<f32>>,V>> = (0..3_000).map(|i| {
+    Row::Insert((0..3).map(|_| coord(&mut r)).collect(), i as u32)
+  }).collect();
+  let bbox = (vec![-0.5,-0.8,0.0],vec![0.3,-0.5,0.2]);
+  let mut db = eyros::open_from_path_n(dir.path()).await?;
+  db.batch(&rows[0..1_500]).await?;
+  db.batch(&rows[1_500..]).await?;
+  let mut results = HashMap::new();
+  let mut stream = db.query(&bbox).await?;
+  while let Some(result) = stream.next().await {
+    let (p,v) = result?;
+    assert![results.insert(v,p).is_none(), "duplicate result"];
+  }
+  let expected: HashMap<V,Vec<Coord<f32>>> = rows.iter().filter_map(|row| match row {
+    Row::Insert(p,v) => {
+      let hit = p.iter().enumerate().all(|(d,c)| contains(&bbox.0[d], &bbox.1[d], c));
+      if hit { Some((*v,p.clone())) } else { None }
+    },
+    _ => None,
+  }).collect();
+  assert_eq![results, expected];
+  Ok(())
+}
+
+fn coord(r: &mut impl Source) -> Coord<f32> {
+  let x: f32 = r.read::<f32>()*2.0-1.0;
+  match r.read::<u32>() % 8 {
+    0 => Coord::From(x),
+    1 => Coord::To(x),
+    2 if r.read::<f32>() < 0.1 => Coord::All,
+    3 | 4 => Coord::Interval(x, x + r.read::<f32>().powf(8.0)*(1.0-x)),
+    _ => Coord::Scalar(x),
+  }
+}
+
+async fn query(db: &mut eyros::DB<impl eyros::RA,eyros::Tree2<f32,f32,V>,P,V>,
+bbox: &((f32,f32),(f32,f32))) -> Result<HashMap<V,P>,Error> {
+  let mut results = HashMap::new();
+  let mut stream = db.query(bbox).await?;
+  while let Some(result) = stream.next().await {
+    let (p,v) = result?;
+    assert![results.insert(v,p).is_none(), "duplicate result"];
+  }
+  Ok(results)
+}
+
+fn expected(inserted: &HashMap<V,P>, bbox: &((f32,f32),(f32,f32))) -> HashMap<V,P> {
+  inserted.iter()
+    .filter(|(_,p)| {
+      contains(&(bbox.0).0, &(bbox.1).0, &p.0) && contains(&(bbox.0).1, &(bbox.1).1, &p.1)
+    })
+    .map(|(v,p)| (*v,p.clone()))
+    .collect()
+}
+
+fn contains<T> (min: &T, max: &T, c: &Coord<T>) -> bool where T: Scalar {
+  c.min().map(|x| x <= max).unwrap_or(true) && c.max().map(|x| min <= x).unwrap_or(true)
+}
@@ -0,0 +1,140 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+fn inserts(n: usize) -> Vec<Row<P,V>> {
+  let mut r = rand().seed([13,12]);
+  (0..n as u32).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i)
+  }).collect()
+}
+
+#[async_std::test]
+async fn pending_bytes() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+  assert_eq![db.pending_bytes().await, 0];
+  db.batch(&inserts(5_000)).await?;
+  let pending = db.pending_bytes().await;
+  assert![pending > 0];
+  db.sync().await?;
+  assert_eq![db.pending_bytes().await, 0];
+  assert![db.stats().bytes_written >= pending];
+  Ok(())
+}
+
+#[async_std::test]
+async fn max_pending_bytes() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let max = 50_000;
+  let rows = inserts(20_000);
+  let mut synced = 0;
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+      .max_records(500)
+      .max_pending_bytes(max)
+      .build()
+      .await?;
+    for (i,batch) in rows.chunks(500).enumerate() {
+      db.batch(batch).await?;
+      let pending = db.pending_bytes().await;
+      assert![pending < max as u64, "{} bytes pending after batch {}", pending, i];
+      if pending == 0 {
+        synced = (i+1)*500;
+      }
+    }
+    // dropped without calling sync()
+  }
+  assert![synced > 0, "no batch synced automatically"];
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+  assert_eq![count(&mut db).await?, synced];
+  assert![db.verify().await?.is_ok()];
+  Ok(())
+}
+
+#[async_std::test]
+async fn large_batch_is_atomic() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let max = 50_000;
+  let rows = inserts(20_000);
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .max_records(500)
+    .max_pending_bytes(max)
+    .build()
+    .await?;
+  db.batch(&rows[0..10_000]).await?;
+  assert![db.stats().bytes_written > 0, "the batch synced after it finished"];
+  assert_eq![db.pending_bytes().await, 0];
+  let mut batch = rows[0..2_000].iter().map(|row| match row {
+    Row::Insert(p,v) => Row::Delete(p.clone(),*v),
+    _ => panic!["unexpected row type"],
+  }).collect::<Vec<_>>();
+  batch.extend_from_slice(&rows[10_000..]);
+  let report = db.batch(&batch).await?;
+  assert_eq![report.deleted, 2_000];
+  assert_eq![report.inserted, 10_000];
+  assert_eq![db.pending_bytes().await, 0];
+  // a batch over the limit merges into the forest once, like it does without a limit
+  let unlimited_dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut unlimited: DB<_,T,P,V> = Setup::from_path(unlimited_dir.path())
+    .max_records(500)
+    .build()
+    .await?;
+  unlimited.batch(&rows[0..10_000]).await?;
+  unlimited.batch(&batch).await?;
+  let (info, unlimited_info) = (db.info().await?, unlimited.info().await?);
+  assert_eq![info.roots.len(), unlimited_info.roots.len()];
+  assert_eq![info.trees, unlimited_info.trees];
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+  assert_eq![count(&mut db).await?, 18_000];
+  assert![db.verify().await?.is_ok()];
+  Ok(())
+}
+
+#[async_std::test]
+async fn transactions_stay_pending() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let rows = inserts(10_000);
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .max_records(500)
+    .max_pending_bytes(1)
+    .build()
+    .await?;
+  db.batch(&rows[0..2_000]).await?;
+  assert_eq![db.pending_bytes().await, 0];
+  let mut tx = db.begin().await?;
+  for batch in rows[2_000..].chunks(2_000) {
+    tx.batch(batch).await?;
+  }
+  tx.rollback().await?;
+  assert_eq![db.pending_bytes().await, 0];
+  assert_eq![count(&mut db).await?, 2_000];
+  // nothing from the rolled back transaction reached storage
+  let mut stored: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+  assert_eq![count(&mut stored).await?, 2_000];
+
+  let mut tx = db.begin().await?;
+  tx.batch(&rows[2_000..4_000]).await?;
+  tx.commit().await?;
+  assert_eq![db.pending_bytes().await, 0];
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+  assert_eq![count(&mut db).await?, 4_000];
+  Ok(())
+}
+
+async fn count(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<usize,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut n = 0;
+  while let Some(result) = stream.next().await {
+    result?;
+    n += 1;
+  }
+  Ok(n)
+}
@@ -0,0 +1,83 @@
+use eyros::{Coord,Row,SerdeValue,SerdeId,Error};
+use desert::{ToBytes,FromBytes};
+use serde::{Serialize,Deserialize};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+use std::collections::HashMap;
+
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+struct Feature {
+  id: u64,
+  name: String,
+  height: Option<f32>,
+  tags: Vec<String>,
+}
+
+struct FeatureId;
+impl SerdeId<Feature> for FeatureId {
+  type Id = u64;
+  fn get_id(feature: &Feature) -> u64 { feature.id }
+}
+
+type P = (Coord<f32>,Coord<f32>);
+type V = SerdeValue<Feature,FeatureId>;
+
+#[async_std::test]
+async fn serde_value() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..2_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), SerdeValue::new(Feature {
+      id: i,
+      name: format!["feature {}", i],
+      height: if i % 3 == 0 { None } else { Some(i as f32) },
+      tags: (0..i%4).map(|j| format!["tag{}", j]).collect(),
+    }))
+  }).collect();
+  let deletes: Vec<Row<P,V>> = rows.iter().step_by(5).map(|row| match row {
+    Row::Insert(p,v) => Row::Delete(p.clone(),v.value.id),
+    _ => panic!["unexpected row type"],
+  }).collect();
+  let expected: HashMap<u64,Feature> = rows.iter().enumerate()
+    .filter(|(i,_)| i % 5 != 0)
+    .map(|(_,row)| match row {
+      Row::Insert(_,v) => (v.value.id,v.value.clone()),
+      _ => panic!["unexpected row type"],
+    })
+    .collect();
+  {
+    let mut db = eyros::open_from_path2(dir.path()).await?;
+    db.batch(&rows).await?;
+    db.batch(&deletes).await?;
+    db.sync().await?;
+  }
+  let mut db = eyros::open_from_path2(dir.path()).await?;
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut results: HashMap<u64,Feature> = HashMap::new();
+  while let Some(result) = stream.next().await {
+    let (_,v): (P,V) = result?;
+    assert![results.insert(v.value.id,v.into_inner()).is_none(), "duplicate result"];
+  }
+  assert_eq![results, expected];
+  Ok(())
+}
+
+#[test]
+fn serde_value_truncated() -> Result<(),Error> {
+  let v: V = SerdeValue::new(Feature {
+    id: 5,
+    name: "feature 5".to_string(),
+    height: Some(5.0),
+    tags: vec!["tag0".to_string()],
+  });
+  let bytes = v.to_bytes()?;
+  assert_eq![V::from_bytes(&bytes)?.1.into_inner(), v.value];
+  for len in 0..bytes.len() {
+    assert![V::from_bytes(&bytes[0..len]).is_err(), "decoded a value from {} bytes", len];
+  }
+  Ok(())
+}
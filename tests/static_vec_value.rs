@@ -103,6 +103,7 @@ fn coord_cmp<X>(x: &Coord<X>, y: &Coord<X>) -> Option<std::cmp::Ordering> where
     (Coord::Scalar(a),Coord::Interval(b,_)) => a.partial_cmp(b),
     (Coord::Interval(a,_),Coord::Scalar(b)) => a.partial_cmp(b),
     (Coord::Interval(a,_),Coord::Interval(b,_)) => a.partial_cmp(b),
+    _ => panic!["unexpected coordinate type"],
   }
 }
 
@@ -110,5 +111,6 @@ fn intersect_coord<X>(low: &X, high: &X, c: &Coord<X>) -> bool where X: Scalar {
   match c {
     Coord::Scalar(x) => low <= x && x <= high,
     Coord::Interval(x,y) => y >= low && x <= high,
+    _ => panic!["unexpected coordinate type"],
   }
 }
@@ -0,0 +1,88 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,Stats,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn stats() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..20_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i)
+  }).collect();
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+    assert_eq![db.stats(), Stats::default()];
+    db.batch(&rows[0..10_000]).await?;
+    db.batch(&rows[10_000..]).await?;
+    db.sync().await?;
+    let stats = db.stats();
+    assert![stats.trees_created > 0];
+    assert![stats.bytes_written > 0];
+    assert_eq![stats.bytes_read, 0];
+    assert_eq![stats.queries, 0];
+  }
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .tree_cache_size(1_000)
+    .build()
+    .await?;
+  let meta_bytes = db.stats().bytes_read;
+  assert![meta_bytes > 0, "meta is read on open"];
+  let n = count(&mut db).await?;
+  assert_eq![n, 20_000];
+  let first = db.stats();
+  assert_eq![first.queries, 1];
+  assert_eq![first.rows_returned, 20_000];
+  assert![first.cache_misses > 0];
+  assert_eq![first.trees_read, first.cache_misses];
+  assert![first.bytes_read > meta_bytes];
+  assert_eq![first.bytes_written, 0];
+
+  // the second query is served from the cache
+  count(&mut db).await?;
+  let second = db.stats();
+  assert_eq![second.queries, 2];
+  assert_eq![second.rows_returned, 40_000];
+  assert_eq![second.cache_misses, first.cache_misses];
+  assert_eq![second.bytes_read, first.bytes_read];
+  assert![second.cache_hits >= first.cache_hits + first.cache_misses];
+  Ok(())
+}
+
+async fn count(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<usize,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut n = 0;
+  while let Some(result) = stream.next().await {
+    result?;
+    n += 1;
+  }
+  Ok(n)
+}
+
+#[async_std::test]
+async fn stats_pending_trees() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let points: Vec<P> = (0..5_000).map(|_| {
+    (Coord::Scalar(r.read::<f32>()*2.0-1.0),Coord::Scalar(r.read::<f32>()*2.0-1.0))
+  }).collect();
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+  let inserts = points.iter().enumerate()
+    .map(|(i,p)| Row::Insert(p.clone(), i as V)).collect::<Vec<_>>();
+  db.batch(&inserts).await?;
+  let created = db.stats().trees_created;
+  assert![created > 0];
+  // trees that haven't been synced are replaced in place, which doesn't create trees
+  let deletes = points.iter().enumerate().take(100)
+    .map(|(i,p)| Row::Delete(p.clone(), i as V)).collect::<Vec<_>>();
+  db.batch(&deletes).await?;
+  assert_eq![db.stats().trees_created, created];
+  assert_eq![count(&mut db).await?, 4_900];
+  Ok(())
+}
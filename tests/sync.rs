@@ -0,0 +1,170 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+use std::collections::HashMap;
+use std::path::{Path,PathBuf};
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+fn inserts(start: u32, n: u32) -> Vec<Row<P,V>> {
+  let mut r = rand().seed([13,12+start as u64]);
+  (start..start+n).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i)
+  }).collect()
+}
+
+#[async_std::test]
+async fn synced_trees_are_not_rewritten() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let rows = inserts(0, 5_000);
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).max_records(200).build().await?;
+  db.batch(&rows).await?;
+  db.sync().await?;
+  let before = files(dir.path())?;
+  let deletes = rows.iter().step_by(5).map(|row| match row {
+    Row::Insert(p,v) => Row::Delete(p.clone(),*v),
+    _ => panic!["unexpected row type"],
+  }).collect::<Vec<_>>();
+  let report = db.batch(&deletes).await?;
+  assert_eq![report.deleted, 1_000];
+  assert![!report.trees_updated.is_empty()];
+  assert![report.trees_updated.iter().all(|id| !report.trees_removed.contains(id))];
+  db.sync().await?;
+  let after = files(dir.path())?;
+  for (file,bytes) in before.iter() {
+    if is_meta(file) { continue }
+    if let Some(b) = after.get(file) {
+      assert![b == bytes, "synced tree file {:?} was rewritten", file];
+    }
+  }
+
+  // a crash after the trees were written but before the meta leaves the previous version
+  let crash = Tmpfile::new().prefix("eyros").tempdir()?;
+  write_files(crash.path(), &before)?;
+  write_files(crash.path(), &after.into_iter().filter(|(file,_)| {
+    !is_meta(file) && !before.contains_key(file)
+  }).collect())?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(crash.path()).build().await?;
+  assert_eq![count(&mut db).await?, 5_000];
+  assert![db.verify().await?.is_ok()];
+  Ok(())
+}
+
+#[async_std::test]
+async fn optimize_moves_trees() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).max_records(200).build().await?;
+    for i in 0..5 {
+      db.batch(&inserts(i*1_000, 1_000)).await?;
+    }
+    db.sync().await?;
+  }
+  let before = files(dir.path())?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).max_records(200).build().await?;
+  db.optimize(2).await?;
+  let after = files(dir.path())?;
+  for (file,bytes) in before.iter() {
+    if is_meta(file) { continue }
+    if let Some(b) = after.get(file) {
+      assert![b == bytes, "synced tree file {:?} was rewritten", file];
+    }
+  }
+  assert_eq![count(&mut db).await?, 5_000];
+  assert![db.verify().await?.is_ok()];
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+  assert_eq![count(&mut db).await?, 5_000];
+  assert![db.verify().await?.is_ok()];
+  Ok(())
+}
+
+#[async_std::test]
+async fn meta_slots() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+    db.batch(&inserts(0, 1_000)).await?;
+    db.sync().await?;
+  }
+  let first = files(dir.path())?;
+  assert![first.contains_key(Path::new("meta")), "the first write goes to meta"];
+  assert![first.get(Path::new("meta.1")).map_or(true, |b| b.is_empty())];
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+    db.batch(&inserts(1_000, 1_000)).await?;
+    db.sync().await?;
+    assert_eq![count(&mut db).await?, 2_000];
+  }
+  let second = files(dir.path())?;
+  assert_eq![first.get(Path::new("meta")), second.get(Path::new("meta")),
+    "the second write goes to meta.1"];
+  {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).build().await?;
+    assert_eq![count(&mut db).await?, 2_000, "the newest slot is read"];
+  }
+
+  // a torn write to meta.1 falls back to the meta in the other slot
+  let torn = Tmpfile::new().prefix("eyros").tempdir()?;
+  write_files(torn.path(), &first)?;
+  write_files(torn.path(), &second.iter().filter(|(file,_)| !first.contains_key(*file))
+    .map(|(file,bytes)| (file.clone(),bytes.clone())).collect())?;
+  let mut meta1 = second.get(Path::new("meta.1")).unwrap().clone();
+  let n = meta1.len();
+  meta1[n-1] ^= 0xff;
+  std::fs::write(torn.path().join("meta.1"), &meta1)?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(torn.path()).build().await?;
+  assert_eq![count(&mut db).await?, 1_000];
+  assert![db.verify().await?.is_ok()];
+  // the next sync overwrites the torn slot
+  db.batch(&inserts(2_000, 10)).await?;
+  db.sync().await?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(torn.path()).build().await?;
+  assert_eq![count(&mut db).await?, 1_010];
+  Ok(())
+}
+
+fn is_meta(file: &Path) -> bool {
+  file == Path::new("meta") || file == Path::new("meta.1")
+}
+
+fn files(dir: &Path) -> Result<HashMap<PathBuf,Vec<u8>>,Error> {
+  let mut files = HashMap::new();
+  let mut dirs = vec![dir.to_path_buf()];
+  while let Some(d) = dirs.pop() {
+    for entry in std::fs::read_dir(&d)? {
+      let path = entry?.path();
+      if path.is_dir() {
+        dirs.push(path);
+      } else {
+        let bytes = std::fs::read(&path)?;
+        files.insert(path.strip_prefix(dir)?.to_path_buf(), bytes);
+      }
+    }
+  }
+  Ok(files)
+}
+
+fn write_files(dir: &Path, files: &HashMap<PathBuf,Vec<u8>>) -> Result<(),Error> {
+  for (file,bytes) in files.iter() {
+    let path = dir.join(file);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, bytes)?;
+  }
+  Ok(())
+}
+
+async fn count(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<usize,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut n = 0;
+  while let Some(result) = stream.next().await {
+    result?;
+    n += 1;
+  }
+  Ok(n)
+}
@@ -0,0 +1,41 @@
+use eyros::Error;
+use tempfile::Builder as Tmpfile;
+
+mod common;
+use common::{points,inserts,deletes,ids};
+
+#[async_std::test]
+async fn transaction() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let points = points(3000);
+  let bbox = ((-1.0,-1.0),(1.0,1.0));
+  {
+    let mut db = eyros::open_from_path2(dir.path()).await?;
+    db.batch(&inserts(&points, 0..1000)).await?;
+    db.sync().await?;
+
+    let mut tx = db.begin().await?;
+    tx.batch(&inserts(&points, 1000..1500)).await?;
+    tx.batch(&inserts(&points, 1500..2000)).await?;
+    tx.batch(&deletes(&points, 0..100)).await?;
+    assert_eq![ids(tx.query(&bbox).await?).await?, (100..2000).collect(),
+      "transaction sees its own changes"];
+    tx.rollback().await?;
+    assert_eq![ids(db.query(&bbox).await?).await?, (0..1000).collect(),
+      "rollback discards every batch in the transaction"];
+
+    let mut tx = db.begin().await?;
+    tx.batch(&inserts(&points, 2000..2500)).await?;
+    tx.batch(&inserts(&points, 2500..3000)).await?;
+    tx.commit().await?;
+  }
+  {
+    let mut db = eyros::open_from_path2(dir.path()).await?;
+    assert_eq![
+      ids(db.query(&bbox).await?).await?,
+      (0..1000).chain(2000..3000).collect(),
+      "committed batches are written to storage"
+    ];
+  }
+  Ok(())
+}
@@ -0,0 +1,80 @@
+use eyros::{Setup,DB,Tree2,Row,Coord,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn tree_cache_bytes() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..20_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i)
+  }).collect();
+  let bytes = {
+    let mut db: DB<_,T,P,V> = Setup::from_path(dir.path()).max_records(500).build().await?;
+    db.batch(&rows[0..10_000]).await?;
+    db.batch(&rows[10_000..]).await?;
+    db.sync().await?;
+    db.info().await?.bytes as usize
+  };
+
+  // a budget that fits every tree serves the second query from the cache
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .tree_cache_bytes(bytes)
+    .build()
+    .await?;
+  let (first,second) = misses(&mut db).await?;
+  assert![first > 1, "expected several trees, read {}", first];
+  assert_eq![second, 0];
+
+  // trees larger than the budget are never cached, even with room for more trees
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .tree_cache_size(10_000)
+    .tree_cache_bytes(1)
+    .build()
+    .await?;
+  let (first,second) = misses(&mut db).await?;
+  assert_eq![first, second];
+
+  // a budget for about one tree at a time evicts trees during each query
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .tree_cache_bytes(bytes/first as usize)
+    .build()
+    .await?;
+  let (first,second) = misses(&mut db).await?;
+  assert![second > first/2, "expected evictions, but {} of {} trees stayed cached",
+    first - second, first];
+
+  // the count limit still applies without a byte limit
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .tree_cache_size(1)
+    .build()
+    .await?;
+  let (first,second) = misses(&mut db).await?;
+  assert![second >= first - 1];
+  Ok(())
+}
+
+// cache misses of two queries over every row
+async fn misses(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<(u64,u64),Error> {
+  assert_eq![count(db).await?, 20_000];
+  let first = db.stats().cache_misses;
+  assert_eq![count(db).await?, 20_000];
+  Ok((first, db.stats().cache_misses - first))
+}
+
+async fn count(db: &mut DB<impl eyros::RA,T,P,V>) -> Result<usize,Error> {
+  let mut stream = db.query(&((-1.0,-1.0),(1.0,1.0))).await?;
+  let mut n = 0;
+  while let Some(result) = stream.next().await {
+    result?;
+    n += 1;
+  }
+  Ok(n)
+}
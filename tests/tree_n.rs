@@ -0,0 +1,96 @@
+use eyros::{Coord,Row,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+
+use std::collections::HashSet;
+
+mod common;
+use common::{ids,contains};
+
+type P = Vec<Coord<f32>>;
+type V = u32;
+
+const DIMS: usize = 12;
+
+#[async_std::test]
+async fn tree_n() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..20_000).map(|i| {
+    let point = (0..DIMS).map(|d| {
+      let x: f32 = r.read::<f32>()*2.0-1.0;
+      if d % 3 == 0 {
+        Coord::Interval(x, x + r.read::<f32>().powf(32.0)*(1.0-x))
+      } else {
+        Coord::Scalar(x)
+      }
+    }).collect::<P>();
+    Row::Insert(point, i as u32)
+  }).collect();
+  let bboxes: Vec<(Vec<f32>,Vec<f32>)> = vec![
+    (vec![-1.0;DIMS],vec![1.0;DIMS]),
+    ((0..DIMS).map(|d| if d < 2 { -0.5 } else { -1.0 }).collect(),
+      (0..DIMS).map(|d| if d < 2 { 0.5 } else { 1.0 }).collect()),
+    ((0..DIMS).map(|d| if d % 4 == 0 { 0.1 } else { -1.0 }).collect(),
+      (0..DIMS).map(|d| if d % 4 == 0 { 0.9 } else { 1.0 }).collect()),
+  ];
+  let deletes: Vec<Row<P,V>> = rows[0..500].iter().map(|row| match row {
+    Row::Insert(p,v) => Row::Delete(p.clone(),*v),
+    _ => panic!["unexpected row type"],
+  }).collect();
+  {
+    let mut db = eyros::open_from_path_n(dir.path()).await?;
+    db.batch(&rows[0..10_000]).await?;
+    db.batch(&rows[10_000..]).await?;
+    db.batch(&deletes).await?;
+    db.sync().await?;
+    for bbox in bboxes.iter() {
+      assert_eq![ids(db.query(bbox).await?).await?, expected(&rows[500..], bbox), "bbox={:?}", bbox];
+    }
+  }
+  {
+    let mut db = eyros::open_from_path_n(dir.path()).await?;
+    for bbox in bboxes.iter() {
+      assert_eq![ids(db.query(bbox).await?).await?, expected(&rows[500..], bbox),
+        "after reopen bbox={:?}", bbox];
+    }
+    let short = vec![Row::Insert(vec![Coord::Scalar(0.0);DIMS-1], 9000)];
+    assert![db.batch(&short).await.is_err(), "points must match the dimensions in meta"];
+    let bbox = (vec![-1.0;DIMS+1],vec![1.0;DIMS+1]);
+    assert![db.query(&bbox).await.is_err(), "bbox must match the dimensions in meta"];
+    let empty: Vec<Row<P,V>> = vec![Row::Insert(vec![], 9001)];
+    assert![db.batch(&empty).await.is_err(), "points must have dimensions"];
+  }
+  Ok(())
+}
+
+#[async_std::test]
+async fn failed_first_batch() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db = eyros::open_from_path_n(dir.path()).await?;
+  // the delete of a missing id fails the batch, which must not fix the number of dimensions
+  let rows: Vec<Row<P,V>> = vec![
+    Row::Insert(vec![Coord::Scalar(0.0);3], 1),
+    Row::Delete(vec![Coord::Scalar(0.0);3], 2),
+  ];
+  assert![db.batch(&rows).await.is_err(), "missing ids fail the batch"];
+  db.batch(&[Row::Insert(vec![Coord::Scalar(0.0);DIMS], 3)]).await?;
+  db.sync().await?;
+  let mut db = eyros::open_from_path_n(dir.path()).await?;
+  let bbox: (Vec<f32>,Vec<f32>) = (vec![-1.0;DIMS],vec![1.0;DIMS]);
+  assert_eq![ids(db.query(&bbox).await?).await?, vec![3].into_iter().collect()];
+  Ok(())
+}
+
+fn expected(rows: &[Row<P,V>], bbox: &(Vec<f32>,Vec<f32>)) -> HashSet<V> {
+  rows.iter().filter_map(|row| match row {
+    Row::Insert(p,v) => {
+      if p.iter().enumerate().all(|(d,c)| contains(&bbox.0[d], &bbox.1[d], c)) {
+        Some(*v)
+      } else {
+        None
+      }
+    },
+    _ => None,
+  }).collect()
+}
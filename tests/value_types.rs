@@ -0,0 +1,27 @@
+use eyros::{Coord,Row,Error};
+use tempfile::Builder as Tmpfile;
+use async_std::prelude::*;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = (u32,[u8;4],bool);
+
+#[async_std::test]
+async fn tuple_array_values() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db = eyros::open_from_path2(dir.path()).await?;
+  let rows: Vec<Row<P,V>> = (0..500).map(|i| {
+    let x = (i as f32)/500.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(-x)), (i, [i as u8,1,2,3], i % 2 == 0))
+  }).collect();
+  db.batch(&rows).await?;
+  db.batch(&[Row::Delete((Coord::Scalar(0.0),Coord::Scalar(0.0)), (0,[0,1,2,3],true))]).await?;
+  let mut stream = db.query(&((0.0,-0.1),(0.1,0.0))).await?;
+  let mut results = vec![];
+  while let Some(result) = stream.next().await {
+    results.push(result?.1);
+  }
+  results.sort();
+  let expected: Vec<V> = (1..=50).map(|i| (i, [i as u8,1,2,3], i % 2 == 0)).collect();
+  assert_eq![results, expected];
+  Ok(())
+}
@@ -0,0 +1,51 @@
+use eyros::{Setup,DB,Tree2,TreeRef,Row,Coord,Problem,Error};
+use random::{Source,default as rand};
+use tempfile::Builder as Tmpfile;
+
+type P = (Coord<f32>,Coord<f32>);
+type V = u32;
+type T = Tree2<f32,f32,V>;
+
+#[async_std::test]
+async fn verify() -> Result<(),Error> {
+  let dir = Tmpfile::new().prefix("eyros").tempdir()?;
+  let mut db: DB<_,T,P,V> = Setup::from_path(dir.path())
+    .max_records(500)
+    .build()
+    .await?;
+  let mut r = rand().seed([13,12]);
+  let rows: Vec<Row<P,V>> = (0..20_000).map(|i| {
+    let x = r.read::<f32>()*2.0-1.0;
+    let y = r.read::<f32>()*2.0-1.0;
+    Row::Insert((Coord::Scalar(x),Coord::Scalar(y)), i)
+  }).collect();
+  for batch in rows.chunks(5_000) {
+    db.batch(batch).await?;
+  }
+  db.sync().await?;
+
+  let info = db.info().await?;
+  let v = db.verify().await?;
+  assert![v.is_ok(), "{:?}", v.problems];
+  assert_eq![v.trees, info.trees];
+  assert_eq![v.records, 20_000];
+
+  {
+    let mut meta = db.meta.write().await;
+    // shrink the bounds of the first root so that some of its records fall outside
+    let root = meta.roots.iter_mut().find_map(|r| r.as_mut()).unwrap();
+    root.bounds = (Coord::Interval(-0.5,0.5),Coord::Interval(-0.5,0.5));
+    let next_tree = meta.next_tree;
+    meta.roots.push(Some(TreeRef {
+      id: next_tree + 100,
+      bounds: (Coord::Interval(0.0,1.0),Coord::Interval(0.0,1.0)),
+    }));
+  }
+  let v = db.verify().await?;
+  assert![!v.is_ok()];
+  assert![v.problems.iter().any(|p| matches![p, Problem::OutOfBounds { .. }])];
+  assert![v.problems.iter().any(|p| matches![p, Problem::Unreadable { .. }])];
+  assert![v.problems.iter().any(|p| matches![p, Problem::UnallocatedId { .. }])];
+  assert![!v.problems.iter().any(|p| matches![p, Problem::InvalidPoint { .. }])];
+  Ok(())
+}